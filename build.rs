@@ -1,4 +1,17 @@
 fn main() {
+    println!("cargo:rustc-check-cfg=cfg(pure_rust)");
+    println!("cargo:rerun-if-changed=src/xdiff/");
+
+    // The Rust port of xdiff stands in for the C sources when asked to, and
+    // on WebAssembly, where there is no C toolchain or libc to build them
+    // with.
+    let wasm = std::env::var("CARGO_CFG_TARGET_FAMILY")
+        .is_ok_and(|family| family.split(',').any(|family| family == "wasm"));
+    if std::env::var_os("CARGO_FEATURE_PURE_RUST").is_some() || wasm {
+        println!("cargo:rustc-cfg=pure_rust");
+        return;
+    }
+
     let mut build = cc::Build::new();
 
     // Add xdiff source directory to include path
@@ -20,5 +33,4 @@ fn main() {
     build.compile("xdiff");
 
     println!("cargo:rustc-link-lib=static=xdiff");
-    println!("cargo:rerun-if-changed=src/xdiff/");
 }
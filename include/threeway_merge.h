@@ -0,0 +1,297 @@
+/* Generated by `cargo xtask capi-header` from src/capi.rs. */
+
+#ifndef THREEWAY_MERGE_H
+#define THREEWAY_MERGE_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/*
+ Version of this API, raised on every incompatible change.
+ */
+#define THREEWAY_MERGE_ABI_VERSION 1
+
+#define THREEWAY_MERGE_OK 0
+
+#define THREEWAY_MERGE_ERROR_INTERNAL 1
+
+#define THREEWAY_MERGE_ERROR_INVALID_INPUT 2
+
+#define THREEWAY_MERGE_ERROR_BINARY 3
+
+#define THREEWAY_MERGE_ERROR_OUT_OF_MEMORY 4
+
+#define THREEWAY_MERGE_ERROR_IO 5
+
+#define THREEWAY_MERGE_ERROR_LIMIT_EXCEEDED 6
+
+#define THREEWAY_MERGE_STYLE_MERGE 0
+
+#define THREEWAY_MERGE_STYLE_DIFF3 1
+
+#define THREEWAY_MERGE_STYLE_ZDIFF3 2
+
+#define THREEWAY_MERGE_ALGORITHM_MYERS 0
+
+#define THREEWAY_MERGE_ALGORITHM_MINIMAL 1
+
+#define THREEWAY_MERGE_ALGORITHM_PATIENCE 2
+
+#define THREEWAY_MERGE_ALGORITHM_HISTOGRAM 3
+
+#define THREEWAY_MERGE_FAVOR_NONE 0
+
+#define THREEWAY_MERGE_FAVOR_OURS 1
+
+#define THREEWAY_MERGE_FAVOR_THEIRS 2
+
+#define THREEWAY_MERGE_FAVOR_UNION 3
+
+#define THREEWAY_MERGE_FAVOR_UNION_DEDUP 4
+
+#define THREEWAY_MERGE_FAVOR_UNION_SORTED 5
+
+#define THREEWAY_MERGE_LEVEL_MINIMAL 0
+
+#define THREEWAY_MERGE_LEVEL_EAGER 1
+
+#define THREEWAY_MERGE_LEVEL_ZEALOUS 2
+
+#define THREEWAY_MERGE_LEVEL_ZEALOUS_ALNUM 3
+
+#define THREEWAY_MERGE_BINARY_ERROR 0
+
+#define THREEWAY_MERGE_BINARY_TAKE_OURS 1
+
+#define THREEWAY_MERGE_BINARY_TAKE_THEIRS 2
+
+#define THREEWAY_MERGE_BINARY_CONFLICT 3
+
+#define THREEWAY_MERGE_EOL_PRESERVE 0
+
+#define THREEWAY_MERGE_EOL_LF 1
+
+#define THREEWAY_MERGE_EOL_CRLF 2
+
+#define THREEWAY_MERGE_EOL_MATCH_OURS 3
+
+/*
+ Merge options, starting out as `MergeOptions::default()`.
+ */
+typedef struct ThreewayMergeOptions ThreewayMergeOptions;
+
+/*
+ The outcome of a merge: the merged content, or an error message.
+ */
+typedef struct ThreewayMergeResult ThreewayMergeResult;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ Returns `THREEWAY_MERGE_ABI_VERSION` as built into the library, for
+ checking against the header at run time.
+ */
+uint32_t threeway_merge_abi_version(void);
+
+/*
+ Creates options with the defaults of `git merge-file`. Free them with
+ `threeway_merge_options_free`.
+ */
+struct ThreewayMergeOptions *threeway_merge_options_new(void);
+
+/*
+ Frees options. Null is ignored.
+
+ # Safety
+
+ `options` must be null or come from `threeway_merge_options_new`, and
+ must not be used afterwards.
+ */
+void threeway_merge_options_free(struct ThreewayMergeOptions *options);
+
+/*
+ Sets the conflict style to one of `THREEWAY_MERGE_STYLE_*`.
+
+ # Safety
+
+ `options` must be null or valid options.
+ */
+int32_t threeway_merge_options_set_style(struct ThreewayMergeOptions *options, uint32_t style);
+
+/*
+ Sets the diff algorithm to one of `THREEWAY_MERGE_ALGORITHM_*`.
+
+ # Safety
+
+ `options` must be null or valid options.
+ */
+int32_t threeway_merge_options_set_algorithm(struct ThreewayMergeOptions *options,
+                                             uint32_t algorithm);
+
+/*
+ Sets how conflicts are resolved to one of `THREEWAY_MERGE_FAVOR_*`;
+ `THREEWAY_MERGE_FAVOR_NONE` leaves them marked.
+
+ # Safety
+
+ `options` must be null or valid options.
+ */
+int32_t threeway_merge_options_set_favor(struct ThreewayMergeOptions *options, uint32_t favor);
+
+/*
+ Sets the merge level to one of `THREEWAY_MERGE_LEVEL_*`.
+
+ # Safety
+
+ `options` must be null or valid options.
+ */
+int32_t threeway_merge_options_set_level(struct ThreewayMergeOptions *options, uint32_t level);
+
+/*
+ Sets the policy for binary inputs to one of `THREEWAY_MERGE_BINARY_*`.
+
+ # Safety
+
+ `options` must be null or valid options.
+ */
+int32_t threeway_merge_options_set_binary(struct ThreewayMergeOptions *options, uint32_t policy);
+
+/*
+ Sets the line-ending policy to one of `THREEWAY_MERGE_EOL_*`.
+
+ # Safety
+
+ `options` must be null or valid options.
+ */
+int32_t threeway_merge_options_set_eol(struct ThreewayMergeOptions *options, uint32_t policy);
+
+/*
+ Sets the length of conflict markers; 0 means the default of 7.
+
+ # Safety
+
+ `options` must be null or valid options.
+ */
+int32_t threeway_merge_options_set_marker_size(struct ThreewayMergeOptions *options,
+                                               size_t marker_size);
+
+/*
+ Turns resolving conflicts in sorted blocks on or off.
+
+ # Safety
+
+ `options` must be null or valid options.
+ */
+int32_t threeway_merge_options_set_sorted_blocks(struct ThreewayMergeOptions *options,
+                                                 bool sorted_blocks);
+
+/*
+ Sets the labels after the conflict markers. Each is a NUL-terminated
+ UTF-8 string, or null for no label; they are copied.
+
+ # Safety
+
+ `options` must be null or valid options, and each label null or a
+ NUL-terminated string.
+ */
+int32_t threeway_merge_options_set_labels(struct ThreewayMergeOptions *options,
+                                          const char *base,
+                                          const char *ours,
+                                          const char *theirs);
+
+/*
+ Sets the limits of `MergeLimits`. 0 leaves a limit unset.
+
+ # Safety
+
+ `options` must be null or valid options.
+ */
+int32_t threeway_merge_options_set_limits(struct ThreewayMergeOptions *options,
+                                          size_t max_input_bytes,
+                                          size_t max_lines,
+                                          uint64_t timeout_ms);
+
+/*
+ Merges three inputs, which need not be UTF-8 or NUL-terminated, with the
+ checks and fast paths of `merge_strings`. Null `options` means the
+ defaults.
+
+ Returns the status and stores a result in `*result` either way: the
+ merged content on `THREEWAY_MERGE_OK`, an error message otherwise. Free
+ it with `threeway_merge_result_free`.
+
+ # Safety
+
+ Each input must be null with a length of 0, or point to `len` readable
+ bytes. `options` must be null or valid options, and `result` must be
+ null or writable.
+ */
+int32_t threeway_merge(const uint8_t *base,
+                       size_t base_len,
+                       const uint8_t *ours,
+                       size_t ours_len,
+                       const uint8_t *theirs,
+                       size_t theirs_len,
+                       const struct ThreewayMergeOptions *options,
+                       struct ThreewayMergeResult **result);
+
+/*
+ The status `threeway_merge` returned with this result.
+
+ # Safety
+
+ `result` must be a result from `threeway_merge`.
+ */
+int32_t threeway_merge_result_status(const struct ThreewayMergeResult *result);
+
+/*
+ The merged content, NUL-terminated, with its length without the NUL
+ stored in `*len` unless `len` is null. Null if the merge failed. The
+ content may itself contain NULs when a binary policy took a side.
+
+ # Safety
+
+ `result` must be a result from `threeway_merge`. The content lives as
+ long as the result.
+ */
+const char *threeway_merge_result_content(const struct ThreewayMergeResult *result, size_t *len);
+
+/*
+ The number of conflicts left in the content.
+
+ # Safety
+
+ `result` must be a result from `threeway_merge`.
+ */
+size_t threeway_merge_result_conflicts(const struct ThreewayMergeResult *result);
+
+/*
+ The error message, NUL-terminated, or null if the merge succeeded.
+
+ # Safety
+
+ `result` must be a result from `threeway_merge`. The message lives as
+ long as the result.
+ */
+const char *threeway_merge_result_error(const struct ThreewayMergeResult *result);
+
+/*
+ Frees a result. Null is ignored.
+
+ # Safety
+
+ `result` must be null or a result from `threeway_merge`, and must not
+ be used afterwards.
+ */
+void threeway_merge_result_free(struct ThreewayMergeResult *result);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* THREEWAY_MERGE_H */
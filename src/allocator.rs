@@ -0,0 +1,183 @@
+//! Allocation for xdiff.
+//!
+//! `git-xdiff.h` maps `xdl_malloc` and friends to the functions exported
+//! here, so xdiff's memory comes from Rust's global allocator, or from the
+//! allocator installed with [`with_xdiff_allocator`] on the calling thread.
+//! C's `free` and `realloc` do not pass the size along, so each block
+//! starts with a header recording its layout and the allocator it came
+//! from.
+//!
+//! The Rust backend allocates through the global allocator like the rest
+//! of the crate, so there the installed allocator goes unused.
+
+use std::alloc::GlobalAlloc;
+#[cfg(not(pure_rust))]
+use std::alloc::Layout;
+use std::cell::Cell;
+use std::mem;
+#[cfg(not(pure_rust))]
+use std::os::raw::c_void;
+#[cfg(not(pure_rust))]
+use std::ptr;
+
+/// Alignment of the blocks handed to C, enough for any type xdiff stores.
+#[cfg(not(pure_rust))]
+const ALIGN: usize = 16;
+
+#[cfg(not(pure_rust))]
+#[repr(C)]
+struct Header {
+    size: usize,
+    allocator: Allocator,
+}
+
+#[cfg(not(pure_rust))]
+const HEADER: usize = mem::size_of::<Header>().next_multiple_of(ALIGN);
+
+type Allocator = Option<*const (dyn GlobalAlloc + 'static)>;
+
+thread_local! {
+    /// The allocator installed by `with_xdiff_allocator`; none means the
+    /// global allocator.
+    static CURRENT: Cell<Allocator> = const { Cell::new(None) };
+}
+
+/// Runs `f` with xdiff's allocations on this thread served by `allocator`
+/// instead of the global allocator.
+///
+/// Every block xdiff allocates during a merge is freed before the merge
+/// returns, so an arena that lives for one request can back the merges
+/// made while handling it. Merges on other threads are not affected.
+///
+/// ```
+/// use std::alloc::System;
+/// use threeway_merge::{merge_strings, with_xdiff_allocator, MergeOptions};
+///
+/// let result = with_xdiff_allocator(&System, || {
+///     merge_strings("a\n", "b\n", "a\n", &MergeOptions::default())
+/// })?;
+/// assert_eq!(result.content, "b\n");
+/// # Ok::<(), threeway_merge::MergeError>(())
+/// ```
+pub fn with_xdiff_allocator<A: GlobalAlloc, R>(allocator: &A, f: impl FnOnce() -> R) -> R {
+    /// Restores the previous allocator, also when `f` panics.
+    struct Restore(Allocator);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            CURRENT.with(|current| current.set(self.0));
+        }
+    }
+
+    let allocator: *const (dyn GlobalAlloc + '_) = allocator;
+    // SAFETY: only the lifetime is erased. The pointer is used for blocks
+    // allocated while `f` runs, which xdiff frees before returning to it.
+    let allocator = unsafe {
+        mem::transmute::<*const (dyn GlobalAlloc + '_), *const (dyn GlobalAlloc + 'static)>(
+            allocator,
+        )
+    };
+    let _restore = Restore(CURRENT.with(|current| current.replace(Some(allocator))));
+    f()
+}
+
+#[cfg(not(pure_rust))]
+fn layout(size: usize) -> Option<Layout> {
+    Layout::from_size_align(size.checked_add(HEADER)?, ALIGN).ok()
+}
+
+/// Allocates `size` bytes after a header, zeroed if asked.
+#[cfg(not(pure_rust))]
+unsafe fn allocate(size: usize, zeroed: bool) -> *mut c_void {
+    let Some(layout) = layout(size) else {
+        return ptr::null_mut();
+    };
+    let allocator = CURRENT.with(Cell::get);
+    // SAFETY: `layout` is never zero-sized, and an installed allocator
+    // outlives the blocks it serves.
+    let block = unsafe {
+        match (allocator, zeroed) {
+            (Some(allocator), false) => (*allocator).alloc(layout),
+            (Some(allocator), true) => (*allocator).alloc_zeroed(layout),
+            (None, false) => std::alloc::alloc(layout),
+            (None, true) => std::alloc::alloc_zeroed(layout),
+        }
+    };
+    if block.is_null() {
+        return ptr::null_mut();
+    }
+    // SAFETY: the block has room for the header and is aligned for it.
+    unsafe {
+        (block as *mut Header).write(Header { size, allocator });
+        block.add(HEADER) as *mut c_void
+    }
+}
+
+/// The header of a block from `allocate`, and the start of the block.
+#[cfg(not(pure_rust))]
+unsafe fn header(ptr: *mut c_void) -> (*mut u8, Header) {
+    // SAFETY: `ptr` was returned by `allocate`, HEADER bytes into its block.
+    unsafe {
+        let block = (ptr as *mut u8).sub(HEADER);
+        (block, (block as *const Header).read())
+    }
+}
+
+#[cfg(not(pure_rust))]
+#[unsafe(no_mangle)]
+unsafe extern "C" fn threeway_merge_xdl_malloc(size: usize) -> *mut c_void {
+    unsafe { allocate(size, false) }
+}
+
+#[cfg(not(pure_rust))]
+#[unsafe(no_mangle)]
+unsafe extern "C" fn threeway_merge_xdl_calloc(count: usize, size: usize) -> *mut c_void {
+    match count.checked_mul(size) {
+        Some(size) => unsafe { allocate(size, true) },
+        None => ptr::null_mut(),
+    }
+}
+
+#[cfg(not(pure_rust))]
+#[unsafe(no_mangle)]
+unsafe extern "C" fn threeway_merge_xdl_realloc(ptr: *mut c_void, size: usize) -> *mut c_void {
+    if ptr.is_null() {
+        return unsafe { allocate(size, false) };
+    }
+    let (Some(new_layout), (block, old)) = (layout(size), unsafe { header(ptr) }) else {
+        return ptr::null_mut();
+    };
+    let old_layout = layout(old.size).expect("layout of an allocated block");
+    // SAFETY: the block was allocated with `old_layout` by `old.allocator`,
+    // and the new size does not overflow once aligned.
+    let block = unsafe {
+        match old.allocator {
+            Some(allocator) => (*allocator).realloc(block, old_layout, new_layout.size()),
+            None => std::alloc::realloc(block, old_layout, new_layout.size()),
+        }
+    };
+    if block.is_null() {
+        return ptr::null_mut();
+    }
+    unsafe {
+        (*(block as *mut Header)).size = size;
+        block.add(HEADER) as *mut c_void
+    }
+}
+
+#[cfg(not(pure_rust))]
+#[unsafe(no_mangle)]
+unsafe extern "C" fn threeway_merge_xdl_free(ptr: *mut c_void) {
+    if ptr.is_null() {
+        return;
+    }
+    let (block, header) = unsafe { header(ptr) };
+    let layout = layout(header.size).expect("layout of an allocated block");
+    // SAFETY: as in `threeway_merge_xdl_realloc`.
+    unsafe {
+        match header.allocator {
+            Some(allocator) => (*allocator).dealloc(block, layout),
+            None => std::alloc::dealloc(block, layout),
+        }
+    }
+}
@@ -0,0 +1,445 @@
+//! Per-path merge selection from `.gitattributes` files.
+//!
+//! Only the attributes that affect a merge are interpreted (`merge`,
+//! `conflict-marker-size`, `eol`), but every attribute is parsed so callers can
+//! inspect the rest through [`GitAttributes::attributes_for`].
+
+use crate::config::{self, GitConfig};
+use crate::types::*;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where an attributes file lives, which decides its precedence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttributeSource {
+    /// The system-wide attributes file.
+    System,
+    /// The file named by `core.attributesFile`.
+    Global,
+    /// A `.gitattributes` file in the given directory, relative to the work
+    /// tree root (`""` for the root). Deeper files take precedence.
+    Tree(String),
+    /// `$GIT_DIR/info/attributes`, which overrides everything else.
+    Info,
+}
+
+/// The state of one attribute for a path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttributeValue {
+    /// `attr`
+    Set,
+    /// `-attr`
+    Unset,
+    /// `attr=value`
+    Value(String),
+}
+
+/// How a path should be merged according to its attributes.
+#[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
+pub enum MergeDecision {
+    /// Run a text merge with these options.
+    Text(MergeOptions),
+    /// `merge=binary` or `-merge`: do not text-merge. Git keeps "ours" and
+    /// reports a conflict, as [`BinaryPolicy::Conflict`] does.
+    Binary,
+    /// `merge=<name>` naming a custom driver; see [`GitConfig::merge_driver`].
+    Driver(String),
+}
+
+#[derive(Debug, Clone)]
+enum Assignment {
+    Value(AttributeValue),
+    /// `!attr`
+    Unspecified,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: String,
+    /// Patterns without a slash match the basename at any depth.
+    basename_only: bool,
+    assignments: Vec<(String, Assignment)>,
+}
+
+#[derive(Debug, Clone)]
+struct AttributeFile {
+    source: AttributeSource,
+    rules: Vec<Rule>,
+}
+
+/// A set of attribute files, queried by path.
+#[derive(Debug, Clone, Default)]
+pub struct GitAttributes {
+    files: Vec<AttributeFile>,
+    macros: BTreeMap<String, Vec<(String, Assignment)>>,
+}
+
+impl GitAttributes {
+    pub fn new() -> Self {
+        let mut attributes = Self::default();
+        // Built-in macro, as in git
+        attributes.macros.insert(
+            "binary".to_string(),
+            ["diff", "merge", "text"]
+                .iter()
+                .map(|name| (name.to_string(), Assignment::Value(AttributeValue::Unset)))
+                .collect(),
+        );
+        attributes
+    }
+
+    /// Reads the attribute files git would consult for `path` (relative to
+    /// `work_tree`): the system file unless `GIT_ATTR_NOSYSTEM` is set,
+    /// `core.attributesFile` (or its XDG default), every `.gitattributes` from
+    /// the root down to the path's directory, and `info/attributes` in the
+    /// common git directory, so linked worktrees share the main repository's.
+    pub fn load_for_path(
+        work_tree: &Path,
+        path: &str,
+        config: Option<&GitConfig>,
+    ) -> Result<Self, MergeError> {
+        let mut attributes = Self::new();
+
+        if env::var_os("GIT_ATTR_NOSYSTEM").is_none() {
+            attributes.add_file(AttributeSource::System, Path::new("/etc/gitattributes"))?;
+        }
+
+        let global = match config.and_then(|config| config.get("core.attributesFile")) {
+            Some(file) => match file.strip_prefix("~/") {
+                Some(rest) => env::var_os("HOME").map(|home| PathBuf::from(home).join(rest)),
+                None => Some(PathBuf::from(file)),
+            },
+            None => env::var_os("XDG_CONFIG_HOME")
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from)
+                .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+                .map(|dir| dir.join("git").join("attributes")),
+        };
+        if let Some(global) = global {
+            attributes.add_file(AttributeSource::Global, &global)?;
+        }
+
+        let mut dir = String::new();
+        attributes.add_file(
+            AttributeSource::Tree(dir.clone()),
+            &work_tree.join(".gitattributes"),
+        )?;
+        if let Some((parents, _)) = path.rsplit_once('/') {
+            for component in parents.split('/').filter(|c| !c.is_empty()) {
+                if !dir.is_empty() {
+                    dir.push('/');
+                }
+                dir.push_str(component);
+                attributes.add_file(
+                    AttributeSource::Tree(dir.clone()),
+                    &work_tree.join(&dir).join(".gitattributes"),
+                )?;
+            }
+        }
+
+        attributes.add_file(
+            AttributeSource::Info,
+            &config::common_dir(&config::git_dir(work_tree))
+                .join("info")
+                .join("attributes"),
+        )?;
+        Ok(attributes)
+    }
+
+    fn add_file(&mut self, source: AttributeSource, path: &Path) -> Result<(), MergeError> {
+        match fs::read_to_string(path) {
+            Ok(text) => {
+                self.add(source, &text);
+                Ok(())
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(MergeError::Io(format!("{}: {}", path.display(), err))),
+        }
+    }
+
+    /// Adds the contents of an attributes file. Malformed lines are skipped,
+    /// as git does.
+    pub fn add(&mut self, source: AttributeSource, text: &str) {
+        let mut rules = Vec::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (pattern, rest) = split_pattern(line);
+            let assignments = rest
+                .split_whitespace()
+                .filter_map(parse_assignment)
+                .collect();
+
+            if let Some(name) = pattern.strip_prefix("[attr]") {
+                // Macros may only be defined outside of nested .gitattributes
+                let top_level = !matches!(&source, AttributeSource::Tree(dir) if !dir.is_empty());
+                if top_level && !name.is_empty() {
+                    self.macros.insert(name.to_string(), assignments);
+                }
+                continue;
+            }
+            // Negative patterns are forbidden; trailing-slash patterns never
+            // match files.
+            if pattern.is_empty() || pattern.starts_with('!') || pattern.ends_with('/') {
+                continue;
+            }
+
+            rules.push(Rule {
+                basename_only: !pattern.contains('/'),
+                pattern: pattern.strip_prefix('/').unwrap_or(&pattern).to_string(),
+                assignments,
+            });
+        }
+        self.files.push(AttributeFile { source, rules });
+    }
+
+    /// Resolves every attribute that is specified for `path`.
+    pub fn attributes_for(&self, path: &str) -> BTreeMap<String, AttributeValue> {
+        let mut files: Vec<&AttributeFile> = self.files.iter().collect();
+        // Stable sort keeps insertion order within the same precedence.
+        files.sort_by_key(|file| match &file.source {
+            AttributeSource::System => (0, 0),
+            AttributeSource::Global => (1, 0),
+            AttributeSource::Tree(dir) if dir.is_empty() => (2, 0),
+            AttributeSource::Tree(dir) => (2, dir.split('/').count()),
+            AttributeSource::Info => (3, 0),
+        });
+
+        let mut resolved = BTreeMap::new();
+        for file in files {
+            let relative = match &file.source {
+                AttributeSource::Tree(dir) if !dir.is_empty() => {
+                    match path
+                        .strip_prefix(dir.as_str())
+                        .and_then(|p| p.strip_prefix('/'))
+                    {
+                        Some(relative) => relative,
+                        None => continue,
+                    }
+                }
+                _ => path,
+            };
+            let basename = relative.rsplit('/').next().unwrap_or(relative);
+
+            for rule in &file.rules {
+                let subject = if rule.basename_only {
+                    basename
+                } else {
+                    relative
+                };
+                if wildmatch(rule.pattern.as_bytes(), subject.as_bytes(), true) {
+                    self.apply(&mut resolved, &rule.assignments, 0);
+                }
+            }
+        }
+        resolved
+    }
+
+    fn apply(
+        &self,
+        resolved: &mut BTreeMap<String, AttributeValue>,
+        assignments: &[(String, Assignment)],
+        depth: usize,
+    ) {
+        for (name, assignment) in assignments {
+            match assignment {
+                Assignment::Value(value) => {
+                    if *value == AttributeValue::Set
+                        && depth < 8
+                        && let Some(expansion) = self.macros.get(name)
+                    {
+                        self.apply(resolved, expansion, depth + 1);
+                    }
+                    resolved.insert(name.clone(), value.clone());
+                }
+                Assignment::Unspecified => {
+                    resolved.remove(name);
+                }
+            }
+        }
+    }
+
+    /// Decides how `path` should be merged, starting from `options`.
+    ///
+    /// `merge=union` selects [`MergeFavor::Union`], `conflict-marker-size`
+    /// overrides `marker_size`, `eol=crlf`/`eol=lf` select the matching
+    /// [`EolPolicy`], and `merge=binary` or `-merge` opts out of text merging
+    /// altogether.
+    pub fn merge_decision(&self, path: &str, options: &MergeOptions) -> MergeDecision {
+        let attributes = self.attributes_for(path);
+        let mut options = options.clone();
+
+        if let Some(AttributeValue::Value(size)) = attributes.get("conflict-marker-size")
+            && let Ok(size) = size.parse::<usize>()
+            && size > 0
+        {
+            options.marker_size = size;
+        }
+
+        match attributes.get("eol") {
+            Some(AttributeValue::Value(eol)) if eol == "crlf" => options.eol = EolPolicy::Crlf,
+            Some(AttributeValue::Value(eol)) if eol == "lf" => options.eol = EolPolicy::Lf,
+            _ => {}
+        }
+
+        match attributes.get("merge") {
+            None | Some(AttributeValue::Set) => MergeDecision::Text(options),
+            Some(AttributeValue::Unset) => MergeDecision::Binary,
+            Some(AttributeValue::Value(driver)) => match driver.as_str() {
+                "text" => MergeDecision::Text(options),
+                "binary" => MergeDecision::Binary,
+                "union" => {
+                    options.favor = Some(MergeFavor::Union);
+                    MergeDecision::Text(options)
+                }
+                _ => MergeDecision::Driver(driver.clone()),
+            },
+        }
+    }
+}
+
+/// Splits a line into its pattern and the attribute list. Patterns may be
+/// C-style quoted to include whitespace.
+fn split_pattern(line: &str) -> (String, &str) {
+    if let Some(quoted) = line.strip_prefix('"') {
+        let mut pattern = String::new();
+        let mut chars = quoted.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return (pattern, &quoted[i + 1..]),
+                '\\' => match chars.next() {
+                    Some((_, 't')) => pattern.push('\t'),
+                    Some((_, 'n')) => pattern.push('\n'),
+                    Some((_, c)) => pattern.push(c),
+                    None => break,
+                },
+                c => pattern.push(c),
+            }
+        }
+        return (String::new(), "");
+    }
+    match line.split_once(char::is_whitespace) {
+        Some((pattern, rest)) => (pattern.to_string(), rest),
+        None => (line.to_string(), ""),
+    }
+}
+
+fn parse_assignment(token: &str) -> Option<(String, Assignment)> {
+    let (name, assignment) = if let Some(name) = token.strip_prefix('-') {
+        (name, Assignment::Value(AttributeValue::Unset))
+    } else if let Some(name) = token.strip_prefix('!') {
+        (name, Assignment::Unspecified)
+    } else if let Some((name, value)) = token.split_once('=') {
+        (
+            name,
+            Assignment::Value(AttributeValue::Value(value.to_string())),
+        )
+    } else {
+        (token, Assignment::Value(AttributeValue::Set))
+    };
+
+    let valid = !name.is_empty()
+        && !name.starts_with('-')
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_'));
+    valid.then(|| (name.to_string(), assignment))
+}
+
+/// Glob matching with git's pathname semantics: `*` and `?` stop at `/`,
+/// while `**/`, `/**/` and a trailing `/**` span directories.
+fn wildmatch(pattern: &[u8], text: &[u8], segment_start: bool) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some(b'*') => {
+            if segment_start
+                && pattern.starts_with(b"**")
+                && (pattern.len() == 2 || pattern[2] == b'/')
+            {
+                if pattern.len() == 2 {
+                    return true;
+                }
+                let rest = &pattern[3..];
+                if wildmatch(rest, text, true) {
+                    return true;
+                }
+                return text
+                    .iter()
+                    .enumerate()
+                    .any(|(i, &b)| b == b'/' && wildmatch(rest, &text[i + 1..], true));
+            }
+
+            let rest = &pattern[pattern.iter().take_while(|&&b| b == b'*').count()..];
+            for i in 0..=text.len() {
+                if wildmatch(rest, &text[i..], false) {
+                    return true;
+                }
+                if i < text.len() && text[i] == b'/' {
+                    break;
+                }
+            }
+            false
+        }
+        Some(b'?') => match text.first() {
+            Some(&b) if b != b'/' => wildmatch(&pattern[1..], &text[1..], false),
+            _ => false,
+        },
+        Some(b'[') => {
+            let Some(&b) = text.first() else {
+                return false;
+            };
+            match match_class(&pattern[1..], b) {
+                Some((matched, consumed)) => {
+                    matched && b != b'/' && wildmatch(&pattern[1 + consumed..], &text[1..], false)
+                }
+                // An unterminated class is a literal '['
+                None => b == b'[' && wildmatch(&pattern[1..], &text[1..], false),
+            }
+        }
+        Some(b'\\') if pattern.len() > 1 => {
+            text.first() == Some(&pattern[1]) && wildmatch(&pattern[2..], &text[1..], false)
+        }
+        Some(&c) => text.first() == Some(&c) && wildmatch(&pattern[1..], &text[1..], c == b'/'),
+    }
+}
+
+/// Matches `b` against a bracket expression starting just after `[`.
+/// Returns whether it matched and how many pattern bytes the class used.
+fn match_class(class: &[u8], b: u8) -> Option<(bool, usize)> {
+    let mut i = 0;
+    let negated = matches!(class.first(), Some(b'!' | b'^'));
+    if negated {
+        i += 1;
+    }
+
+    let mut matched = false;
+    let mut first = true;
+    while i < class.len() {
+        let c = class[i];
+        if c == b']' && !first {
+            return Some((matched != negated, i + 1));
+        }
+        first = false;
+
+        let (lo, next) = if c == b'\\' && i + 1 < class.len() {
+            (class[i + 1], i + 2)
+        } else {
+            (c, i + 1)
+        };
+        if next + 1 < class.len() && class[next] == b'-' && class[next + 1] != b']' {
+            let hi = class[next + 1];
+            matched |= lo <= b && b <= hi;
+            i = next + 2;
+        } else {
+            matched |= lo == b;
+            i = next;
+        }
+    }
+    None
+}
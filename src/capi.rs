@@ -0,0 +1,507 @@
+//! C API for consumers outside Rust.
+//!
+//! Options and results are opaque handles, created and freed here. Enum
+//! options are passed as the `THREEWAY_MERGE_*` constants rather than Rust
+//! enums, so out-of-range values from C are rejected instead of being
+//! undefined behaviour. `include/threeway_merge.h` is generated from this
+//! file with `cargo xtask capi-header`.
+
+use crate::merge::merge_into_vec;
+use crate::types::*;
+use std::ffi::{CStr, CString, c_char};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::time::Duration;
+
+/// Version of this API, raised on every incompatible change.
+pub const THREEWAY_MERGE_ABI_VERSION: u32 = 1;
+
+pub const THREEWAY_MERGE_OK: i32 = 0;
+pub const THREEWAY_MERGE_ERROR_INTERNAL: i32 = 1;
+pub const THREEWAY_MERGE_ERROR_INVALID_INPUT: i32 = 2;
+pub const THREEWAY_MERGE_ERROR_BINARY: i32 = 3;
+pub const THREEWAY_MERGE_ERROR_OUT_OF_MEMORY: i32 = 4;
+pub const THREEWAY_MERGE_ERROR_IO: i32 = 5;
+pub const THREEWAY_MERGE_ERROR_LIMIT_EXCEEDED: i32 = 6;
+
+pub const THREEWAY_MERGE_STYLE_MERGE: u32 = 0;
+pub const THREEWAY_MERGE_STYLE_DIFF3: u32 = 1;
+pub const THREEWAY_MERGE_STYLE_ZDIFF3: u32 = 2;
+
+pub const THREEWAY_MERGE_ALGORITHM_MYERS: u32 = 0;
+pub const THREEWAY_MERGE_ALGORITHM_MINIMAL: u32 = 1;
+pub const THREEWAY_MERGE_ALGORITHM_PATIENCE: u32 = 2;
+pub const THREEWAY_MERGE_ALGORITHM_HISTOGRAM: u32 = 3;
+
+pub const THREEWAY_MERGE_FAVOR_NONE: u32 = 0;
+pub const THREEWAY_MERGE_FAVOR_OURS: u32 = 1;
+pub const THREEWAY_MERGE_FAVOR_THEIRS: u32 = 2;
+pub const THREEWAY_MERGE_FAVOR_UNION: u32 = 3;
+pub const THREEWAY_MERGE_FAVOR_UNION_DEDUP: u32 = 4;
+pub const THREEWAY_MERGE_FAVOR_UNION_SORTED: u32 = 5;
+
+pub const THREEWAY_MERGE_LEVEL_MINIMAL: u32 = 0;
+pub const THREEWAY_MERGE_LEVEL_EAGER: u32 = 1;
+pub const THREEWAY_MERGE_LEVEL_ZEALOUS: u32 = 2;
+pub const THREEWAY_MERGE_LEVEL_ZEALOUS_ALNUM: u32 = 3;
+
+pub const THREEWAY_MERGE_BINARY_ERROR: u32 = 0;
+pub const THREEWAY_MERGE_BINARY_TAKE_OURS: u32 = 1;
+pub const THREEWAY_MERGE_BINARY_TAKE_THEIRS: u32 = 2;
+pub const THREEWAY_MERGE_BINARY_CONFLICT: u32 = 3;
+
+pub const THREEWAY_MERGE_EOL_PRESERVE: u32 = 0;
+pub const THREEWAY_MERGE_EOL_LF: u32 = 1;
+pub const THREEWAY_MERGE_EOL_CRLF: u32 = 2;
+pub const THREEWAY_MERGE_EOL_MATCH_OURS: u32 = 3;
+
+/// Merge options, starting out as `MergeOptions::default()`.
+pub struct ThreewayMergeOptions {
+    options: MergeOptions,
+}
+
+/// The outcome of a merge: the merged content, or an error message.
+pub struct ThreewayMergeResult {
+    status: i32,
+    /// The merged content followed by a NUL
+    content: Vec<u8>,
+    conflicts: usize,
+    error: Option<CString>,
+}
+
+/// Returns `THREEWAY_MERGE_ABI_VERSION` as built into the library, for
+/// checking against the header at run time.
+#[unsafe(no_mangle)]
+pub extern "C" fn threeway_merge_abi_version() -> u32 {
+    THREEWAY_MERGE_ABI_VERSION
+}
+
+/// Creates options with the defaults of `git merge-file`. Free them with
+/// `threeway_merge_options_free`.
+#[unsafe(no_mangle)]
+pub extern "C" fn threeway_merge_options_new() -> *mut ThreewayMergeOptions {
+    Box::into_raw(Box::new(ThreewayMergeOptions {
+        options: MergeOptions::default(),
+    }))
+}
+
+/// Frees options. Null is ignored.
+///
+/// # Safety
+///
+/// `options` must be null or come from `threeway_merge_options_new`, and
+/// must not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn threeway_merge_options_free(options: *mut ThreewayMergeOptions) {
+    if !options.is_null() {
+        drop(unsafe { Box::from_raw(options) });
+    }
+}
+
+/// Applies `value` with `set`, if `options` is not null and `parse` accepts
+/// the value.
+unsafe fn set_option<T>(
+    options: *mut ThreewayMergeOptions,
+    value: u32,
+    parse: fn(u32) -> Option<T>,
+    set: fn(&mut MergeOptions, T),
+) -> i32 {
+    let (Some(options), Some(value)) = (unsafe { options.as_mut() }, parse(value)) else {
+        return THREEWAY_MERGE_ERROR_INVALID_INPUT;
+    };
+    set(&mut options.options, value);
+    THREEWAY_MERGE_OK
+}
+
+/// Sets the conflict style to one of `THREEWAY_MERGE_STYLE_*`.
+///
+/// # Safety
+///
+/// `options` must be null or valid options.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn threeway_merge_options_set_style(
+    options: *mut ThreewayMergeOptions,
+    style: u32,
+) -> i32 {
+    let parse = |style| match style {
+        THREEWAY_MERGE_STYLE_MERGE => Some(MergeStyle::Normal),
+        THREEWAY_MERGE_STYLE_DIFF3 => Some(MergeStyle::Diff3),
+        THREEWAY_MERGE_STYLE_ZDIFF3 => Some(MergeStyle::ZealousDiff3),
+        _ => None,
+    };
+    unsafe {
+        set_option(options, style, parse, |options, style| {
+            options.style = style
+        })
+    }
+}
+
+/// Sets the diff algorithm to one of `THREEWAY_MERGE_ALGORITHM_*`.
+///
+/// # Safety
+///
+/// `options` must be null or valid options.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn threeway_merge_options_set_algorithm(
+    options: *mut ThreewayMergeOptions,
+    algorithm: u32,
+) -> i32 {
+    let parse = |algorithm| match algorithm {
+        THREEWAY_MERGE_ALGORITHM_MYERS => Some(DiffAlgorithm::Myers),
+        THREEWAY_MERGE_ALGORITHM_MINIMAL => Some(DiffAlgorithm::Minimal),
+        THREEWAY_MERGE_ALGORITHM_PATIENCE => Some(DiffAlgorithm::Patience),
+        THREEWAY_MERGE_ALGORITHM_HISTOGRAM => Some(DiffAlgorithm::Histogram),
+        _ => None,
+    };
+    unsafe {
+        set_option(options, algorithm, parse, |options, algorithm| {
+            options.algorithm = algorithm
+        })
+    }
+}
+
+/// Sets how conflicts are resolved to one of `THREEWAY_MERGE_FAVOR_*`;
+/// `THREEWAY_MERGE_FAVOR_NONE` leaves them marked.
+///
+/// # Safety
+///
+/// `options` must be null or valid options.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn threeway_merge_options_set_favor(
+    options: *mut ThreewayMergeOptions,
+    favor: u32,
+) -> i32 {
+    let parse = |favor| match favor {
+        THREEWAY_MERGE_FAVOR_NONE => Some(None),
+        THREEWAY_MERGE_FAVOR_OURS => Some(Some(MergeFavor::Ours)),
+        THREEWAY_MERGE_FAVOR_THEIRS => Some(Some(MergeFavor::Theirs)),
+        THREEWAY_MERGE_FAVOR_UNION => Some(Some(MergeFavor::Union)),
+        THREEWAY_MERGE_FAVOR_UNION_DEDUP => Some(Some(MergeFavor::UnionDedup)),
+        THREEWAY_MERGE_FAVOR_UNION_SORTED => Some(Some(MergeFavor::UnionSorted)),
+        _ => None,
+    };
+    unsafe {
+        set_option(options, favor, parse, |options, favor| {
+            options.favor = favor
+        })
+    }
+}
+
+/// Sets the merge level to one of `THREEWAY_MERGE_LEVEL_*`.
+///
+/// # Safety
+///
+/// `options` must be null or valid options.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn threeway_merge_options_set_level(
+    options: *mut ThreewayMergeOptions,
+    level: u32,
+) -> i32 {
+    let parse = |level| match level {
+        THREEWAY_MERGE_LEVEL_MINIMAL => Some(MergeLevel::Minimal),
+        THREEWAY_MERGE_LEVEL_EAGER => Some(MergeLevel::Eager),
+        THREEWAY_MERGE_LEVEL_ZEALOUS => Some(MergeLevel::Zealous),
+        THREEWAY_MERGE_LEVEL_ZEALOUS_ALNUM => Some(MergeLevel::ZealousAlnum),
+        _ => None,
+    };
+    unsafe {
+        set_option(options, level, parse, |options, level| {
+            options.level = level
+        })
+    }
+}
+
+/// Sets the policy for binary inputs to one of `THREEWAY_MERGE_BINARY_*`.
+///
+/// # Safety
+///
+/// `options` must be null or valid options.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn threeway_merge_options_set_binary(
+    options: *mut ThreewayMergeOptions,
+    policy: u32,
+) -> i32 {
+    let parse = |policy| match policy {
+        THREEWAY_MERGE_BINARY_ERROR => Some(BinaryPolicy::Error),
+        THREEWAY_MERGE_BINARY_TAKE_OURS => Some(BinaryPolicy::TakeOurs),
+        THREEWAY_MERGE_BINARY_TAKE_THEIRS => Some(BinaryPolicy::TakeTheirs),
+        THREEWAY_MERGE_BINARY_CONFLICT => Some(BinaryPolicy::Conflict),
+        _ => None,
+    };
+    unsafe {
+        set_option(options, policy, parse, |options, policy| {
+            options.binary = policy
+        })
+    }
+}
+
+/// Sets the line-ending policy to one of `THREEWAY_MERGE_EOL_*`.
+///
+/// # Safety
+///
+/// `options` must be null or valid options.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn threeway_merge_options_set_eol(
+    options: *mut ThreewayMergeOptions,
+    policy: u32,
+) -> i32 {
+    let parse = |policy| match policy {
+        THREEWAY_MERGE_EOL_PRESERVE => Some(EolPolicy::Preserve),
+        THREEWAY_MERGE_EOL_LF => Some(EolPolicy::Lf),
+        THREEWAY_MERGE_EOL_CRLF => Some(EolPolicy::Crlf),
+        THREEWAY_MERGE_EOL_MATCH_OURS => Some(EolPolicy::MatchOurs),
+        _ => None,
+    };
+    unsafe {
+        set_option(options, policy, parse, |options, policy| {
+            options.eol = policy
+        })
+    }
+}
+
+/// Sets the length of conflict markers; 0 means the default of 7.
+///
+/// # Safety
+///
+/// `options` must be null or valid options.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn threeway_merge_options_set_marker_size(
+    options: *mut ThreewayMergeOptions,
+    marker_size: usize,
+) -> i32 {
+    let Some(options) = (unsafe { options.as_mut() }) else {
+        return THREEWAY_MERGE_ERROR_INVALID_INPUT;
+    };
+    options.options.marker_size = marker_size;
+    THREEWAY_MERGE_OK
+}
+
+/// Turns resolving conflicts in sorted blocks on or off.
+///
+/// # Safety
+///
+/// `options` must be null or valid options.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn threeway_merge_options_set_sorted_blocks(
+    options: *mut ThreewayMergeOptions,
+    sorted_blocks: bool,
+) -> i32 {
+    let Some(options) = (unsafe { options.as_mut() }) else {
+        return THREEWAY_MERGE_ERROR_INVALID_INPUT;
+    };
+    options.options.sorted_blocks = sorted_blocks;
+    THREEWAY_MERGE_OK
+}
+
+/// Sets the labels after the conflict markers. Each is a NUL-terminated
+/// UTF-8 string, or null for no label; they are copied.
+///
+/// # Safety
+///
+/// `options` must be null or valid options, and each label null or a
+/// NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn threeway_merge_options_set_labels(
+    options: *mut ThreewayMergeOptions,
+    base: *const c_char,
+    ours: *const c_char,
+    theirs: *const c_char,
+) -> i32 {
+    let Some(options) = (unsafe { options.as_mut() }) else {
+        return THREEWAY_MERGE_ERROR_INVALID_INPUT;
+    };
+    let label = |label: *const c_char| {
+        if label.is_null() {
+            return Some(None);
+        }
+        let label = unsafe { CStr::from_ptr(label) };
+        label.to_str().ok().map(|label| Some(label.to_string()))
+    };
+    let (Some(base), Some(ours), Some(theirs)) = (label(base), label(ours), label(theirs)) else {
+        return THREEWAY_MERGE_ERROR_INVALID_INPUT;
+    };
+    options.options.base_label = base;
+    options.options.ours_label = ours;
+    options.options.theirs_label = theirs;
+    THREEWAY_MERGE_OK
+}
+
+/// Sets the limits of `MergeLimits`. 0 leaves a limit unset.
+///
+/// # Safety
+///
+/// `options` must be null or valid options.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn threeway_merge_options_set_limits(
+    options: *mut ThreewayMergeOptions,
+    max_input_bytes: usize,
+    max_lines: usize,
+    timeout_ms: u64,
+) -> i32 {
+    let Some(options) = (unsafe { options.as_mut() }) else {
+        return THREEWAY_MERGE_ERROR_INVALID_INPUT;
+    };
+    let limits = &mut options.options.limits;
+    limits.max_input_bytes = (max_input_bytes != 0).then_some(max_input_bytes);
+    limits.max_lines = (max_lines != 0).then_some(max_lines);
+    limits.timeout = (timeout_ms != 0).then(|| Duration::from_millis(timeout_ms));
+    THREEWAY_MERGE_OK
+}
+
+fn status(err: &MergeError) -> i32 {
+    match err {
+        MergeError::Internal(_) => THREEWAY_MERGE_ERROR_INTERNAL,
+        MergeError::InvalidInput(_) => THREEWAY_MERGE_ERROR_INVALID_INPUT,
+        MergeError::Binary(_) => THREEWAY_MERGE_ERROR_BINARY,
+        MergeError::OutOfMemory => THREEWAY_MERGE_ERROR_OUT_OF_MEMORY,
+        MergeError::Io(_) => THREEWAY_MERGE_ERROR_IO,
+        MergeError::LimitExceeded(_) => THREEWAY_MERGE_ERROR_LIMIT_EXCEEDED,
+    }
+}
+
+unsafe fn input<'a>(data: *const u8, len: usize, side: &str) -> Result<&'a [u8], MergeError> {
+    if data.is_null() {
+        if len == 0 {
+            return Ok(&[]);
+        }
+        return Err(MergeError::InvalidInput(format!("{} is null", side)));
+    }
+    Ok(unsafe { std::slice::from_raw_parts(data, len) })
+}
+
+/// Merges three inputs, which need not be UTF-8 or NUL-terminated, with the
+/// checks and fast paths of `merge_strings`. Null `options` means the
+/// defaults.
+///
+/// Returns the status and stores a result in `*result` either way: the
+/// merged content on `THREEWAY_MERGE_OK`, an error message otherwise. Free
+/// it with `threeway_merge_result_free`.
+///
+/// # Safety
+///
+/// Each input must be null with a length of 0, or point to `len` readable
+/// bytes. `options` must be null or valid options, and `result` must be
+/// null or writable.
+#[unsafe(no_mangle)]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn threeway_merge(
+    base: *const u8,
+    base_len: usize,
+    ours: *const u8,
+    ours_len: usize,
+    theirs: *const u8,
+    theirs_len: usize,
+    options: *const ThreewayMergeOptions,
+    result: *mut *mut ThreewayMergeResult,
+) -> i32 {
+    let merged = panic::catch_unwind(AssertUnwindSafe(|| {
+        let base = unsafe { input(base, base_len, "base")? };
+        let ours = unsafe { input(ours, ours_len, "ours")? };
+        let theirs = unsafe { input(theirs, theirs_len, "theirs")? };
+        let default = MergeOptions::default();
+        let options = unsafe { options.as_ref() }.map_or(&default, |options| &options.options);
+
+        let mut content = Vec::new();
+        let summary = merge_into_vec(base, ours, theirs, options, &mut content)?;
+        content.push(0);
+        Ok::<_, MergeError>((content, summary.conflicts))
+    }))
+    .unwrap_or_else(|_| Err(MergeError::Internal("merge panicked".to_string())));
+
+    let merged = match merged {
+        Ok((content, conflicts)) => ThreewayMergeResult {
+            status: THREEWAY_MERGE_OK,
+            content,
+            conflicts,
+            error: None,
+        },
+        Err(err) => ThreewayMergeResult {
+            status: status(&err),
+            content: Vec::new(),
+            conflicts: 0,
+            error: Some(CString::new(err.to_string().replace('\0', "\\0")).unwrap()),
+        },
+    };
+    let status = merged.status;
+    if let Some(result) = unsafe { result.as_mut() } {
+        *result = Box::into_raw(Box::new(merged));
+    }
+    status
+}
+
+/// The status `threeway_merge` returned with this result.
+///
+/// # Safety
+///
+/// `result` must be a result from `threeway_merge`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn threeway_merge_result_status(result: *const ThreewayMergeResult) -> i32 {
+    unsafe { (*result).status }
+}
+
+/// The merged content, NUL-terminated, with its length without the NUL
+/// stored in `*len` unless `len` is null. Null if the merge failed. The
+/// content may itself contain NULs when a binary policy took a side.
+///
+/// # Safety
+///
+/// `result` must be a result from `threeway_merge`. The content lives as
+/// long as the result.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn threeway_merge_result_content(
+    result: *const ThreewayMergeResult,
+    len: *mut usize,
+) -> *const c_char {
+    let result = unsafe { &*result };
+    if let Some(len) = unsafe { len.as_mut() } {
+        *len = result.content.len().saturating_sub(1);
+    }
+    if result.content.is_empty() {
+        ptr::null()
+    } else {
+        result.content.as_ptr().cast()
+    }
+}
+
+/// The number of conflicts left in the content.
+///
+/// # Safety
+///
+/// `result` must be a result from `threeway_merge`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn threeway_merge_result_conflicts(
+    result: *const ThreewayMergeResult,
+) -> usize {
+    unsafe { (*result).conflicts }
+}
+
+/// The error message, NUL-terminated, or null if the merge succeeded.
+///
+/// # Safety
+///
+/// `result` must be a result from `threeway_merge`. The message lives as
+/// long as the result.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn threeway_merge_result_error(
+    result: *const ThreewayMergeResult,
+) -> *const c_char {
+    unsafe {
+        (*result)
+            .error
+            .as_ref()
+            .map_or(ptr::null(), |error| error.as_ptr())
+    }
+}
+
+/// Frees a result. Null is ignored.
+///
+/// # Safety
+///
+/// `result` must be null or a result from `threeway_merge`, and must not
+/// be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn threeway_merge_result_free(result: *mut ThreewayMergeResult) {
+    if !result.is_null() {
+        drop(unsafe { Box::from_raw(result) });
+    }
+}
@@ -0,0 +1,559 @@
+//! Reading merge defaults from git-config format files.
+//!
+//! Files are parsed locally; git itself is never invoked. Later files and
+//! later entries win, so layering system, global, repository and worktree
+//! files in that order yields the same effective values git would use.
+
+use crate::types::*;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Nesting limit for `include.path`, matching git's own limit.
+const MAX_INCLUDE_DEPTH: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ConfigEntry {
+    key: String,
+    value: Option<String>,
+}
+
+/// An ordered set of git config entries, possibly layered from several files.
+#[derive(Debug, Clone, Default)]
+pub struct GitConfig {
+    entries: Vec<ConfigEntry>,
+}
+
+/// The config files git would consult, from lowest to highest precedence.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigFiles {
+    pub system: Option<PathBuf>,
+    pub global: Vec<PathBuf>,
+    pub repository: Option<PathBuf>,
+    /// Only read when the repository enables `extensions.worktreeConfig`.
+    pub worktree: Option<PathBuf>,
+}
+
+/// A custom merge driver declared in a `[merge "<name>"]` section.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeDriver {
+    pub name: Option<String>,
+    pub driver: Option<String>,
+    pub recursive: Option<String>,
+}
+
+impl ConfigFiles {
+    /// Locates config files the way git does, honouring `GIT_CONFIG_NOSYSTEM`,
+    /// `GIT_CONFIG_SYSTEM`, `GIT_CONFIG_GLOBAL`, `XDG_CONFIG_HOME` and `HOME`.
+    ///
+    /// `git_dir` is the repository's `.git` directory (or a linked worktree's
+    /// private git directory); pass `None` outside a repository.
+    pub fn discover(git_dir: Option<&Path>) -> Self {
+        let system = if env_flag("GIT_CONFIG_NOSYSTEM") {
+            None
+        } else {
+            Some(
+                env::var_os("GIT_CONFIG_SYSTEM")
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| PathBuf::from("/etc/gitconfig")),
+            )
+        };
+
+        let global = match env::var_os("GIT_CONFIG_GLOBAL") {
+            Some(path) => vec![PathBuf::from(path)],
+            None => {
+                let home = env::var_os("HOME").map(PathBuf::from);
+                let xdg = env::var_os("XDG_CONFIG_HOME")
+                    .filter(|dir| !dir.is_empty())
+                    .map(PathBuf::from)
+                    .or_else(|| home.as_ref().map(|home| home.join(".config")));
+                xdg.map(|dir| dir.join("git").join("config"))
+                    .into_iter()
+                    .chain(home.map(|home| home.join(".gitconfig")))
+                    .collect()
+            }
+        };
+
+        let (repository, worktree) = match git_dir {
+            Some(git_dir) => {
+                let common_dir = common_dir(git_dir);
+                (
+                    Some(common_dir.join("config")),
+                    Some(git_dir.join("config.worktree")),
+                )
+            }
+            None => (None, None),
+        };
+
+        Self {
+            system,
+            global,
+            repository,
+            worktree,
+        }
+    }
+}
+
+/// Finds the git directory of `work_tree`, following the `gitdir:` file that
+/// linked worktrees and submodules have in place of a `.git` directory.
+pub(crate) fn git_dir(work_tree: &Path) -> PathBuf {
+    let dot_git = work_tree.join(".git");
+    match fs::read_to_string(&dot_git) {
+        Ok(text) => match text.strip_prefix("gitdir:") {
+            Some(dir) => work_tree.join(dir.trim()),
+            None => dot_git,
+        },
+        Err(_) => dot_git,
+    }
+}
+
+/// Resolves the directory shared by all worktrees, named by `commondir` in a
+/// linked worktree's private git directory.
+pub(crate) fn common_dir(git_dir: &Path) -> PathBuf {
+    fs::read_to_string(git_dir.join("commondir"))
+        .map(|dir| git_dir.join(dir.trim_end()))
+        .unwrap_or_else(|_| git_dir.to_path_buf())
+}
+
+impl GitConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses config text. Relative `include.path` entries are rejected since
+    /// there is no file to resolve them against.
+    pub fn parse(text: &str) -> Result<Self, MergeError> {
+        let mut config = Self::new();
+        parse_into(&mut config.entries, text, None, 0)?;
+        Ok(config)
+    }
+
+    /// Parses a config file, following `include.path` directives.
+    pub fn from_file(path: &Path) -> Result<Self, MergeError> {
+        let mut config = Self::new();
+        read_into(&mut config.entries, path, 0)?;
+        Ok(config)
+    }
+
+    /// Reads and layers every existing file in `files`. Missing files are
+    /// skipped, as git does.
+    pub fn load(files: &ConfigFiles) -> Result<Self, MergeError> {
+        let mut config = Self::new();
+        for path in files.system.iter().chain(&files.global) {
+            config.layer_file(path)?;
+        }
+        // Like git, only the repository's own config can enable the
+        // worktree layer.
+        let mut repository = Self::new();
+        if let Some(path) = &files.repository {
+            repository.layer_file(path)?;
+        }
+        let worktree_config = repository.get_bool("extensions.worktreeConfig") == Some(true);
+        config.layer(repository);
+        if let Some(path) = &files.worktree
+            && worktree_config
+        {
+            config.layer_file(path)?;
+        }
+        Ok(config)
+    }
+
+    /// Appends `other` on top of this config; its values take precedence.
+    pub fn layer(&mut self, other: GitConfig) {
+        self.entries.extend(other.entries);
+    }
+
+    fn layer_file(&mut self, path: &Path) -> Result<(), MergeError> {
+        if path.is_file() {
+            read_into(&mut self.entries, path, 0)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the last value set for `key`, e.g. `merge.conflictStyle`.
+    ///
+    /// Section and variable names are case-insensitive, subsection names are
+    /// not. A variable given without `=` reads as an empty string.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        let key = normalize_key(key)?;
+        self.entries
+            .iter()
+            .rev()
+            .find(|entry| entry.key == key)
+            .map(|entry| entry.value.as_deref().unwrap_or(""))
+    }
+
+    /// Returns every value set for a multi-valued `key`, in file order.
+    pub fn get_all(&self, key: &str) -> Vec<&str> {
+        let Some(key) = normalize_key(key) else {
+            return Vec::new();
+        };
+        self.entries
+            .iter()
+            .filter(|entry| entry.key == key)
+            .map(|entry| entry.value.as_deref().unwrap_or(""))
+            .collect()
+    }
+
+    /// Interprets `key` as a git boolean. Returns `None` when unset or not a
+    /// recognised boolean spelling.
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        let key = normalize_key(key)?;
+        let entry = self.entries.iter().rev().find(|entry| entry.key == key)?;
+        match entry.value.as_deref() {
+            None => Some(true),
+            Some(value) => parse_bool(value),
+        }
+    }
+
+    /// Returns the `merge.<name>.*` settings of a custom merge driver.
+    pub fn merge_driver(&self, name: &str) -> Option<MergeDriver> {
+        let prefix = format!("merge.{}.", name);
+        if !self.entries.iter().any(|entry| {
+            entry
+                .key
+                .strip_prefix(&prefix)
+                .is_some_and(|var| !var.contains('.'))
+        }) {
+            return None;
+        }
+        let get = |var: &str| self.get(&format!("{}{}", prefix, var)).map(str::to_owned);
+        Some(MergeDriver {
+            name: get("name"),
+            driver: get("driver"),
+            recursive: get("recursive"),
+        })
+    }
+
+    /// Applies `merge.conflictStyle` and `diff.algorithm` on top of `options`.
+    pub fn apply_to(&self, options: &mut MergeOptions) -> Result<(), MergeError> {
+        if let Some(style) = self.get("merge.conflictStyle") {
+            options.style = style.parse()?;
+        }
+        if let Some(algorithm) = self.get("diff.algorithm") {
+            options.algorithm = algorithm.parse()?;
+        }
+        Ok(())
+    }
+}
+
+impl MergeOptions {
+    /// Builds options from git config, starting from [`MergeOptions::default`].
+    pub fn from_git_config(config: &GitConfig) -> Result<Self, MergeError> {
+        let mut options = Self::default();
+        config.apply_to(&mut options)?;
+        Ok(options)
+    }
+}
+
+fn env_flag(name: &str) -> bool {
+    env::var(name).is_ok_and(|value| parse_bool(&value).unwrap_or(false))
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Some(true),
+        "false" | "no" | "off" | "0" | "" => Some(false),
+        _ => None,
+    }
+}
+
+/// Lowercases the section and variable name, keeping the subsection as-is.
+fn normalize_key(key: &str) -> Option<String> {
+    let (section, rest) = key.split_once('.')?;
+    let (subsection, name) = match rest.rsplit_once('.') {
+        Some((subsection, name)) => (Some(subsection), name),
+        None => (None, rest),
+    };
+    Some(make_key(
+        &section.to_ascii_lowercase(),
+        subsection,
+        &name.to_ascii_lowercase(),
+    ))
+}
+
+fn make_key(section: &str, subsection: Option<&str>, name: &str) -> String {
+    match subsection {
+        Some(subsection) => format!("{}.{}.{}", section, subsection, name),
+        None => format!("{}.{}", section, name),
+    }
+}
+
+fn read_into(entries: &mut Vec<ConfigEntry>, path: &Path, depth: usize) -> Result<(), MergeError> {
+    let text = fs::read_to_string(path)
+        .map_err(|err| MergeError::Io(format!("{}: {}", path.display(), err)))?;
+    parse_into(entries, &text, Some(path), depth)
+}
+
+fn parse_into(
+    entries: &mut Vec<ConfigEntry>,
+    text: &str,
+    origin: Option<&Path>,
+    depth: usize,
+) -> Result<(), MergeError> {
+    let mut parser = Parser::new(text, origin);
+    let mut section: Option<(String, Option<String>)> = None;
+
+    while let Some(c) = parser.skip_blank() {
+        match c {
+            '#' | ';' => parser.skip_line(),
+            '[' => {
+                parser.bump();
+                section = Some(parser.section_header()?);
+            }
+            c if c.is_ascii_alphabetic() => {
+                let Some((name, subsection)) = &section else {
+                    return Err(parser.error("variable outside of a section"));
+                };
+                let var = parser.variable_name();
+                let value = parser.variable_value()?;
+                let key = make_key(name, subsection.as_deref(), &var);
+
+                if key == "include.path"
+                    && let Some(value) = &value
+                {
+                    include(entries, value, origin, depth)?;
+                }
+                entries.push(ConfigEntry { key, value });
+            }
+            _ => return Err(parser.error("unexpected character")),
+        }
+    }
+    Ok(())
+}
+
+fn include(
+    entries: &mut Vec<ConfigEntry>,
+    path: &str,
+    origin: Option<&Path>,
+    depth: usize,
+) -> Result<(), MergeError> {
+    if depth >= MAX_INCLUDE_DEPTH {
+        return Err(MergeError::InvalidInput(format!(
+            "exceeded maximum include depth ({}) while including {}",
+            MAX_INCLUDE_DEPTH, path
+        )));
+    }
+
+    let path = match path.strip_prefix("~/") {
+        Some(rest) => match env::var_os("HOME") {
+            Some(home) => PathBuf::from(home).join(rest),
+            None => return Ok(()),
+        },
+        None => PathBuf::from(path),
+    };
+    let path = if path.is_absolute() {
+        path
+    } else {
+        match origin.and_then(Path::parent) {
+            Some(dir) => dir.join(path),
+            None => {
+                return Err(MergeError::InvalidInput(format!(
+                    "relative config include {} must come from a file",
+                    path.display()
+                )));
+            }
+        }
+    };
+
+    // Like git, a missing include target is not an error.
+    if path.is_file() {
+        read_into(entries, &path, depth + 1)?;
+    }
+    Ok(())
+}
+
+struct Parser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    origin: Option<&'a Path>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &str, origin: Option<&'a Path>) -> Self {
+        // git treats CRLF line endings as LF
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        Self {
+            chars: text.replace("\r\n", "\n").chars().collect(),
+            pos: 0,
+            line: 1,
+            origin,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_blank(&mut self) -> Option<char> {
+        while let Some(c) = self.peek() {
+            if !c.is_whitespace() {
+                return Some(c);
+            }
+            self.bump();
+        }
+        None
+    }
+
+    fn skip_inline_space(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.bump();
+        }
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(c) = self.bump() {
+            if c == '\n' {
+                break;
+            }
+        }
+    }
+
+    fn error(&self, message: &str) -> MergeError {
+        let origin = self
+            .origin
+            .map_or_else(|| "config".to_string(), |path| path.display().to_string());
+        MergeError::InvalidInput(format!(
+            "bad config line {} in {}: {}",
+            self.line, origin, message
+        ))
+    }
+
+    /// Parses `section]`, `section "subsection"]` or `section.subsection]`
+    /// after the opening bracket.
+    fn section_header(&mut self) -> Result<(String, Option<String>), MergeError> {
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                name.push(c.to_ascii_lowercase());
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        if name.is_empty() {
+            return Err(self.error("empty section name"));
+        }
+
+        match self.bump() {
+            Some(']') => match name.split_once('.') {
+                // Deprecated `[section.subsection]` syntax; subsection is lowercased
+                Some((section, subsection)) => Ok((section.to_string(), Some(subsection.into()))),
+                None => Ok((name, None)),
+            },
+            Some(' ' | '\t') if !name.contains('.') => {
+                self.skip_inline_space();
+                if self.bump() != Some('"') {
+                    return Err(self.error("expected quoted subsection name"));
+                }
+                let mut subsection = String::new();
+                loop {
+                    match self.bump() {
+                        Some('"') => break,
+                        Some('\\') => match self.bump() {
+                            Some(c) if c != '\n' => subsection.push(c),
+                            _ => return Err(self.error("unterminated subsection name")),
+                        },
+                        Some(c) if c != '\n' => subsection.push(c),
+                        _ => return Err(self.error("unterminated subsection name")),
+                    }
+                }
+                if self.bump() != Some(']') {
+                    return Err(self.error("expected ']' after subsection name"));
+                }
+                Ok((name, Some(subsection)))
+            }
+            _ => Err(self.error("invalid section header")),
+        }
+    }
+
+    fn variable_name(&mut self) -> String {
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                name.push(c.to_ascii_lowercase());
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        name
+    }
+
+    /// Parses an optional `= value` following a variable name. Returns `None`
+    /// for a bare variable, which git treats as boolean true.
+    fn variable_value(&mut self) -> Result<Option<String>, MergeError> {
+        self.skip_inline_space();
+        match self.peek() {
+            None => return Ok(None),
+            Some('\n') => {
+                self.bump();
+                return Ok(None);
+            }
+            Some('=') => {
+                self.bump();
+            }
+            Some(_) => return Err(self.error("expected '=' after variable name")),
+        }
+
+        let mut value = String::new();
+        let mut quoted = false;
+        let mut in_comment = false;
+        let mut pending_space = 0;
+        loop {
+            let c = match self.peek() {
+                None | Some('\n') if quoted => {
+                    return Err(self.error("unterminated quoted value"));
+                }
+                None => return Ok(Some(value)),
+                Some('\n') => {
+                    self.bump();
+                    return Ok(Some(value));
+                }
+                Some(c) => {
+                    self.bump();
+                    c
+                }
+            };
+            if in_comment {
+                continue;
+            }
+            if !quoted && (c == ' ' || c == '\t') {
+                // Leading whitespace is dropped, trailing whitespace only
+                // survives if something follows it.
+                if !value.is_empty() {
+                    pending_space += 1;
+                }
+                continue;
+            }
+            if !quoted && (c == '#' || c == ';') {
+                in_comment = true;
+                continue;
+            }
+            value.extend(std::iter::repeat_n(' ', pending_space));
+            pending_space = 0;
+            match c {
+                '\\' => match self.bump() {
+                    Some('\n') => {}
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some('b') => value.push('\u{8}'),
+                    Some(c @ ('\\' | '"')) => value.push(c),
+                    _ => return Err(self.error("invalid escape sequence")),
+                },
+                '"' => quoted = !quoted,
+                c => value.push(c),
+            }
+        }
+    }
+}
@@ -0,0 +1,374 @@
+//! Row-keyed three-way merge of CSV and TSV tables.
+//!
+//! Rows are matched by their key columns and merged cell by cell, so edits to
+//! different columns of a row, or rows appended on both sides, never
+//! conflict. Rows that come through unchanged keep the exact text of the side
+//! they were taken from.
+
+use crate::merge::merge_strings;
+use crate::types::*;
+use std::collections::HashMap;
+
+/// Options for [`merge_csv`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct CsvMergeOptions {
+    /// Header names of the columns identifying a row; the first column when
+    /// empty
+    pub key_columns: Vec<String>,
+    /// Field delimiter, `b','` by default; `b'\t'` for TSV
+    pub delimiter: u8,
+    /// Options for the line merge used when the inputs can't be merged as
+    /// tables
+    pub text: MergeOptions,
+}
+
+impl Default for CsvMergeOptions {
+    fn default() -> Self {
+        Self {
+            key_columns: Vec::new(),
+            delimiter: b',',
+            text: MergeOptions::default(),
+        }
+    }
+}
+
+/// A row or cell that both sides changed in incompatible ways.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CellConflict {
+    /// Values of the key columns of the row
+    pub key: Vec<String>,
+    /// The conflicting column, or `None` when one side deleted the row and
+    /// the other changed it
+    pub column: Option<String>,
+    /// The cell (or the row, joined with the delimiter) on each side; `None`
+    /// when the row is absent on that side
+    pub base: Option<String>,
+    pub ours: Option<String>,
+    pub theirs: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CsvMergeResult {
+    /// The merged table. Conflicting cells keep "ours".
+    pub content: String,
+    pub conflicts: Vec<CellConflict>,
+    /// The line merge result, when the inputs could not be merged as tables
+    pub fallback: Option<MergeResult>,
+}
+
+impl CsvMergeResult {
+    /// Returns true if the merge was successful without conflicts
+    pub fn is_clean_merge(&self) -> bool {
+        self.conflicts.is_empty() && self.fallback.as_ref().is_none_or(|r| r.is_clean_merge())
+    }
+}
+
+/// Merges three CSV (or TSV) tables with a header row.
+///
+/// Rows are identified by `options.key_columns` and kept in the order of
+/// "ours"; rows added only by "theirs" follow the row they follow in
+/// "theirs", after any rows "ours" added there.
+///
+/// Falls back to [`merge_strings`] with `options.text` when an input can't be
+/// parsed, the headers differ between the sides, or a key is not unique. A key
+/// column missing from the header is an error.
+pub fn merge_csv(
+    base: &str,
+    ours: &str,
+    theirs: &str,
+    options: &CsvMergeOptions,
+) -> Result<CsvMergeResult, MergeError> {
+    let tables = [base, ours, theirs].map(|text| Table::parse(text, options.delimiter));
+    let [Some(base_table), Some(ours_table), Some(theirs_table)] = tables else {
+        return fallback(base, ours, theirs, options);
+    };
+    if base_table.headers != ours_table.headers || ours_table.headers != theirs_table.headers {
+        return fallback(base, ours, theirs, options);
+    }
+
+    let key_columns = if options.key_columns.is_empty() {
+        vec![0]
+    } else {
+        options
+            .key_columns
+            .iter()
+            .map(|name| {
+                ours_table
+                    .headers
+                    .iter()
+                    .position(|header| header == name)
+                    .ok_or_else(|| {
+                        MergeError::InvalidInput(format!("key column '{}' not found", name))
+                    })
+            })
+            .collect::<Result<_, _>>()?
+    };
+    let (Some(base_index), Some(ours_index), Some(theirs_index)) = (
+        base_table.index(&key_columns),
+        ours_table.index(&key_columns),
+        theirs_table.index(&key_columns),
+    ) else {
+        return fallback(base, ours, theirs, options);
+    };
+
+    let terminator = if ours.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut lines = vec![ours_table.header_text.to_string()];
+    let mut conflicts = Vec::new();
+
+    let order = row_order(
+        &base_index,
+        &ours_index,
+        &ours_table,
+        &theirs_table,
+        &key_columns,
+    );
+    for key in order {
+        let b = base_index.get(&key).map(|&i| &base_table.rows[i]);
+        let o = ours_index.get(&key).map(|&i| &ours_table.rows[i]);
+        let t = theirs_index.get(&key).map(|&i| &theirs_table.rows[i]);
+
+        let row = match (o, t) {
+            (Some(o), Some(t)) => Some(merge_row(
+                &key,
+                &ours_table.headers,
+                b,
+                o,
+                t,
+                options.delimiter,
+                &mut conflicts,
+            )),
+            (Some(o), None) if b.is_some_and(|b| b.cells != o.cells) => {
+                conflicts.push(row_conflict(&key, b, Some(o), None, options.delimiter));
+                Some(o.text.to_string())
+            }
+            (None, Some(t)) if b.is_some_and(|b| b.cells != t.cells) => {
+                conflicts.push(row_conflict(&key, b, None, Some(t), options.delimiter));
+                None
+            }
+            (Some(_), None) | (None, Some(_)) if b.is_some() => None,
+            (Some(row), None) | (None, Some(row)) => Some(row.text.to_string()),
+            (None, None) => None,
+        };
+        lines.extend(row);
+    }
+
+    let mut content = lines.join(terminator);
+    if ours.ends_with('\n') {
+        content.push_str(terminator);
+    }
+    Ok(CsvMergeResult {
+        content,
+        conflicts,
+        fallback: None,
+    })
+}
+
+fn fallback(
+    base: &str,
+    ours: &str,
+    theirs: &str,
+    options: &CsvMergeOptions,
+) -> Result<CsvMergeResult, MergeError> {
+    let result = merge_strings(base, ours, theirs, &options.text)?;
+    Ok(CsvMergeResult {
+        content: result.content.clone(),
+        conflicts: Vec::new(),
+        fallback: Some(result),
+    })
+}
+
+struct Row<'a> {
+    cells: Vec<String>,
+    /// The record as written, without line terminators
+    text: &'a str,
+}
+
+struct Table<'a> {
+    headers: Vec<String>,
+    header_text: &'a str,
+    rows: Vec<Row<'a>>,
+}
+
+impl<'a> Table<'a> {
+    fn parse(text: &'a str, delimiter: u8) -> Option<Self> {
+        let mut reader = ::csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .from_reader(text.as_bytes());
+        let headers: Vec<String> = reader.headers().ok()?.iter().map(String::from).collect();
+
+        let mut records = Vec::new();
+        for record in reader.records() {
+            let record = record.ok()?;
+            let start = usize::try_from(record.position()?.byte()).ok()?;
+            records.push((record.iter().map(String::from).collect(), start));
+        }
+
+        let trim = |s: &'a str| s.trim_matches(['\r', '\n']);
+        let mut ends: Vec<usize> = records.iter().skip(1).map(|(_, start)| *start).collect();
+        ends.push(text.len());
+        let header_end = records.first().map_or(text.len(), |(_, start)| *start);
+        let rows = records
+            .into_iter()
+            .zip(ends)
+            .map(|((cells, start), end)| Row {
+                cells,
+                text: trim(&text[start..end]),
+            })
+            .collect();
+
+        Some(Self {
+            headers,
+            header_text: trim(&text[..header_end]),
+            rows,
+        })
+    }
+
+    /// Maps each row key to its row, or `None` if a key is not unique.
+    fn index(&self, key_columns: &[usize]) -> Option<HashMap<Vec<String>, usize>> {
+        let mut index = HashMap::with_capacity(self.rows.len());
+        for (i, row) in self.rows.iter().enumerate() {
+            if index.insert(row_key(row, key_columns), i).is_some() {
+                return None;
+            }
+        }
+        Some(index)
+    }
+}
+
+fn row_key(row: &Row, key_columns: &[usize]) -> Vec<String> {
+    key_columns.iter().map(|&i| row.cells[i].clone()).collect()
+}
+
+/// The keys of "ours" in order, with keys added by "theirs" inserted after
+/// their predecessor in "theirs" and after any rows "ours" added there.
+fn row_order(
+    base: &HashMap<Vec<String>, usize>,
+    ours: &HashMap<Vec<String>, usize>,
+    ours_table: &Table,
+    theirs: &Table,
+    key_columns: &[usize],
+) -> Vec<Vec<String>> {
+    // A linked list of the keys, with each key's node, so that rows can be
+    // inserted after any key without searching or shifting
+    let mut keys: Vec<Vec<String>> = ours_table
+        .rows
+        .iter()
+        .map(|row| row_key(row, key_columns))
+        .collect();
+    let mut next: Vec<Option<usize>> = (1..=keys.len())
+        .map(|i| (i < keys.len()).then_some(i))
+        .collect();
+    let mut head = (!keys.is_empty()).then_some(0);
+    let mut nodes: HashMap<Vec<String>, usize> = keys
+        .iter()
+        .enumerate()
+        .map(|(i, key)| (key.clone(), i))
+        .collect();
+
+    let mut previous: Option<usize> = None;
+    for row in &theirs.rows {
+        let key = row_key(row, key_columns);
+        if !nodes.contains_key(&key) && !base.contains_key(&key) {
+            // After the predecessor, or at the start, then past rows ours
+            // added there
+            let mut after = previous;
+            while let Some(at) = after.map_or(head, |node| next[node])
+                && ours.contains_key(&keys[at])
+                && !base.contains_key(&keys[at])
+            {
+                after = Some(at);
+            }
+            let node = keys.len();
+            let successor = match after {
+                Some(after) => next[after].replace(node),
+                None => head.replace(node),
+            };
+            keys.push(key.clone());
+            next.push(successor);
+            nodes.insert(key.clone(), node);
+        }
+        if let Some(&node) = nodes.get(&key) {
+            previous = Some(node);
+        }
+    }
+
+    let mut order = Vec::with_capacity(keys.len());
+    let mut node = head;
+    while let Some(at) = node {
+        order.push(std::mem::take(&mut keys[at]));
+        node = next[at];
+    }
+    order
+}
+
+fn merge_row(
+    key: &[String],
+    headers: &[String],
+    base: Option<&Row>,
+    ours: &Row,
+    theirs: &Row,
+    delimiter: u8,
+    conflicts: &mut Vec<CellConflict>,
+) -> String {
+    if ours.cells == theirs.cells || base.is_some_and(|b| b.cells == theirs.cells) {
+        return ours.text.to_string();
+    }
+    if base.is_some_and(|b| b.cells == ours.cells) {
+        return theirs.text.to_string();
+    }
+
+    let mut cells = ours.cells.clone();
+    for (column, cell) in cells.iter_mut().enumerate() {
+        let b = base.map(|b| &b.cells[column]);
+        let (o, t) = (&ours.cells[column], &theirs.cells[column]);
+        if o == t || b == Some(t) {
+            continue;
+        }
+        if b == Some(o) {
+            *cell = t.clone();
+            continue;
+        }
+        conflicts.push(CellConflict {
+            key: key.to_vec(),
+            column: Some(headers[column].clone()),
+            base: b.cloned(),
+            ours: Some(o.clone()),
+            theirs: Some(t.clone()),
+        });
+    }
+    write_record(&cells, delimiter)
+}
+
+fn row_conflict(
+    key: &[String],
+    base: Option<&Row>,
+    ours: Option<&Row>,
+    theirs: Option<&Row>,
+    delimiter: u8,
+) -> CellConflict {
+    let render = |row: Option<&Row>| row.map(|row| write_record(&row.cells, delimiter));
+    CellConflict {
+        key: key.to_vec(),
+        column: None,
+        base: render(base),
+        ours: render(ours),
+        theirs: render(theirs),
+    }
+}
+
+fn write_record(cells: &[String], delimiter: u8) -> String {
+    let mut writer = ::csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .terminator(::csv::Terminator::Any(b'\n'))
+        .from_writer(Vec::new());
+    // Writing to a Vec cannot fail
+    let _ = writer.write_record(cells);
+    let bytes = writer.into_inner().unwrap_or_default();
+    let mut text = String::from_utf8(bytes).unwrap_or_default();
+    text.pop();
+    text
+}
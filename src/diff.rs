@@ -0,0 +1,181 @@
+//! Two-way line diffs and line hashing through xdiff, for features that need
+//! more than the merged output of `xdl_merge`.
+
+#[cfg(not(pure_rust))]
+use crate::ffi::{self, XdEmitCb, XdEmitConf, XppParam};
+use crate::limits::Watchdog;
+#[cfg(not(pure_rust))]
+use crate::merge::{bytes_to_mmfile, diff_flags};
+use crate::types::*;
+#[cfg(pure_rust)]
+use crate::xdiff_rs;
+use std::ops::Range;
+#[cfg(not(pure_rust))]
+use std::os::raw::{c_char, c_int, c_long, c_void};
+#[cfg(not(pure_rust))]
+use std::ptr;
+
+/// A changed region between two texts, in 0-based line numbers. Pure
+/// insertions have an empty `old` range positioned before the line they
+/// precede; pure deletions an empty `new` range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Hunk {
+    pub old: Range<usize>,
+    pub new: Range<usize>,
+}
+
+#[cfg(not(pure_rust))]
+unsafe extern "C" fn collect_hunk(
+    start_a: c_long,
+    count_a: c_long,
+    start_b: c_long,
+    count_b: c_long,
+    cb_data: *mut c_void,
+) -> c_int {
+    // SAFETY: `cb_data` is the `Vec<Hunk>` passed by `diff_lines`.
+    let hunks = unsafe { &mut *(cb_data as *mut Vec<Hunk>) };
+    let (a, b) = (start_a as usize, start_b as usize);
+    hunks.push(Hunk {
+        old: a..a + count_a as usize,
+        new: b..b + count_b as usize,
+    });
+    0
+}
+
+/// Diffs `old` against `new` line by line with no context, so each hunk is a
+/// single run of changed lines. `watchdog` is the one of the public call the
+/// diff is part of.
+#[cfg(not(pure_rust))]
+pub(crate) fn diff_lines(
+    old: &[u8],
+    new: &[u8],
+    options: &MergeOptions,
+    watchdog: &Watchdog,
+) -> Result<Vec<Hunk>, MergeError> {
+    let old_mmf = bytes_to_mmfile(old, "old")?;
+    let new_mmf = bytes_to_mmfile(new, "new")?;
+    let (abort_func, abort_priv) = watchdog.abort_func();
+    let xpp = XppParam {
+        flags: diff_flags(options.algorithm),
+        ignore_regex: ptr::null_mut(),
+        ignore_regex_nr: 0,
+        anchors: ptr::null_mut(),
+        anchors_nr: 0,
+        abort_func,
+        abort_priv,
+    };
+    let xecfg = XdEmitConf {
+        ctxlen: 0,
+        interhunkctxlen: 0,
+        flags: 0,
+        find_func: ptr::null(),
+        find_func_priv: ptr::null_mut(),
+        hunk_func: Some(collect_hunk),
+    };
+
+    let mut hunks: Vec<Hunk> = Vec::new();
+    let mut ecb = XdEmitCb {
+        priv_: &mut hunks as *mut Vec<Hunk> as *mut c_void,
+        out_hunk: ptr::null(),
+        out_line: ptr::null(),
+    };
+
+    // xdl_call_hunk_func hands the callback `ecb->priv`
+    let ret = unsafe { ffi::xdl_diff(&old_mmf, &new_mmf, &xpp, &xecfg, &mut ecb) };
+    if ret < 0 {
+        if let Some(err) = watchdog.error() {
+            return Err(err);
+        }
+        return Err(MergeError::Internal(format!(
+            "xdl_diff failed with code {}",
+            ret
+        )));
+    }
+    Ok(hunks)
+}
+
+/// Diffs `old` against `new` line by line with no context, so each hunk is a
+/// single run of changed lines. `watchdog` is the one of the public call the
+/// diff is part of.
+#[cfg(pure_rust)]
+pub(crate) fn diff_lines(
+    old: &[u8],
+    new: &[u8],
+    options: &MergeOptions,
+    watchdog: &Watchdog,
+) -> Result<Vec<Hunk>, MergeError> {
+    let abort = || watchdog.check();
+    let params = xdiff_rs::DiffParams {
+        algorithm: options.algorithm,
+        abort: &abort,
+    };
+    xdiff_rs::diff(old, new, &params).map_err(|_| {
+        watchdog
+            .error()
+            .unwrap_or_else(|| MergeError::Internal("xdl_diff failed with code -1".to_string()))
+    })
+}
+
+/// A text split into lines, each with the hash xdiff uses to match records.
+pub(crate) struct Lines<'a> {
+    text: &'a [u8],
+    /// Byte offset of each line, plus the end of the text
+    starts: Vec<usize>,
+    hashes: Vec<u64>,
+}
+
+impl<'a> Lines<'a> {
+    #[cfg(not(pure_rust))]
+    pub fn new(text: &'a [u8]) -> Self {
+        let mut starts = vec![0];
+        let mut hashes = Vec::new();
+        let top = text.as_ptr_range().end as *const c_char;
+        let mut ptr = text.as_ptr() as *const c_char;
+        while (ptr as usize) < (top as usize) {
+            // Advances `ptr` past the line's newline
+            hashes.push(unsafe { ffi::xdl_hash_record(&mut ptr, top, 0) } as u64);
+            starts.push(ptr as usize - text.as_ptr() as usize);
+        }
+        Self {
+            text,
+            starts,
+            hashes,
+        }
+    }
+
+    #[cfg(pure_rust)]
+    pub fn new(text: &'a [u8]) -> Self {
+        let mut starts = vec![0];
+        let mut hashes = Vec::new();
+        let mut start = 0;
+        while start < text.len() {
+            let (hash, next) = xdiff_rs::hash_record(text, start);
+            hashes.push(hash);
+            starts.push(next);
+            start = next;
+        }
+        Self {
+            text,
+            starts,
+            hashes,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn hash(&self, line: usize) -> u64 {
+        self.hashes[line]
+    }
+
+    /// The line with its terminator
+    pub fn line(&self, line: usize) -> &'a [u8] {
+        &self.text[self.starts[line]..self.starts[line + 1]]
+    }
+
+    /// The bytes spanned by a range of lines
+    pub fn slice(&self, lines: Range<usize>) -> &'a [u8] {
+        &self.text[self.starts[lines.start]..self.starts[lines.end]]
+    }
+}
@@ -0,0 +1,439 @@
+//! GNU `diff3` output formats.
+//!
+//! The inputs are split into blocks the way `diff3` does it, from the
+//! two-way diffs of one input to the other two: hunks of either diff that
+//! overlap or touch join one block, which is then classified by which input
+//! differs from the other two. The diffs are xdiff's, so the output matches
+//! `diff3` wherever GNU diff and xdiff agree on the diffs.
+
+use crate::diff::{Hunk, Lines, diff_lines};
+use crate::merge::is_binary;
+use crate::types::*;
+use std::ops::Range;
+
+/// Which blocks the ed-script and merge formats include, named after
+/// `diff3`'s long options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Diff3Mode {
+    /// `-A`: every change of theirs, with conflicts bracketed including
+    /// the base's lines, and changes both sides made alike bracketed too
+    #[default]
+    ShowAll,
+    /// `-E`: like `Ed`, with conflicts bracketed
+    ShowOverlap,
+    /// `-e`: every change of theirs; on conflicts theirs wins
+    Ed,
+    /// `-3`: only the changes of theirs that do not conflict
+    EasyOnly,
+    /// `-x`: only the conflicting changes, theirs winning
+    OverlapOnly,
+}
+
+impl Diff3Mode {
+    fn shows_base(self) -> bool {
+        self == Diff3Mode::ShowAll
+    }
+
+    fn brackets(self) -> bool {
+        matches!(self, Diff3Mode::ShowAll | Diff3Mode::ShowOverlap)
+    }
+}
+
+/// Output formats of GNU `diff3`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Diff3Format {
+    /// The default listing of `====`, `====1`, `====2` and `====3` blocks
+    Listing,
+    /// An ed script that applies the selected changes to ours
+    EdScript(Diff3Mode),
+    /// `-m`: ours with the selected changes applied
+    Merge(Diff3Mode),
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diff3Result {
+    pub content: String,
+    /// Bracketed conflicts in the ed-script and merge formats; `====`
+    /// blocks in the listing
+    pub conflicts: usize,
+}
+
+impl Diff3Result {
+    /// Returns true if there are any conflicts in the output
+    pub fn has_conflicts(&self) -> bool {
+        self.conflicts > 0
+    }
+}
+
+/// The input that differs from the other two in a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Odd {
+    /// All three differ
+    All,
+    Ours,
+    Base,
+    Theirs,
+}
+
+struct Block {
+    odd: Odd,
+    /// Line ranges in ours, the base and theirs, `diff3`'s files 1 to 3
+    ranges: [Range<usize>; 3],
+}
+
+/// Groups the hunks of the two-way diffs from the `common` file to each
+/// of the other two into blocks like `make_3way_diff`. With
+/// `whole_lines`, a missing newline at the end of a file is ignored when
+/// comparing the other two, as `diff3` does for ed scripts.
+fn blocks(files: &[Lines; 3], common: usize, hunks: [&[Hunk]; 2], whole_lines: bool) -> Vec<Block> {
+    let others: Vec<usize> = (0..3).filter(|&file| file != common).collect();
+    let mut next = [0; 2];
+    let mut shift = [0isize; 2];
+    let mut blocks = Vec::new();
+    loop {
+        let mut high = match (hunks[0].get(next[0]), hunks[1].get(next[1])) {
+            (None, None) => break,
+            (Some(_), None) => 0,
+            (None, Some(_)) => 1,
+            (Some(a), Some(b)) => usize::from(a.old.start > b.old.start),
+        };
+        let first = next;
+        let start = hunks[high][next[high]].old.start;
+        let mut end = hunks[high][next[high]].old.end;
+        next[high] += 1;
+        // Hunks of the other diff that start before or right at the end of
+        // the block join it
+        while let Some(hunk) = hunks[1 - high].get(next[1 - high])
+            && hunk.old.start <= end
+        {
+            next[1 - high] += 1;
+            if hunk.old.end > end {
+                end = hunk.old.end;
+                high = 1 - high;
+            }
+        }
+
+        let mut ranges = [start..end, start..end, start..end];
+        for side in 0..2 {
+            let before = shift[side];
+            shift[side] += hunks[side][first[side]..next[side]]
+                .iter()
+                .map(|hunk| hunk.new.len() as isize - hunk.old.len() as isize)
+                .sum::<isize>();
+            ranges[others[side]] =
+                (start as isize + before) as usize..(end as isize + shift[side]) as usize;
+        }
+        let odd = if first[0] == next[0] {
+            others[1]
+        } else if first[1] == next[1] {
+            others[0]
+        } else if same_lines(
+            files[others[0]].slice(ranges[others[0]].clone()),
+            files[others[1]].slice(ranges[others[1]].clone()),
+            whole_lines,
+        ) {
+            common
+        } else {
+            3
+        };
+        blocks.push(Block {
+            odd: [Odd::Ours, Odd::Base, Odd::Theirs, Odd::All][odd],
+            ranges,
+        });
+    }
+    blocks
+}
+
+fn same_lines(a: &[u8], b: &[u8], whole_lines: bool) -> bool {
+    if whole_lines {
+        a.strip_suffix(b"\n").unwrap_or(a) == b.strip_suffix(b"\n").unwrap_or(b)
+    } else {
+        a == b
+    }
+}
+
+/// Whether `mode` includes a block, and if so whether it is bracketed.
+fn select(mode: Diff3Mode, odd: Odd) -> Option<bool> {
+    match odd {
+        Odd::Ours => None,
+        Odd::Base => mode.shows_base().then_some(true),
+        Odd::Theirs => (mode != Diff3Mode::OverlapOnly).then_some(false),
+        Odd::All => (mode != Diff3Mode::EasyOnly).then_some(mode.brackets()),
+    }
+}
+
+struct Writer<'a> {
+    out: Vec<u8>,
+    files: [&'a Lines<'a>; 3],
+    labels: [Option<&'a str>; 3],
+    marker_size: usize,
+}
+
+impl Writer<'_> {
+    fn text(&mut self, text: &str) {
+        self.out.extend_from_slice(text.as_bytes());
+    }
+
+    fn lines(&mut self, file: usize, range: &Range<usize>) {
+        self.out
+            .extend_from_slice(self.files[file].slice(range.clone()));
+    }
+
+    /// A conflict marker line, labelled with `file`'s label if given.
+    fn marker(&mut self, marker: u8, file: Option<usize>) {
+        self.out
+            .extend(std::iter::repeat_n(marker, self.marker_size));
+        if let Some(label) = file.and_then(|file| self.labels[file]) {
+            self.out.push(b' ');
+            self.out.extend_from_slice(label.as_bytes());
+        }
+        self.out.push(b'\n');
+    }
+
+    /// Writes lines for an ed `a` or `c` command, doubling leading dots.
+    /// Returns whether any line had one.
+    fn dot_lines(&mut self, file: usize, range: &Range<usize>) -> bool {
+        let mut leading_dot = false;
+        for line in range.clone() {
+            let line = self.files[file].line(line);
+            if line.starts_with(b".") {
+                leading_dot = true;
+                self.out.push(b'.');
+            }
+            self.out.extend_from_slice(line);
+            // ed scripts need whole lines; `diff3` adds the missing newline
+            if !line.ends_with(b"\n") {
+                self.out.push(b'\n');
+            }
+        }
+        leading_dot
+    }
+
+    /// Ends the lines of an ed command and undoes the doubled dots of the
+    /// `count` lines from `start`.
+    fn undot_lines(&mut self, leading_dot: bool, start: usize, count: usize) {
+        self.text(".\n");
+        if leading_dot {
+            let command = if count == 1 {
+                format!("{}s/^\\.//\n", start)
+            } else {
+                format!("{},{}s/^\\.//\n", start, start + count - 1)
+            };
+            self.text(&command);
+        }
+    }
+}
+
+fn listing(w: &mut Writer, blocks: &[Block]) -> usize {
+    // Files in output order, and whether their lines are shown; of two
+    // files with the same lines only the second shows them
+    const ALL: [(usize, bool); 3] = [(0, true), (1, true), (2, true)];
+    const OURS: [(usize, bool); 3] = [(0, true), (1, false), (2, true)];
+    const BASE: [(usize, bool); 3] = [(0, false), (2, true), (1, true)];
+    const THEIRS: [(usize, bool); 3] = [(0, false), (1, true), (2, true)];
+
+    for block in blocks {
+        let (header, files) = match block.odd {
+            Odd::All => ("====\n", ALL),
+            Odd::Ours => ("====1\n", OURS),
+            Odd::Base => ("====2\n", BASE),
+            Odd::Theirs => ("====3\n", THEIRS),
+        };
+        w.text(header);
+        for (file, show) in files {
+            let range = &block.ranges[file];
+            let command = match range.len() {
+                0 => format!("{}:{}a\n", file + 1, range.start),
+                1 => format!("{}:{}c\n", file + 1, range.end),
+                _ => format!("{}:{},{}c\n", file + 1, range.start + 1, range.end),
+            };
+            w.text(&command);
+            if !show {
+                continue;
+            }
+            for line in range.clone() {
+                let line = w.files[file].line(line);
+                w.text("  ");
+                w.out.extend_from_slice(line);
+                if !line.ends_with(b"\n") {
+                    w.text("\n\\ No newline at end of file\n");
+                }
+            }
+        }
+    }
+    blocks.iter().filter(|block| block.odd == Odd::All).count()
+}
+
+/// Writes the ed script, last block first so line numbers stay valid.
+fn ed_script(w: &mut Writer, blocks: &[Block], mode: Diff3Mode) -> usize {
+    let mut conflicts = 0;
+    for block in blocks.iter().rev() {
+        let Some(bracketed) = select(mode, block.odd) else {
+            continue;
+        };
+        let [ours, base, theirs] = &block.ranges;
+        // 1-based first and last lines of ours, as in `diff3`
+        let (low, high) = (ours.start + 1, ours.end);
+
+        if bracketed {
+            conflicts += 1;
+            w.text(&format!("{}a\n", high));
+            let mut leading_dot = false;
+            if block.odd == Odd::All {
+                if mode.shows_base() {
+                    w.marker(b'|', Some(1));
+                    leading_dot = w.dot_lines(1, base);
+                }
+                w.marker(b'=', None);
+                leading_dot |= w.dot_lines(2, theirs);
+            }
+            w.marker(b'>', Some(2));
+            w.undot_lines(leading_dot, high + 2, base.len() + theirs.len() + 1);
+
+            w.text(&format!("{}a\n", low - 1));
+            w.marker(b'<', Some(if block.odd == Odd::All { 0 } else { 1 }));
+            let mut leading_dot = false;
+            if block.odd == Odd::Base {
+                leading_dot = w.dot_lines(1, base);
+                w.marker(b'=', None);
+            }
+            w.undot_lines(leading_dot, low + 1, base.len());
+        } else if theirs.is_empty() {
+            let command = if low == high {
+                format!("{}d\n", low)
+            } else {
+                format!("{},{}d\n", low, high)
+            };
+            w.text(&command);
+        } else {
+            let command = match ours.len() {
+                0 => format!("{}a\n", high),
+                1 => format!("{}c\n", high),
+                _ => format!("{},{}c\n", low, high),
+            };
+            w.text(&command);
+            let leading_dot = w.dot_lines(2, theirs);
+            w.undot_lines(leading_dot, low, theirs.len());
+        }
+    }
+    conflicts
+}
+
+fn merge(w: &mut Writer, blocks: &[Block], mode: Diff3Mode) -> usize {
+    let mut conflicts = 0;
+    let mut copied = 0;
+    for block in blocks {
+        let Some(bracketed) = select(mode, block.odd) else {
+            continue;
+        };
+        let [ours, base, theirs] = &block.ranges;
+        w.lines(0, &(copied..ours.start));
+        if bracketed {
+            conflicts += 1;
+            if block.odd == Odd::All {
+                w.marker(b'<', Some(0));
+                w.lines(0, ours);
+            }
+            if mode.shows_base() {
+                w.marker(if block.odd == Odd::All { b'|' } else { b'<' }, Some(1));
+                w.lines(1, base);
+            }
+            w.marker(b'=', None);
+        }
+        w.lines(2, theirs);
+        if bracketed {
+            w.marker(b'>', Some(2));
+        }
+        copied = ours.end;
+    }
+    w.lines(0, &(copied..w.files[0].len()));
+    conflicts
+}
+
+/// Compares `ours`, `base` and `theirs` like `diff3 ours base theirs`, and
+/// renders the result in one of `diff3`'s formats.
+///
+/// The ed-script and merge formats work from the same two-way diffs
+/// against the base that [`merge_strings`](crate::merge_strings) merges;
+/// the listing, like `diff3`'s, diffs from ours. Diffs use
+/// `options.algorithm` and `options.limits`. Conflict markers are
+/// `options.marker_size` long and carry the `*_label`s, where `diff3`
+/// would print the file names. The other options do not apply. Inputs that
+/// look binary fail with [`MergeError::Binary`].
+///
+/// ```
+/// use threeway_merge::{Diff3Format, Diff3Mode, MergeOptions, diff3};
+///
+/// let result = diff3(
+///     "a\nb\nc\nd\n",
+///     "a\nB\nc\nd\n",
+///     "a\nb\nc\nD\n",
+///     Diff3Format::EdScript(Diff3Mode::Ed),
+///     &MergeOptions::default(),
+/// )?;
+/// assert_eq!(result.content, "4c\nD\n.\n");
+/// # Ok::<(), threeway_merge::MergeError>(())
+/// ```
+pub fn diff3(
+    base: &str,
+    ours: &str,
+    theirs: &str,
+    format: Diff3Format,
+    options: &MergeOptions,
+) -> Result<Diff3Result, MergeError> {
+    let (base, ours, theirs) = (base.as_bytes(), ours.as_bytes(), theirs.as_bytes());
+    for (side, input) in [("base", base), ("ours", ours), ("theirs", theirs)] {
+        options.limits.check_input(side, input)?;
+        if is_binary(input) {
+            return Err(MergeError::Binary(format!("{} contains a NUL byte", side)));
+        }
+    }
+
+    let watchdog = options.limits.watchdog();
+    let files = [Lines::new(ours), Lines::new(base), Lines::new(theirs)];
+    // `diff3` diffs from ours for the listing and from the base for the
+    // other formats
+    let blocks = if format == Diff3Format::Listing {
+        let hunks = [
+            diff_lines(ours, base, options, &watchdog)?,
+            diff_lines(ours, theirs, options, &watchdog)?,
+        ];
+        blocks(&files, 0, [&hunks[0], &hunks[1]], false)
+    } else {
+        let hunks = [
+            diff_lines(base, ours, options, &watchdog)?,
+            diff_lines(base, theirs, options, &watchdog)?,
+        ];
+        let whole_lines = matches!(format, Diff3Format::EdScript(_));
+        blocks(&files, 1, [&hunks[0], &hunks[1]], whole_lines)
+    };
+
+    let mut w = Writer {
+        out: Vec::new(),
+        files: [&files[0], &files[1], &files[2]],
+        labels: [
+            options.ours_label.as_deref(),
+            options.base_label.as_deref(),
+            options.theirs_label.as_deref(),
+        ],
+        // Like xdiff, 0 means the default
+        marker_size: if options.marker_size == 0 {
+            7
+        } else {
+            options.marker_size
+        },
+    };
+    let conflicts = match format {
+        Diff3Format::Listing => listing(&mut w, &blocks),
+        Diff3Format::EdScript(mode) => ed_script(&mut w, &blocks, mode),
+        Diff3Format::Merge(mode) => merge(&mut w, &blocks, mode),
+    };
+    let content = String::from_utf8(w.out)
+        .unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned());
+    Ok(Diff3Result { content, conflicts })
+}
@@ -0,0 +1,186 @@
+//! Encoding-aware merging of byte inputs.
+//!
+//! Inputs are classified by their byte-order mark. BOMs are stripped and
+//! UTF-16 is transcoded to UTF-8 before `xdl_merge`, so a BOM on only one side
+//! no longer conflicts with the first line. The merged output is re-encoded
+//! afterwards.
+
+use crate::merge::{RawMerge, merge_bytes};
+use crate::types::*;
+use std::borrow::Cow;
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+const UTF32LE_BOM: &[u8] = &[0xFF, 0xFE, 0x00, 0x00];
+const UTF32BE_BOM: &[u8] = &[0x00, 0x00, 0xFE, 0xFF];
+
+/// The encoding of an input, as identified by its byte-order mark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum TextEncoding {
+    /// No byte-order mark. The bytes are merged as-is and need not be valid
+    /// UTF-8.
+    #[default]
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl TextEncoding {
+    /// Detects the encoding of `bytes` from its byte-order mark.
+    ///
+    /// UTF-32 is rejected rather than misread as UTF-16, whose little-endian
+    /// BOM is a prefix of UTF-32LE's.
+    pub fn detect(bytes: &[u8]) -> Result<Self, MergeError> {
+        if bytes.starts_with(UTF32LE_BOM) || bytes.starts_with(UTF32BE_BOM) {
+            Err(MergeError::InvalidInput(
+                "UTF-32 input is not supported".to_string(),
+            ))
+        } else if bytes.starts_with(UTF8_BOM) {
+            Ok(TextEncoding::Utf8Bom)
+        } else if bytes.starts_with(UTF16LE_BOM) {
+            Ok(TextEncoding::Utf16Le)
+        } else if bytes.starts_with(UTF16BE_BOM) {
+            Ok(TextEncoding::Utf16Be)
+        } else {
+            Ok(TextEncoding::Utf8)
+        }
+    }
+
+    fn bom(self) -> &'static [u8] {
+        match self {
+            TextEncoding::Utf8 => &[],
+            TextEncoding::Utf8Bom => UTF8_BOM,
+            TextEncoding::Utf16Le => UTF16LE_BOM,
+            TextEncoding::Utf16Be => UTF16BE_BOM,
+        }
+    }
+
+    /// Strips the BOM and transcodes to UTF-8.
+    fn decode<'a>(self, bytes: &'a [u8], field: &str) -> Result<Cow<'a, [u8]>, MergeError> {
+        let body = &bytes[self.bom().len()..];
+        let from_bytes: fn([u8; 2]) -> u16 = match self {
+            TextEncoding::Utf8 | TextEncoding::Utf8Bom => return Ok(Cow::Borrowed(body)),
+            TextEncoding::Utf16Le => u16::from_le_bytes,
+            TextEncoding::Utf16Be => u16::from_be_bytes,
+        };
+
+        if !body.len().is_multiple_of(2) {
+            return Err(MergeError::InvalidInput(format!(
+                "{} is not valid UTF-16: odd number of bytes",
+                field
+            )));
+        }
+        let units = body
+            .chunks_exact(2)
+            .map(|pair| from_bytes([pair[0], pair[1]]));
+        let text = char::decode_utf16(units)
+            .collect::<Result<String, _>>()
+            .map_err(|err| {
+                MergeError::InvalidInput(format!("{} is not valid UTF-16: {}", field, err))
+            })?;
+        Ok(Cow::Owned(text.into_bytes()))
+    }
+
+    /// Adds the BOM and transcodes from UTF-8.
+    fn encode(self, content: Vec<u8>) -> Vec<u8> {
+        let to_bytes: fn(u16) -> [u8; 2] = match self {
+            TextEncoding::Utf8 => return content,
+            TextEncoding::Utf8Bom => return [UTF8_BOM, &content].concat(),
+            TextEncoding::Utf16Le => u16::to_le_bytes,
+            TextEncoding::Utf16Be => u16::to_be_bytes,
+        };
+
+        let text = String::from_utf8_lossy(&content);
+        let mut out = Vec::with_capacity(2 + text.len() * 2);
+        out.extend_from_slice(self.bom());
+        for unit in text.encode_utf16() {
+            out.extend_from_slice(&to_bytes(unit));
+        }
+        out
+    }
+}
+
+/// The outcome of [`merge_encoded`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EncodedMergeResult {
+    /// The merged content in `encoding`, including its BOM
+    pub content: Vec<u8>,
+    pub conflicts: usize,
+    /// The encoding the content was written in
+    pub encoding: TextEncoding,
+    /// The policy applied when an input was detected as binary
+    pub binary: Option<BinaryPolicy>,
+    /// The line endings detected in each (decoded) input
+    pub line_endings: LineEndings,
+}
+
+impl EncodedMergeResult {
+    /// Returns true if there are any conflicts in the merge result
+    pub fn has_conflicts(&self) -> bool {
+        self.conflicts > 0
+    }
+}
+
+/// Merges byte inputs that may carry a BOM or be UTF-16 encoded.
+///
+/// The output encoding is merged like any other change: it is the encoding of
+/// "ours", unless only "theirs" changed it relative to "base".
+///
+/// When the binary policy applies, the chosen side is returned byte-for-byte
+/// in its original encoding.
+pub fn merge_encoded(
+    base: &[u8],
+    ours: &[u8],
+    theirs: &[u8],
+    options: &MergeOptions,
+) -> Result<EncodedMergeResult, MergeError> {
+    let detect = |bytes: &[u8], field: &str| {
+        TextEncoding::detect(bytes).map_err(|err| match err {
+            MergeError::InvalidInput(msg) => {
+                MergeError::InvalidInput(format!("{}: {}", field, msg))
+            }
+            other => other,
+        })
+    };
+    let base_encoding = detect(base, "base")?;
+    let ours_encoding = detect(ours, "ours")?;
+    let theirs_encoding = detect(theirs, "theirs")?;
+    let encoding = if ours_encoding == base_encoding {
+        theirs_encoding
+    } else {
+        ours_encoding
+    };
+
+    let RawMerge {
+        content,
+        conflicts,
+        binary,
+        line_endings,
+        ..
+    } = merge_bytes(
+        &base_encoding.decode(base, "base")?,
+        &ours_encoding.decode(ours, "ours")?,
+        &theirs_encoding.decode(theirs, "theirs")?,
+        options,
+        &options.limits.watchdog(),
+    )?;
+
+    let (content, encoding) = match binary {
+        Some(BinaryPolicy::TakeTheirs) => (theirs.to_vec(), theirs_encoding),
+        Some(_) => (ours.to_vec(), ours_encoding),
+        None => (encoding.encode(content), encoding),
+    };
+
+    Ok(EncodedMergeResult {
+        content,
+        conflicts,
+        encoding,
+        binary,
+        line_endings,
+    })
+}
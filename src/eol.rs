@@ -0,0 +1,122 @@
+use crate::types::*;
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+/// Classifies the line breaks in `s`. A lone CR is not a line break, as in
+/// xdiff.
+pub(crate) fn detect(s: &[u8]) -> LineEnding {
+    let (lf, crlf) = count(s);
+    match (lf, crlf) {
+        (0, 0) => LineEnding::None,
+        (_, 0) => LineEnding::Lf,
+        (0, _) => LineEnding::Crlf,
+        _ => LineEnding::Mixed,
+    }
+}
+
+/// Counts LF-only and CRLF line breaks.
+fn count(bytes: &[u8]) -> (usize, usize) {
+    let mut lf = 0;
+    let mut crlf = 0;
+    for (i, &b) in bytes.iter().enumerate() {
+        if b == b'\n' {
+            if i > 0 && bytes[i - 1] == b'\r' {
+                crlf += 1;
+            } else {
+                lf += 1;
+            }
+        }
+    }
+    (lf, crlf)
+}
+
+/// Decides which line ending the merged output gets, or `None` to pass the
+/// inputs through untouched.
+pub(crate) fn target(
+    policy: EolPolicy,
+    ours: &[u8],
+    theirs: &[u8],
+    base: &[u8],
+) -> Option<LineEnding> {
+    match policy {
+        EolPolicy::Preserve => None,
+        EolPolicy::Lf => Some(LineEnding::Lf),
+        EolPolicy::Crlf => Some(LineEnding::Crlf),
+        // A mixed "ours" follows its dominant style; with no line breaks in
+        // ours, look at theirs and then base.
+        EolPolicy::MatchOurs => [ours, theirs, base]
+            .into_iter()
+            .map(count)
+            .find(|&(lf, crlf)| lf + crlf > 0)
+            .map(|(lf, crlf)| {
+                if crlf > lf {
+                    LineEnding::Crlf
+                } else {
+                    LineEnding::Lf
+                }
+            })
+            .or(Some(LineEnding::Lf)),
+    }
+}
+
+pub(crate) fn to_lf(s: &[u8]) -> Cow<'_, [u8]> {
+    if !s.windows(2).any(|w| w == b"\r\n") {
+        return Cow::Borrowed(s);
+    }
+    let mut out = Vec::with_capacity(s.len());
+    for (i, &b) in s.iter().enumerate() {
+        if b == b'\r' && s.get(i + 1) == Some(&b'\n') {
+            continue;
+        }
+        out.push(b);
+    }
+    Cow::Owned(out)
+}
+
+/// Converts LF line breaks to CRLF on their way to the inner writer. An LF
+/// that already follows a CR, such as xdiff's newline after a last line
+/// ending in a lone CR, is left alone, also across writes.
+pub(crate) struct CrlfWriter<W> {
+    inner: W,
+    /// Whether the last byte written was a CR
+    after_cr: bool,
+}
+
+impl<W> CrlfWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            after_cr: false,
+        }
+    }
+}
+
+impl<W: Write> Write for CrlfWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn write_all(&mut self, mut buf: &[u8]) -> io::Result<()> {
+        while let Some(at) = buf.iter().position(|&b| b == b'\n') {
+            let after_cr = match at {
+                0 => self.after_cr,
+                _ => buf[at - 1] == b'\r',
+            };
+            self.inner.write_all(&buf[..at])?;
+            self.inner
+                .write_all(if after_cr { b"\n" } else { b"\r\n" })?;
+            self.after_cr = false;
+            buf = &buf[at + 1..];
+        }
+        self.inner.write_all(buf)?;
+        if let Some(&last) = buf.last() {
+            self.after_cr = last == b'\r';
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
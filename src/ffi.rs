@@ -8,12 +8,6 @@ pub struct MmFile {
     pub size: c_long,
 }
 
-#[repr(C)]
-pub struct MmBuffer {
-    pub ptr: *mut c_char,
-    pub size: c_long,
-}
-
 #[repr(C)]
 pub struct XppParam {
     pub flags: c_ulong,
@@ -21,8 +15,12 @@ pub struct XppParam {
     pub ignore_regex_nr: usize,
     pub anchors: *mut *mut c_char,
     pub anchors_nr: usize,
+    pub abort_func: Option<AbortFunc>,
+    pub abort_priv: *mut c_void,
 }
 
+pub type AbortFunc = unsafe extern "C" fn(priv_: *mut c_void) -> c_int;
+
 #[repr(C)]
 pub struct XmpParam {
     pub xpp: XppParam,
@@ -33,6 +31,68 @@ pub struct XmpParam {
     pub ancestor: *const c_char,
     pub file1: *const c_char,
     pub file2: *const c_char,
+    pub resolve: c_int,
+    /// Indexed start, base, separator, end, as `XDL_MARKER_*`
+    pub marker_prefix: [*const c_char; 4],
+    pub marker_suffix: [*const c_char; 4],
+    pub label_prefix: *const c_char,
+    pub label_suffix: *const c_char,
+    pub conflict_func: Option<ConflictFunc>,
+    pub conflict_priv: *mut c_void,
+}
+
+/// `xmconflict_t`: line indices of a conflict in the merge result; `base`
+/// and `base_end` are -1 without a base section.
+#[repr(C)]
+pub struct XmConflict {
+    pub start: c_long,
+    pub ours: c_long,
+    pub ours_end: c_long,
+    pub base: c_long,
+    pub base_end: c_long,
+    pub theirs: c_long,
+    pub theirs_end: c_long,
+    pub end: c_long,
+}
+
+pub type ConflictFunc = unsafe extern "C" fn(priv_: *mut c_void, conflict: *const XmConflict);
+
+pub type AllocFunc = unsafe extern "C" fn(priv_: *mut c_void, size: usize) -> *mut c_char;
+pub type WriteFunc =
+    unsafe extern "C" fn(priv_: *mut c_void, buf: *const c_char, size: usize) -> c_int;
+
+/// Destination of `xdl_merge_to`: streamed to `write` if set, otherwise
+/// filled into one buffer from `alloc`.
+#[repr(C)]
+pub struct XmOutput {
+    pub alloc: Option<AllocFunc>,
+    pub write: Option<WriteFunc>,
+    pub priv_: *mut c_void,
+}
+
+pub type HunkFunc = unsafe extern "C" fn(
+    start_a: c_long,
+    count_a: c_long,
+    start_b: c_long,
+    count_b: c_long,
+    cb_data: *mut c_void,
+) -> c_int;
+
+#[repr(C)]
+pub struct XdEmitCb {
+    pub priv_: *mut c_void,
+    pub out_hunk: *const c_void,
+    pub out_line: *const c_void,
+}
+
+#[repr(C)]
+pub struct XdEmitConf {
+    pub ctxlen: c_long,
+    pub interhunkctxlen: c_long,
+    pub flags: c_ulong,
+    pub find_func: *const c_void,
+    pub find_func_priv: *mut c_void,
+    pub hunk_func: Option<HunkFunc>,
 }
 
 // xdiff constants from xdiff.h
@@ -47,16 +107,33 @@ pub const XDL_MERGE_ZEALOUS_ALNUM: c_int = 3;
 pub const XDL_MERGE_FAVOR_OURS: c_int = 1;
 pub const XDL_MERGE_FAVOR_THEIRS: c_int = 2;
 pub const XDL_MERGE_FAVOR_UNION: c_int = 3;
+pub const XDL_MERGE_FAVOR_UNION_DEDUP: c_int = 4;
+pub const XDL_MERGE_FAVOR_UNION_SORTED: c_int = 5;
+
+pub const XDL_MERGE_RESOLVE_SORTED: c_int = 1 << 0;
 
 pub const XDL_MERGE_DIFF3: c_int = 1;
 pub const XDL_MERGE_ZEALOUS_DIFF3: c_int = 2;
 
 unsafe extern "C" {
-    pub fn xdl_merge(
+    pub fn xdl_merge_to(
         orig: *const MmFile,
         mf1: *const MmFile,
         mf2: *const MmFile,
         xmp: *const XmpParam,
-        result: *mut MmBuffer,
+        out: *const XmOutput,
     ) -> c_int;
+
+    pub fn xdl_diff(
+        mf1: *const MmFile,
+        mf2: *const MmFile,
+        xpp: *const XppParam,
+        xecfg: *const XdEmitConf,
+        ecb: *mut XdEmitCb,
+    ) -> c_int;
+
+    pub fn xdl_hash_record(data: *mut *const c_char, top: *const c_char, flags: c_long) -> c_ulong;
+
+    #[cfg(test)]
+    pub fn xdl_size_add(a: usize, b: usize) -> usize;
 }
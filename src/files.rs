@@ -0,0 +1,119 @@
+//! Merging files on disk.
+//!
+//! Inputs are memory-mapped and handed to `xdl_merge` as they are, so large
+//! files are never read into `String`s. Results are written back with a
+//! temporary file and a rename, so readers never see a half-written file.
+
+use crate::encoding::{EncodedMergeResult, merge_encoded};
+use crate::types::*;
+use memmap2::Mmap;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// Merges three files like `git merge-file`, without reading them into
+/// memory first.
+///
+/// Byte-order marks and UTF-16 are handled as in [`merge_encoded`]. Use
+/// [`EncodedMergeResult::write_to`] to store the result; writing it to
+/// `ours` matches `git merge-file` without `--stdout`.
+///
+/// The files are mapped for the duration of the merge; if another process
+/// modifies one meanwhile, the merge may see a mix of old and new content.
+pub fn merge_files(
+    base: &Path,
+    ours: &Path,
+    theirs: &Path,
+    options: &MergeOptions,
+) -> Result<EncodedMergeResult, MergeError> {
+    let base = MappedFile::open(base)?;
+    let ours = MappedFile::open(ours)?;
+    let theirs = MappedFile::open(theirs)?;
+    merge_encoded(&base, &ours, &theirs, options)
+}
+
+impl EncodedMergeResult {
+    /// Writes the merged content to `path` atomically.
+    ///
+    /// The content goes to a temporary file next to the destination, which
+    /// is then renamed over it. Like git's in-place write, a symlink is
+    /// followed and the file it points to replaced, and an existing file
+    /// keeps its permissions.
+    pub fn write_to(&self, path: &Path) -> Result<(), MergeError> {
+        let io_error = |err: io::Error| MergeError::Io(format!("{}: {}", path.display(), err));
+
+        let target = resolve_symlinks(path).map_err(io_error)?;
+        let dir = match target.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        #[cfg_attr(not(unix), allow(unused_mut))]
+        let mut builder = tempfile::Builder::new();
+        // A new file gets the usual 0666 minus umask rather than 0600
+        #[cfg(unix)]
+        builder.permissions(std::os::unix::fs::PermissionsExt::from_mode(0o666));
+        let mut file = builder.tempfile_in(dir).map_err(io_error)?;
+        file.write_all(&self.content).map_err(io_error)?;
+        if let Ok(metadata) = fs::metadata(&target) {
+            fs::set_permissions(file.path(), metadata.permissions()).map_err(io_error)?;
+        }
+        file.as_file().sync_all().map_err(io_error)?;
+        file.persist(&target).map_err(|err| io_error(err.error))?;
+        Ok(())
+    }
+}
+
+/// The final target of `path`, which need not exist yet.
+fn resolve_symlinks(path: &Path) -> io::Result<PathBuf> {
+    let mut path = path.to_path_buf();
+    // Bounded like the kernel's limit on nested links
+    for _ in 0..40 {
+        match fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                let link = fs::read_link(&path)?;
+                path = match path.parent() {
+                    Some(parent) => parent.join(link),
+                    None => link,
+                };
+            }
+            Ok(_) => return Ok(path),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(path),
+            Err(err) => return Err(err),
+        }
+    }
+    Err(io::Error::other("too many levels of symbolic links"))
+}
+
+/// A read-only mapping of a whole file.
+enum MappedFile {
+    Mapped(Mmap),
+    /// Empty files cannot be mapped on every platform
+    Empty,
+}
+
+impl MappedFile {
+    fn open(path: &Path) -> Result<Self, MergeError> {
+        let io_error = |err: io::Error| MergeError::Io(format!("{}: {}", path.display(), err));
+
+        let file = File::open(path).map_err(io_error)?;
+        if file.metadata().map_err(io_error)?.len() == 0 {
+            return Ok(MappedFile::Empty);
+        }
+        // SAFETY: the mapping is read-only; see the note on `merge_files`
+        // about concurrent modification.
+        let map = unsafe { Mmap::map(&file) }.map_err(io_error)?;
+        Ok(MappedFile::Mapped(map))
+    }
+}
+
+impl Deref for MappedFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            MappedFile::Mapped(map) => map,
+            MappedFile::Empty => &[],
+        }
+    }
+}
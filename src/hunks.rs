@@ -0,0 +1,70 @@
+//! Splitting merged content into clean runs and conflicts, for the
+//! renderers and for bindings that hand structured results to other
+//! languages.
+
+use crate::types::ConflictRegion;
+use std::ops::Range;
+
+/// A run of the merged output: lines both sides agree on, or a conflict.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", rename_all = "lowercase"))]
+pub(crate) enum Hunk {
+    Clean {
+        /// 1-based line of the merged content where the hunk starts
+        line: usize,
+        content: String,
+    },
+    Conflict {
+        /// 1-based line of the opening marker
+        line: usize,
+        ours: String,
+        /// Only with the `diff3` and `zdiff3` styles
+        base: Option<String>,
+        theirs: String,
+    },
+}
+
+/// Splits merged content at the conflicts the merge recorded in
+/// `regions`. The markers themselves are never read, so content lines that
+/// look like markers stay where they are. Results resolved by a favor or a
+/// binary policy have no regions and come back as one clean hunk.
+pub(crate) fn hunks(content: &str, regions: &[ConflictRegion]) -> Vec<Hunk> {
+    // Byte offset of the start of each line, and of the end of the content
+    let mut offsets = vec![0];
+    offsets.extend(content.match_indices('\n').map(|(at, _)| at + 1));
+    if !content.is_empty() && !content.ends_with('\n') {
+        offsets.push(content.len());
+    }
+    let lines = offsets.len() - 1;
+    // Regions of a result deserialized from elsewhere may not fit it
+    let text = |range: &Range<usize>| {
+        let end = range.end.min(lines);
+        content[offsets[range.start.min(end)]..offsets[end]].to_string()
+    };
+
+    let mut hunks = Vec::new();
+    let mut clean = 0;
+    for region in regions {
+        if clean < region.start {
+            hunks.push(Hunk::Clean {
+                line: clean + 1,
+                content: text(&(clean..region.start)),
+            });
+        }
+        hunks.push(Hunk::Conflict {
+            line: region.start + 1,
+            ours: text(&region.ours),
+            base: region.base.as_ref().map(text),
+            theirs: text(&region.theirs),
+        });
+        clean = clean.max(region.end);
+    }
+    if clean < lines {
+        hunks.push(Hunk::Clean {
+            line: clean + 1,
+            content: text(&(clean..lines)),
+        });
+    }
+    hunks
+}
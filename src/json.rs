@@ -0,0 +1,203 @@
+//! Structured three-way merge of JSON documents.
+//!
+//! Objects are merged key by key, so concurrent edits to different keys never
+//! conflict. String leaves and arrays fall back to the xdiff line merge: strings
+//! line by line, arrays one element per line.
+
+use crate::structured::merge_lines;
+use crate::types::*;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// A value that both sides changed in incompatible ways.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JsonConflict {
+    /// JSON Pointer (RFC 6901) to the conflicting value; `""` is the root
+    pub path: String,
+    /// `None` when the key is absent on that side
+    pub base: Option<Value>,
+    pub ours: Option<Value>,
+    pub theirs: Option<Value>,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JsonMergeResult {
+    /// The merged document. Conflicting values keep "ours".
+    pub value: Value,
+    /// `value` serialized with the indentation of "ours"
+    pub content: String,
+    pub conflicts: Vec<JsonConflict>,
+}
+
+impl JsonMergeResult {
+    /// Returns true if the merge was successful without conflicts
+    pub fn is_clean_merge(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+/// Merges three JSON documents.
+///
+/// Key order follows "ours", with keys added only by "theirs" appended. The
+/// output is always valid JSON; when values conflict, "ours" is kept and the
+/// conflict is listed in [`JsonMergeResult::conflicts`].
+pub fn merge_json(base: &str, ours: &str, theirs: &str) -> Result<JsonMergeResult, MergeError> {
+    let parse = |text: &str, field: &str| {
+        serde_json::from_str::<Value>(text).map_err(|err| {
+            MergeError::InvalidInput(format!("{} is not valid JSON: {}", field, err))
+        })
+    };
+    let base_value = parse(base, "base")?;
+    let ours_value = parse(ours, "ours")?;
+    let theirs_value = parse(theirs, "theirs")?;
+
+    let mut conflicts = Vec::new();
+    let value = merge_value(
+        &mut String::new(),
+        Some(&base_value),
+        Some(&ours_value),
+        Some(&theirs_value),
+        &mut conflicts,
+    )
+    .unwrap_or(Value::Null);
+
+    let content = serialize_like(&value, ours)?;
+    Ok(JsonMergeResult {
+        value,
+        content,
+        conflicts,
+    })
+}
+
+fn merge_value(
+    path: &mut String,
+    base: Option<&Value>,
+    ours: Option<&Value>,
+    theirs: Option<&Value>,
+    conflicts: &mut Vec<JsonConflict>,
+) -> Option<Value> {
+    if ours == theirs || theirs == base {
+        return ours.cloned();
+    }
+    // Object equality ignores key order, so objects still go through
+    // `merge_objects` to keep the order of "ours".
+    let both_objects = matches!(
+        (ours, theirs),
+        (Some(Value::Object(_)), Some(Value::Object(_)))
+    );
+    if ours == base && !both_objects {
+        return theirs.cloned();
+    }
+
+    let merged = match (ours, theirs) {
+        (Some(Value::Object(o)), Some(Value::Object(t))) => {
+            let empty = Map::new();
+            let b = match base {
+                Some(Value::Object(b)) => b,
+                _ => &empty,
+            };
+            return Some(Value::Object(merge_objects(path, b, o, t, conflicts)));
+        }
+        (Some(Value::String(o)), Some(Value::String(t))) => {
+            let b = match base {
+                Some(Value::String(b)) => b.as_str(),
+                _ => "",
+            };
+            merge_lines(b, o, t).map(Value::String)
+        }
+        (Some(Value::Array(o)), Some(Value::Array(t))) => {
+            let b = match base {
+                Some(Value::Array(b)) => b.as_slice(),
+                _ => &[],
+            };
+            merge_arrays(b, o, t).map(Value::Array)
+        }
+        _ => None,
+    };
+
+    match merged {
+        Some(value) => Some(value),
+        None => {
+            conflicts.push(JsonConflict {
+                path: path.clone(),
+                base: base.cloned(),
+                ours: ours.cloned(),
+                theirs: theirs.cloned(),
+            });
+            ours.cloned()
+        }
+    }
+}
+
+fn merge_objects(
+    path: &mut String,
+    base: &Map<String, Value>,
+    ours: &Map<String, Value>,
+    theirs: &Map<String, Value>,
+    conflicts: &mut Vec<JsonConflict>,
+) -> Map<String, Value> {
+    let keys = ours
+        .keys()
+        .chain(theirs.keys().filter(|key| !ours.contains_key(*key)));
+
+    let mut merged = Map::new();
+    for key in keys {
+        let len = path.len();
+        path.push('/');
+        path.push_str(&key.replace('~', "~0").replace('/', "~1"));
+        if let Some(value) = merge_value(
+            path,
+            base.get(key),
+            ours.get(key),
+            theirs.get(key),
+            conflicts,
+        ) {
+            merged.insert(key.clone(), value);
+        }
+        path.truncate(len);
+    }
+    merged
+}
+
+/// Merges arrays as one compact element per line, so insertions on both
+/// sides in different places merge cleanly.
+fn merge_arrays(base: &[Value], ours: &[Value], theirs: &[Value]) -> Option<Vec<Value>> {
+    let lines = |values: &[Value]| -> String {
+        values.iter().map(|value| format!("{}\n", value)).collect()
+    };
+    let merged = merge_lines(&lines(base), &lines(ours), &lines(theirs))?;
+    merged
+        .lines()
+        .map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Serializes `value` with the indentation used by `like`: compact when it
+/// has no line breaks, otherwise indented with its first indented line.
+fn serialize_like(value: &Value, like: &str) -> Result<String, MergeError> {
+    let trimmed = like.trim_end();
+    let mut content = match trimmed.find('\n') {
+        None => value.to_string(),
+        Some(newline) => {
+            let indent: String = trimmed[newline + 1..]
+                .chars()
+                .take_while(|c| *c == ' ' || *c == '\t')
+                .collect();
+            let indent = if indent.is_empty() { "  " } else { &indent };
+
+            let mut out = Vec::new();
+            let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+            let mut serializer = serde_json::Serializer::with_formatter(&mut out, formatter);
+            value
+                .serialize(&mut serializer)
+                .map_err(|err| MergeError::Internal(err.to_string()))?;
+            String::from_utf8(out).map_err(|err| MergeError::Internal(err.to_string()))?
+        }
+    };
+    if like.ends_with('\n') {
+        content.push('\n');
+    }
+    Ok(content)
+}
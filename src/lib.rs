@@ -45,10 +45,109 @@
 //! - **Normal**: Standard 2-way conflict markers
 //! - **Diff3**: Shows base version in conflicts
 //! - **ZealousDiff3**: More aggressive 3-way conflicts
+//!
+//! ## Git Config
+//!
+//! [`MergeOptions::from_git_config`] reads `merge.conflictStyle` and
+//! `diff.algorithm` from git-config files without invoking git:
+//!
+//! ```rust,no_run
+//! use std::path::Path;
+//! use threeway_merge::{ConfigFiles, GitConfig, MergeOptions};
+//!
+//! let files = ConfigFiles::discover(Some(Path::new(".git")));
+//! let config = GitConfig::load(&files)?;
+//! let options = MergeOptions::from_git_config(&config)?;
+//! # Ok::<(), threeway_merge::MergeError>(())
+//! ```
+//!
+//! ## Git Attributes
+//!
+//! [`GitAttributes::merge_decision`] applies per-path `.gitattributes`
+//! settings such as `merge=union`, `-merge` and `conflict-marker-size`:
+//!
+//! ```rust
+//! use threeway_merge::{AttributeSource, GitAttributes, MergeDecision, MergeFavor, MergeOptions};
+//!
+//! let mut attributes = GitAttributes::new();
+//! attributes.add(AttributeSource::Tree(String::new()), "CHANGELOG.md merge=union\n*.png binary\n");
+//!
+//! match attributes.merge_decision("CHANGELOG.md", &MergeOptions::default()) {
+//!     MergeDecision::Text(options) => assert_eq!(options.favor, Some(MergeFavor::Union)),
+//!     _ => unreachable!(),
+//! }
+//! assert!(matches!(
+//!     attributes.merge_decision("assets/logo.png", &MergeOptions::default()),
+//!     MergeDecision::Binary
+//! ));
+//! ```
 
+mod allocator;
+mod attributes;
+#[cfg(feature = "capi")]
+mod capi;
+mod config;
+#[cfg(feature = "csv")]
+mod csv;
+mod diff;
+mod diff3;
+mod encoding;
+mod eol;
+#[cfg(not(pure_rust))]
 mod ffi;
+#[cfg(feature = "fs")]
+mod files;
+mod hunks;
+#[cfg(feature = "json")]
+mod json;
+mod limits;
 mod merge;
+mod moves;
+mod output;
+mod patch;
+#[cfg(feature = "python")]
+mod python;
+mod render;
+// `merge_lines` is shared with the JSON merge, which has its own result type
+#[cfg(any(feature = "json", feature = "toml", feature = "yaml"))]
+#[cfg_attr(not(any(feature = "toml", feature = "yaml")), allow(dead_code))]
+mod structured;
+#[cfg(feature = "toml")]
+mod toml;
 mod types;
+#[cfg(feature = "wasm")]
+mod wasm;
+// Also built for the C backend's unit tests, which compare the two
+#[cfg(any(pure_rust, test))]
+#[cfg_attr(not(pure_rust), allow(dead_code))]
+mod xdiff_rs;
+#[cfg(feature = "yaml")]
+mod yaml;
 
-pub use merge::merge_strings;
+pub use allocator::with_xdiff_allocator;
+pub use attributes::{AttributeSource, AttributeValue, GitAttributes, MergeDecision};
+pub use config::{ConfigFiles, GitConfig, MergeDriver};
+#[cfg(feature = "csv")]
+pub use csv::{CellConflict, CsvMergeOptions, CsvMergeResult, merge_csv};
+pub use diff3::{Diff3Format, Diff3Mode, Diff3Result, diff3};
+pub use encoding::{EncodedMergeResult, TextEncoding, merge_encoded};
+#[cfg(feature = "fs")]
+pub use files::merge_files;
+#[cfg(feature = "json")]
+pub use json::{JsonConflict, JsonMergeResult, merge_json};
+pub use limits::{CancellationToken, MergeLimits};
+pub use merge::{merge_into, merge_into_vec, merge_strings};
+pub use moves::{MoveMergeResult, MoveStatus, MovedBlock, merge_with_moves};
+pub use patch::{PatchResult, apply_patch_3way};
+#[cfg(feature = "python")]
+pub use python::python_module;
+pub use render::{RenderOptions, render_ansi, render_html};
+#[cfg(any(feature = "toml", feature = "yaml"))]
+pub use structured::{KeyConflict, StructuredMergeResult};
+#[cfg(feature = "toml")]
+pub use toml::merge_toml;
 pub use types::*;
+#[cfg(feature = "wasm")]
+pub use wasm::merge_js;
+#[cfg(feature = "yaml")]
+pub use yaml::merge_yaml;
@@ -0,0 +1,139 @@
+//! Size, time and cancellation limits for merges of untrusted input.
+
+#[cfg(not(pure_rust))]
+use crate::ffi::AbortFunc;
+use crate::types::*;
+use std::cell::Cell;
+#[cfg(not(pure_rust))]
+use std::os::raw::{c_int, c_void};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Bounds on the input a merge accepts and the work it may do. Unbounded by
+/// default.
+///
+/// The time and cancellation checks run inside xdiff's diff loops, so even a
+/// pathological `DiffAlgorithm::Minimal` diff stops promptly. Exceeding any
+/// limit fails the merge with [`MergeError::LimitExceeded`].
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct MergeLimits {
+    /// Largest accepted size of each input, in bytes
+    pub max_input_bytes: Option<usize>,
+    /// Largest accepted number of lines in each input
+    pub max_lines: Option<usize>,
+    /// Time one call, such as a merge, may take across all the diffs and
+    /// merges it runs
+    pub timeout: Option<Duration>,
+    /// Aborts merges in progress once cancelled
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub cancel: Option<CancellationToken>,
+}
+
+/// A flag shared between a merge and the code that wants to stop it.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes merges using this token fail with [`MergeError::LimitExceeded`]
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl MergeLimits {
+    /// Checks one input against the size limits.
+    pub(crate) fn check_input(&self, side: &str, input: &[u8]) -> Result<(), MergeError> {
+        if let Some(max) = self.max_input_bytes
+            && input.len() > max
+        {
+            return Err(MergeError::LimitExceeded(format!(
+                "{} is {} bytes, more than the limit of {}",
+                side,
+                input.len(),
+                max
+            )));
+        }
+        if let Some(max) = self.max_lines {
+            let lines = input.iter().filter(|&&b| b == b'\n').count()
+                + usize::from(!input.is_empty() && !input.ends_with(b"\n"));
+            if lines > max {
+                return Err(MergeError::LimitExceeded(format!(
+                    "{} has {} lines, more than the limit of {}",
+                    side, lines, max
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts the clock for one public call, to be shared by every xdiff
+    /// run it makes.
+    pub(crate) fn watchdog(&self) -> Watchdog<'_> {
+        Watchdog {
+            deadline: self.timeout.map(|timeout| Instant::now() + timeout),
+            cancel: self.cancel.as_ref(),
+            tripped: Cell::new(None),
+        }
+    }
+}
+
+/// The state behind xdiff's `abort_func` for one public call.
+pub(crate) struct Watchdog<'a> {
+    deadline: Option<Instant>,
+    cancel: Option<&'a CancellationToken>,
+    tripped: Cell<Option<&'static str>>,
+}
+
+impl Watchdog<'_> {
+    /// The callback and its argument for `xpparam_t`; none when nothing is
+    /// being watched.
+    #[cfg(not(pure_rust))]
+    pub fn abort_func(&self) -> (Option<AbortFunc>, *mut c_void) {
+        if self.deadline.is_none() && self.cancel.is_none() {
+            return (None, std::ptr::null_mut());
+        }
+        (Some(should_abort), self as *const Self as *mut c_void)
+    }
+
+    /// The error to report if xdiff failed because of this watchdog.
+    pub fn error(&self) -> Option<MergeError> {
+        self.tripped
+            .get()
+            .map(|reason| MergeError::LimitExceeded(reason.to_string()))
+    }
+
+    /// Whether the merge should stop, recording why.
+    pub fn check(&self) -> bool {
+        let reason = if self.cancel.is_some_and(CancellationToken::is_cancelled) {
+            "merge was cancelled"
+        } else if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            "merge timed out"
+        } else {
+            return false;
+        };
+        self.tripped.set(Some(reason));
+        true
+    }
+}
+
+#[cfg(not(pure_rust))]
+unsafe extern "C" fn should_abort(data: *mut c_void) -> c_int {
+    // SAFETY: `data` is the `Watchdog` handed out by `abort_func`, which
+    // outlives the xdiff call.
+    let watchdog = unsafe { &*(data as *const Watchdog) };
+    c_int::from(watchdog.check())
+}
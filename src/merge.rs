@@ -1,60 +1,346 @@
-use crate::ffi::{self, MmBuffer, MmFile, XmpParam, XppParam};
+use crate::eol::{self, CrlfWriter};
+#[cfg(not(pure_rust))]
+use crate::ffi::{self, MmFile, XmOutput, XmpParam, XppParam};
+use crate::limits::Watchdog;
+use crate::output::Sink;
 use crate::types::*;
+#[cfg(any(pure_rust, test))]
+use crate::xdiff_rs;
+#[cfg(not(pure_rust))]
 use std::ffi::CString;
-use std::os::raw::{c_char, c_int, c_long, c_ulong};
+use std::io::Write;
+use std::os::raw::c_int;
+#[cfg(not(pure_rust))]
+use std::os::raw::{c_char, c_long, c_ulong, c_void};
+#[cfg(not(pure_rust))]
 use std::ptr;
 
-fn string_to_mmfile(s: &str, field: &str) -> Result<MmFile, MergeError> {
-    let size = c_long::try_from(s.len()).map_err(|_| {
-        MergeError::InvalidInput(format!(
-            "{} is too large for xdiff input size ({})",
-            field,
-            s.len()
-        ))
-    })?;
+/// Git looks for a NUL byte in this many leading bytes to classify content as
+/// binary (`FIRST_FEW_BYTES` in git's xdiff-interface.c).
+const FIRST_FEW_BYTES: usize = 8000;
+
+pub(crate) fn is_binary(s: &[u8]) -> bool {
+    s.iter().take(FIRST_FEW_BYTES).any(|&b| b == 0)
+}
 
+#[cfg(not(pure_rust))]
+pub(crate) fn bytes_to_mmfile(s: &[u8], field: &str) -> Result<MmFile, MergeError> {
     Ok(MmFile {
         ptr: s.as_ptr() as *mut c_char,
-        size,
+        size: mmfile_size(s.len(), field)?,
     })
 }
 
+/// Checks that an input of `len` bytes fits xdiff's `long` sizes, which
+/// are only 32 bits wide on LLP64 targets.
+#[cfg(not(pure_rust))]
+fn mmfile_size(len: usize, field: &str) -> Result<c_long, MergeError> {
+    c_long::try_from(len).map_err(|_| {
+        MergeError::InvalidInput(format!(
+            "{} is too large for xdiff input size ({})",
+            field, len
+        ))
+    })
+}
+
+/// A merge result before its content is decoded.
+pub(crate) struct RawMerge {
+    pub content: Vec<u8>,
+    pub conflicts: usize,
+    pub binary: Option<BinaryPolicy>,
+    pub line_endings: LineEndings,
+    pub regions: Vec<ConflictRegion>,
+}
+
+#[cfg(not(pure_rust))]
+pub(crate) fn diff_flags(algorithm: DiffAlgorithm) -> c_ulong {
+    match algorithm {
+        DiffAlgorithm::Myers => 0,
+        DiffAlgorithm::Minimal => ffi::XDF_NEED_MINIMAL,
+        DiffAlgorithm::Patience => ffi::XDF_PATIENCE_DIFF,
+        DiffAlgorithm::Histogram => ffi::XDF_HISTOGRAM_DIFF,
+    }
+}
+
 pub fn merge_strings(
     base: &str,
     ours: &str,
     theirs: &str,
     options: &MergeOptions,
 ) -> Result<MergeResult, MergeError> {
-    // Keep CStrings alive for the entire function
-    let base_cstr = options
-        .base_label
-        .as_ref()
-        .map(|s| CString::new(s.as_str()))
-        .transpose()
-        .map_err(|_| MergeError::InvalidInput("Invalid base label".to_string()))?;
-
-    let file1_cstr = options
-        .ours_label
-        .as_ref()
-        .map(|s| CString::new(s.as_str()))
-        .transpose()
-        .map_err(|_| MergeError::InvalidInput("Invalid ours label".to_string()))?;
-
-    let file2_cstr = options
-        .theirs_label
-        .as_ref()
-        .map(|s| CString::new(s.as_str()))
-        .transpose()
-        .map_err(|_| MergeError::InvalidInput("Invalid theirs label".to_string()))?;
-
-    let mut flags = 0 as c_ulong;
-    match options.algorithm {
-        DiffAlgorithm::Myers => {}
-        DiffAlgorithm::Minimal => flags |= ffi::XDF_NEED_MINIMAL,
-        DiffAlgorithm::Patience => flags |= ffi::XDF_PATIENCE_DIFF,
-        DiffAlgorithm::Histogram => flags |= ffi::XDF_HISTOGRAM_DIFF,
+    let watchdog = options.limits.watchdog();
+    let merged = merge_bytes(
+        base.as_bytes(),
+        ours.as_bytes(),
+        theirs.as_bytes(),
+        options,
+        &watchdog,
+    )?;
+    let content = String::from_utf8(merged.content)
+        .unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned());
+
+    Ok(MergeResult {
+        content,
+        conflicts: merged.conflicts,
+        binary: merged.binary,
+        line_endings: merged.line_endings,
+        regions: merged.regions,
+    })
+}
+
+/// Merges into `writer`, streaming the result as it is produced instead of
+/// building it in memory.
+///
+/// The result is the same as [`merge_strings`] would return, for inputs
+/// that need not be UTF-8. xdiff hands over many small pieces, so an
+/// unbuffered writer such as a `File` or `TcpStream` is best wrapped in a
+/// [`std::io::BufWriter`]. If the merge fails part way, whatever was
+/// written so far stays written.
+pub fn merge_into<W: Write + ?Sized>(
+    base: &[u8],
+    ours: &[u8],
+    theirs: &[u8],
+    options: &MergeOptions,
+    writer: &mut W,
+) -> Result<MergeSummary, MergeError> {
+    let mut writer = writer;
+    let watchdog = options.limits.watchdog();
+    merge_to_sink(
+        base,
+        ours,
+        theirs,
+        options,
+        &watchdog,
+        Sink::Writer(&mut writer),
+        None,
+    )
+}
+
+/// Merges into `out`, appending to what it holds.
+///
+/// Room for exactly the result is reserved up front and filled in place,
+/// so reusing one buffer across merges avoids allocating at all once it is
+/// large enough. On error, `out` is left as it was.
+pub fn merge_into_vec(
+    base: &[u8],
+    ours: &[u8],
+    theirs: &[u8],
+    options: &MergeOptions,
+    out: &mut Vec<u8>,
+) -> Result<MergeSummary, MergeError> {
+    let len = out.len();
+    let watchdog = options.limits.watchdog();
+    merge_to_sink(base, ours, theirs, options, &watchdog, Sink::Vec(out), None)
+        .inspect_err(|_| out.truncate(len))
+}
+
+/// Byte-level merge shared by every entry point that wants the whole
+/// result in memory. `watchdog` is started once per public call, so the
+/// timeout covers every merge and diff the call runs.
+pub(crate) fn merge_bytes(
+    base: &[u8],
+    ours: &[u8],
+    theirs: &[u8],
+    options: &MergeOptions,
+    watchdog: &Watchdog,
+) -> Result<RawMerge, MergeError> {
+    let mut content = Vec::new();
+    let mut regions = Vec::new();
+    let summary = merge_to_sink(
+        base,
+        ours,
+        theirs,
+        options,
+        watchdog,
+        Sink::Vec(&mut content),
+        Some(&mut regions),
+    )?;
+    Ok(RawMerge {
+        content,
+        conflicts: summary.conflicts,
+        binary: summary.binary,
+        line_endings: summary.line_endings,
+        regions,
+    })
+}
+
+/// Applies the EOL policy around [`merge_normalized`]. Converting line
+/// endings keeps the lines, so `regions` index the content as written.
+fn merge_to_sink(
+    base: &[u8],
+    ours: &[u8],
+    theirs: &[u8],
+    options: &MergeOptions,
+    watchdog: &Watchdog,
+    mut sink: Sink<'_>,
+    regions: Option<&mut Vec<ConflictRegion>>,
+) -> Result<MergeSummary, MergeError> {
+    for (side, input) in [("base", base), ("ours", ours), ("theirs", theirs)] {
+        options.limits.check_input(side, input)?;
     }
 
+    let line_endings = LineEndings {
+        base: eol::detect(base),
+        ours: eol::detect(ours),
+        theirs: eol::detect(theirs),
+    };
+
+    // Binary content is never normalized; the binary policy sees it as-is.
+    let target = eol::target(options.eol, ours, theirs, base)
+        .filter(|_| ![base, ours, theirs].into_iter().any(is_binary));
+
+    let mut merged = match target {
+        None => merge_normalized(base, ours, theirs, options, watchdog, sink, regions)?,
+        Some(target) => {
+            let (base, ours, theirs) = (eol::to_lf(base), eol::to_lf(ours), eol::to_lf(theirs));
+            if target == LineEnding::Crlf {
+                let mut crlf = CrlfWriter::new(&mut sink);
+                merge_normalized(
+                    &base,
+                    &ours,
+                    &theirs,
+                    options,
+                    watchdog,
+                    Sink::Writer(&mut crlf),
+                    regions,
+                )?
+            } else {
+                merge_normalized(&base, &ours, &theirs, options, watchdog, sink, regions)?
+            }
+        }
+    };
+    merged.line_endings = line_endings;
+    Ok(merged)
+}
+
+/// Merges with xdiff, recording where it writes conflicts in `regions`.
+fn merge_normalized(
+    base: &[u8],
+    ours: &[u8],
+    theirs: &[u8],
+    options: &MergeOptions,
+    watchdog: &Watchdog,
+    mut sink: Sink<'_>,
+    regions: Option<&mut Vec<ConflictRegion>>,
+) -> Result<MergeSummary, MergeError> {
+    for (label, side) in [
+        (&options.base_label, "base"),
+        (&options.ours_label, "ours"),
+        (&options.theirs_label, "theirs"),
+    ] {
+        if label.as_ref().is_some_and(|s| s.contains('\0')) {
+            return Err(MergeError::InvalidInput(format!("Invalid {} label", side)));
+        }
+    }
+
+    options.markers.validate()?;
+
+    if c_int::try_from(options.marker_size).is_err() {
+        return Err(MergeError::InvalidInput(format!(
+            "marker_size ({}) exceeds supported range",
+            options.marker_size
+        )));
+    }
+
+    // Binary input is handled before the fast paths, like `git merge-file`
+    // refuses it before merging anything, so the policy also applies when
+    // the sides are identical or one side is unchanged.
+    if let Some(side) = [("base", base), ("ours", ours), ("theirs", theirs)]
+        .into_iter()
+        .find_map(|(side, s)| is_binary(s).then_some(side))
+    {
+        let (content, conflicts) = match options.binary {
+            BinaryPolicy::Error => {
+                return Err(MergeError::Binary(format!("{} contains a NUL byte", side)));
+            }
+            BinaryPolicy::TakeOurs => (ours, 0),
+            BinaryPolicy::TakeTheirs => (theirs, 0),
+            BinaryPolicy::Conflict => (ours, 1),
+        };
+        sink.put(content)?;
+        return Ok(MergeSummary {
+            conflicts,
+            binary: Some(options.binary),
+            line_endings: LineEndings::default(),
+        });
+    }
+
+    // Fast paths for obvious clean-merge outcomes.
+    let clean = if ours == theirs || theirs == base {
+        Some(ours)
+    } else if ours == base {
+        Some(theirs)
+    } else {
+        None
+    };
+    if let Some(content) = clean {
+        sink.put(content)?;
+        return Ok(MergeSummary::default());
+    }
+
+    let conflicts = xdiff_merge(base, ours, theirs, options, watchdog, &mut sink, regions)
+        .map_err(|err| watchdog.error().unwrap_or(err))?;
+    Ok(MergeSummary {
+        conflicts,
+        binary: None,
+        line_endings: LineEndings::default(),
+    })
+}
+
+/// Runs `xdl_merge_to` into `sink` and returns the number of conflicts.
+#[cfg(not(pure_rust))]
+fn xdiff_merge(
+    base: &[u8],
+    ours: &[u8],
+    theirs: &[u8],
+    options: &MergeOptions,
+    watchdog: &Watchdog,
+    sink: &mut Sink<'_>,
+    regions: Option<&mut Vec<ConflictRegion>>,
+) -> Result<usize, MergeError> {
+    let ret =
+        sink.run(|output| xdl_merge_to(base, ours, theirs, options, watchdog, output, regions))?;
+
+    if ret < 0 {
+        return Err(MergeError::Internal(format!(
+            "xdl_merge failed with code {}",
+            ret
+        )));
+    }
+    // A non-negative return is the number of conflicts
+    Ok(ret as usize)
+}
+
+/// Runs `xdl_merge_to` into `output` with the parameters for `options`,
+/// and records where it writes conflicts in `regions`.
+#[cfg(not(pure_rust))]
+fn xdl_merge_to(
+    base: &[u8],
+    ours: &[u8],
+    theirs: &[u8],
+    options: &MergeOptions,
+    watchdog: &Watchdog,
+    output: &XmOutput,
+    regions: Option<&mut Vec<ConflictRegion>>,
+) -> Result<c_int, MergeError> {
+    // Labels were checked for NUL bytes by the caller
+    let c_label = |label: &Option<String>| {
+        label
+            .as_deref()
+            .map(|s| CString::new(s).expect("label without NUL"))
+    };
+    let base_cstr = c_label(&options.base_label);
+    let file1_cstr = c_label(&options.ours_label);
+    let file2_cstr = c_label(&options.theirs_label);
+    // Marker templates were checked by the caller too
+    let c_text = |text: &str| CString::new(text).expect("template without NUL");
+    let templates = options.markers.templates();
+    let prefix_cstrs = templates.map(|template| c_text(&template.prefix));
+    let suffix_cstrs = templates.map(|template| c_text(&template.suffix));
+    let (label_prefix, label_suffix) = options.markers.label_parts();
+    let label_prefix_cstr = c_text(label_prefix);
+    let label_suffix_cstr = c_text(label_suffix);
+
     let level = match options.level {
         MergeLevel::Minimal => ffi::XDL_MERGE_MINIMAL,
         MergeLevel::Eager => ffi::XDL_MERGE_EAGER,
@@ -67,6 +353,8 @@ pub fn merge_strings(
         Some(MergeFavor::Ours) => ffi::XDL_MERGE_FAVOR_OURS,
         Some(MergeFavor::Theirs) => ffi::XDL_MERGE_FAVOR_THEIRS,
         Some(MergeFavor::Union) => ffi::XDL_MERGE_FAVOR_UNION,
+        Some(MergeFavor::UnionDedup) => ffi::XDL_MERGE_FAVOR_UNION_DEDUP,
+        Some(MergeFavor::UnionSorted) => ffi::XDL_MERGE_FAVOR_UNION_SORTED,
     };
 
     let style = match options.style {
@@ -75,106 +363,323 @@ pub fn merge_strings(
         MergeStyle::ZealousDiff3 => ffi::XDL_MERGE_ZEALOUS_DIFF3,
     };
 
-    let marker_size = c_int::try_from(options.marker_size).map_err(|_| {
-        MergeError::InvalidInput(format!(
-            "marker_size ({}) exceeds supported range",
-            options.marker_size
-        ))
-    })?;
-
-    // Fast paths for obvious clean-merge outcomes.
-    if ours == theirs {
-        return Ok(MergeResult {
-            content: ours.to_owned(),
-            conflicts: 0,
-        });
-    }
-    if ours == base {
-        return Ok(MergeResult {
-            content: theirs.to_owned(),
-            conflicts: 0,
-        });
-    }
-    if theirs == base {
-        return Ok(MergeResult {
-            content: ours.to_owned(),
-            conflicts: 0,
-        });
+    let mut resolve = 0;
+    if options.sorted_blocks {
+        resolve |= ffi::XDL_MERGE_RESOLVE_SORTED;
     }
 
+    let (abort_func, abort_priv) = watchdog.abort_func();
     let xmp = XmpParam {
         xpp: XppParam {
-            flags,
+            flags: diff_flags(options.algorithm),
             ignore_regex: ptr::null_mut(),
             ignore_regex_nr: 0,
             anchors: ptr::null_mut(),
             anchors_nr: 0,
+            abort_func,
+            abort_priv,
         },
-        marker_size,
+        // Range checked by the caller
+        marker_size: options.marker_size as c_int,
         level,
         favor,
         style,
         ancestor: base_cstr.as_ref().map_or(ptr::null(), |s| s.as_ptr()),
         file1: file1_cstr.as_ref().map_or(ptr::null(), |s| s.as_ptr()),
         file2: file2_cstr.as_ref().map_or(ptr::null(), |s| s.as_ptr()),
+        resolve,
+        marker_prefix: prefix_cstrs.each_ref().map(|s| s.as_ptr()),
+        marker_suffix: suffix_cstrs.each_ref().map(|s| s.as_ptr()),
+        label_prefix: label_prefix_cstr.as_ptr(),
+        label_suffix: label_suffix_cstr.as_ptr(),
+        conflict_func: regions
+            .is_some()
+            .then_some(record_conflict as ffi::ConflictFunc),
+        conflict_priv: regions.map_or(ptr::null_mut(), |regions| {
+            regions as *mut Vec<ConflictRegion> as *mut c_void
+        }),
     };
 
-    let base_mmf = string_to_mmfile(base, "base")?;
-    let ours_mmf = string_to_mmfile(ours, "ours")?;
-    let theirs_mmf = string_to_mmfile(theirs, "theirs")?;
+    let base_mmf = bytes_to_mmfile(base, "base")?;
+    let ours_mmf = bytes_to_mmfile(ours, "ours")?;
+    let theirs_mmf = bytes_to_mmfile(theirs, "theirs")?;
+
+    Ok(unsafe { ffi::xdl_merge_to(&base_mmf, &ours_mmf, &theirs_mmf, &xmp, output) })
+}
+
+#[cfg(not(pure_rust))]
+unsafe extern "C" fn record_conflict(data: *mut c_void, conflict: *const ffi::XmConflict) {
+    // SAFETY: `data` is the `Vec` passed to `xdl_merge_to`, which outlives
+    // the xdiff call, and xdiff passes a valid conflict.
+    let (regions, c) = unsafe { (&mut *(data as *mut Vec<ConflictRegion>), &*conflict) };
+    // Line counts of a result in memory are never negative
+    let line = |at: c_long| at as usize;
+    regions.push(ConflictRegion {
+        start: line(c.start),
+        ours: line(c.ours)..line(c.ours_end),
+        base: (c.base >= 0).then(|| line(c.base)..line(c.base_end)),
+        theirs: line(c.theirs)..line(c.theirs_end),
+        end: line(c.end),
+    });
+}
+
+#[cfg(pure_rust)]
+use rust_merge as xdiff_merge;
 
-    let mut result = MmBuffer {
-        ptr: ptr::null_mut(),
-        size: 0,
+/// Runs the Rust port of `xdl_merge_to` into `sink` and returns the number
+/// of conflicts.
+#[cfg(any(pure_rust, test))]
+fn rust_merge(
+    base: &[u8],
+    ours: &[u8],
+    theirs: &[u8],
+    options: &MergeOptions,
+    watchdog: &Watchdog,
+    sink: &mut Sink<'_>,
+    regions: Option<&mut Vec<ConflictRegion>>,
+) -> Result<usize, MergeError> {
+    let abort = || watchdog.check();
+    let xmp = xdiff_rs::MergeParams {
+        diff: xdiff_rs::DiffParams {
+            algorithm: options.algorithm,
+            abort: &abort,
+        },
+        marker_size: options.marker_size,
+        level: options.level,
+        favor: options.favor,
+        style: options.style,
+        ancestor: options.base_label.as_deref(),
+        file1: options.ours_label.as_deref(),
+        file2: options.theirs_label.as_deref(),
+        resolve_sorted: options.sorted_blocks,
+        markers: &options.markers,
     };
+    xdiff_rs::merge(base, ours, theirs, &xmp, sink, regions)
+}
 
-    let ret = unsafe { ffi::xdl_merge(&base_mmf, &ours_mmf, &theirs_mmf, &xmp, &mut result) };
+/// Compares the Rust port of xdiff with the C sources on the merge
+/// scenarios, and checks the size arithmetic of the C backend with sizes
+/// too large to allocate.
+#[cfg(all(test, not(pure_rust)))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::raw::c_void;
+    use std::path::Path;
 
-    if ret < 0 {
-        return Err(MergeError::Internal(format!(
-            "xdl_merge failed with code {}",
-            ret
-        )));
+    type Backend = fn(
+        &[u8],
+        &[u8],
+        &[u8],
+        &MergeOptions,
+        &Watchdog,
+        &mut Sink<'_>,
+        Option<&mut Vec<ConflictRegion>>,
+    ) -> Result<usize, MergeError>;
+
+    type Merged = (Vec<u8>, usize, Vec<ConflictRegion>);
+
+    fn run(backend: Backend, inputs: &[Vec<u8>; 3], options: &MergeOptions) -> Merged {
+        let mut content = Vec::new();
+        let mut regions = Vec::new();
+        let watchdog = options.limits.watchdog();
+        let conflicts = backend(
+            &inputs[0],
+            &inputs[1],
+            &inputs[2],
+            options,
+            &watchdog,
+            &mut Sink::Vec(&mut content),
+            Some(&mut regions),
+        )
+        .unwrap();
+        (content, conflicts, regions)
     }
 
-    if result.ptr.is_null() {
-        if result.size == 0 {
-            return Ok(MergeResult {
-                content: String::new(),
-                conflicts: ret as usize,
-            });
+    /// The scenarios of `tests/scenarios`, as written and with CRLF line
+    /// endings.
+    fn scenarios() -> Vec<(String, [Vec<u8>; 3])> {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scenarios");
+        let mut scenarios = Vec::new();
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            let read = |file: &str| fs::read_to_string(path.join(file)).unwrap();
+            let inputs = [read("base.txt"), read("ours.txt"), read("theirs.txt")];
+            scenarios.push((
+                format!("{} (crlf)", name),
+                inputs.clone().map(|s| s.replace('\n', "\r\n").into_bytes()),
+            ));
+            scenarios.push((name, inputs.map(String::into_bytes)));
         }
-        return Err(MergeError::Internal(format!(
-            "xdl_merge returned null buffer with non-zero size ({})",
-            result.size
-        )));
+        scenarios.sort();
+        scenarios
     }
 
-    let result_size = match usize::try_from(result.size) {
-        Ok(size) => size,
-        Err(_) => {
-            unsafe {
-                libc::free(result.ptr as *mut libc::c_void);
+    #[test]
+    fn rust_port_matches_c_xdiff() {
+        let scenarios = scenarios();
+        assert!(!scenarios.is_empty());
+
+        let mut options = Vec::new();
+        for style in [
+            MergeStyle::Normal,
+            MergeStyle::Diff3,
+            MergeStyle::ZealousDiff3,
+        ] {
+            for algorithm in [
+                DiffAlgorithm::Myers,
+                DiffAlgorithm::Minimal,
+                DiffAlgorithm::Patience,
+                DiffAlgorithm::Histogram,
+            ] {
+                for level in [
+                    MergeLevel::Minimal,
+                    MergeLevel::Eager,
+                    MergeLevel::Zealous,
+                    MergeLevel::ZealousAlnum,
+                ] {
+                    for favor in [
+                        None,
+                        Some(MergeFavor::Ours),
+                        Some(MergeFavor::Theirs),
+                        Some(MergeFavor::Union),
+                        Some(MergeFavor::UnionDedup),
+                        Some(MergeFavor::UnionSorted),
+                    ] {
+                        options.push(MergeOptions {
+                            style,
+                            algorithm,
+                            level,
+                            favor,
+                            ..MergeOptions::default()
+                        });
+                    }
+                }
             }
-            return Err(MergeError::Internal(format!(
-                "xdl_merge returned invalid output size ({})",
-                result.size
-            )));
         }
-    };
+        options.push(MergeOptions {
+            style: MergeStyle::Diff3,
+            marker_size: 3,
+            base_label: Some("base".to_string()),
+            ours_label: Some("ours".to_string()),
+            theirs_label: Some("theirs".to_string()),
+            markers: ConflictMarkers::with_prefix("# "),
+            sorted_blocks: true,
+            ..MergeOptions::default()
+        });
 
-    let content = unsafe {
-        let slice = std::slice::from_raw_parts(result.ptr as *const u8, result_size);
-        String::from_utf8_lossy(slice).into_owned()
-    };
+        for (name, inputs) in &scenarios {
+            for options in &options {
+                let c = run(xdiff_merge, inputs, options);
+                let rust = run(rust_merge, inputs, options);
+                assert!(
+                    c == rust,
+                    "{} differs with {:?}:\n{}\n{:?}\n---\n{}\n{:?}",
+                    name,
+                    options,
+                    String::from_utf8_lossy(&c.0),
+                    c.2,
+                    String::from_utf8_lossy(&rust.0),
+                    rust.2
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rust_port_diffs_match_c_xdiff() {
+        for (name, [base, ours, theirs]) in &scenarios() {
+            for algorithm in [
+                DiffAlgorithm::Myers,
+                DiffAlgorithm::Minimal,
+                DiffAlgorithm::Patience,
+                DiffAlgorithm::Histogram,
+            ] {
+                let options = MergeOptions {
+                    algorithm,
+                    ..MergeOptions::default()
+                };
+                let params = xdiff_rs::DiffParams {
+                    algorithm,
+                    abort: &|| false,
+                };
+                for side in [ours, theirs] {
+                    assert_eq!(
+                        crate::diff::diff_lines(base, side, &options, &options.limits.watchdog())
+                            .unwrap(),
+                        xdiff_rs::diff(base, side, &params).unwrap(),
+                        "{} with {:?}",
+                        name,
+                        algorithm
+                    );
+                }
+            }
+        }
+    }
 
-    // Use xdl_merge return value as conflict count (ret >= 0 means success, value is conflict count)
-    let conflicts = ret as usize;
+    #[test]
+    fn input_sizes_must_fit_a_c_long() {
+        assert_eq!(
+            mmfile_size(c_long::MAX as usize, "base").unwrap(),
+            c_long::MAX
+        );
+        assert!(matches!(
+            mmfile_size(c_long::MAX as usize + 1, "ours"),
+            Err(MergeError::InvalidInput(msg)) if msg.contains("ours")
+        ));
+        // Past the range of a C int wherever long is 64 bits wide
+        assert_eq!(
+            mmfile_size(i32::MAX as usize + 1, "theirs").is_ok(),
+            c_long::BITS == 64
+        );
+    }
 
-    // Free the memory allocated by xdiff
-    unsafe {
-        libc::free(result.ptr as *mut libc::c_void);
+    #[test]
+    fn output_sizes_saturate_instead_of_wrapping() {
+        let add = |a, b| unsafe { ffi::xdl_size_add(a, b) };
+        assert_eq!(add(i32::MAX as usize, 1), 1 << 31);
+        assert_eq!(add(usize::MAX - 2, 2), usize::MAX);
+        assert_eq!(add(usize::MAX - 2, 3), usize::MAX);
+        assert_eq!(add(usize::MAX, usize::MAX), usize::MAX);
     }
 
-    Ok(MergeResult { content, conflicts })
+    #[test]
+    fn output_sizes_are_counted_past_4_gib() {
+        /// Records the size xdiff asks for and refuses to allocate it.
+        unsafe extern "C" fn refuse(data: *mut c_void, size: usize) -> *mut c_char {
+            unsafe { *(data as *mut Option<usize>) = Some(size) };
+            ptr::null_mut()
+        }
+
+        // Three markers of the largest size make up more than 6 GiB
+        let options = MergeOptions {
+            marker_size: c_int::MAX as usize,
+            base_label: None,
+            ours_label: None,
+            theirs_label: None,
+            ..MergeOptions::default()
+        };
+        let mut size = None;
+        let output = XmOutput {
+            alloc: Some(refuse),
+            write: None,
+            priv_: &mut size as *mut Option<usize> as *mut c_void,
+        };
+        let ret = xdl_merge_to(
+            b"a\n",
+            b"b\n",
+            b"c\n",
+            &options,
+            &options.limits.watchdog(),
+            &output,
+            None,
+        )
+        .unwrap();
+        assert_eq!(ret, -1);
+
+        // Three markers with their newlines, and one line from each side
+        let expected = 3 * (c_int::MAX as u64 + 1) + 4;
+        assert_eq!(size.unwrap() as u64, expected.min(usize::MAX as u64));
+    }
 }
@@ -0,0 +1,328 @@
+//! Move-aware merging.
+//!
+//! A block that one side deletes in one place and inserts elsewhere looks to
+//! `xdl_merge` like an unrelated deletion and insertion, so edits the other
+//! side made to the block either conflict with the deletion or are lost. Here
+//! such moves are found from the two-way diffs against the base, the other
+//! side's edits are carried over to the block's new location, and the result
+//! goes through the ordinary merge.
+
+use crate::diff::{Hunk, Lines, diff_lines};
+use crate::limits::Watchdog;
+use crate::merge::{is_binary, merge_bytes};
+use crate::types::*;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Blocks shorter than this are never treated as moved.
+const MIN_MOVE_LINES: usize = 3;
+
+/// What happened to the other side's edits of a moved block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum MoveStatus {
+    /// The other side left the block alone
+    Untouched,
+    /// The other side's edits were applied at the new location
+    EditsApplied,
+    /// The edits conflict with changes made while moving; the new location
+    /// holds conflict markers
+    Conflict,
+}
+
+/// A block of lines that one side moved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MovedBlock {
+    /// The side that moved the block
+    pub side: MergeSide,
+    /// The block's 0-based line range in the base
+    pub base_lines: Range<usize>,
+    /// The block's 0-based line range in the moving side's input
+    pub lines: Range<usize>,
+    pub status: MoveStatus,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MoveMergeResult {
+    pub content: String,
+    /// Conflicts of the merge, including one per conflict inside a moved
+    /// block
+    pub conflicts: usize,
+    pub moves: Vec<MovedBlock>,
+}
+
+impl MoveMergeResult {
+    /// Returns true if there are any conflicts in the merge result
+    pub fn has_conflicts(&self) -> bool {
+        self.conflicts > 0
+    }
+
+    /// Returns true if the merge was successful without conflicts
+    pub fn is_clean_merge(&self) -> bool {
+        self.conflicts == 0
+    }
+}
+
+/// Merges like [`merge_strings`](crate::merge_strings), following blocks of
+/// lines that one side moved.
+///
+/// A block counts as moved when one side deletes at least three lines and
+/// inserts a mostly identical block somewhere else. If the other side edited
+/// lines inside the block, those edits are three-way merged into the block at
+/// its new location, and a clash with changes made during the move is
+/// reported as a [`MoveStatus::Conflict`] there. Moves whose block the other
+/// side deleted, moved too, or edited across the block's edges are merged as
+/// ordinary lines.
+pub fn merge_with_moves(
+    base: &str,
+    ours: &str,
+    theirs: &str,
+    options: &MergeOptions,
+) -> Result<MoveMergeResult, MergeError> {
+    let (base, ours, theirs) = (base.as_bytes(), ours.as_bytes(), theirs.as_bytes());
+    for (side, input) in [("base", base), ("ours", ours), ("theirs", theirs)] {
+        options.limits.check_input(side, input)?;
+    }
+    // One clock for every diff and merge below
+    let watchdog = options.limits.watchdog();
+    if [base, ours, theirs].into_iter().any(is_binary) {
+        let merged = merge_bytes(base, ours, theirs, options, &watchdog)?;
+        return Ok(MoveMergeResult {
+            content: decode(merged.content),
+            conflicts: merged.conflicts,
+            moves: Vec::new(),
+        });
+    }
+
+    let base_lines = Lines::new(base);
+    let sides = [
+        (MergeSide::Ours, Lines::new(ours)),
+        (MergeSide::Theirs, Lines::new(theirs)),
+    ];
+    let hunks = [
+        diff_lines(base, ours, options, &watchdog)?,
+        diff_lines(base, theirs, options, &watchdog)?,
+    ];
+    let found = [
+        find_moves(&base_lines, &sides[0].1, &hunks[0], options, &watchdog)?,
+        find_moves(&base_lines, &sides[1].1, &hunks[1], options, &watchdog)?,
+    ];
+
+    let mut edits: [Vec<(Range<usize>, Vec<u8>)>; 2] = [Vec::new(), Vec::new()];
+    let mut moves = Vec::new();
+    let mut block_conflicts = 0;
+    for mover in 0..2 {
+        let other = 1 - mover;
+        let (side, lines) = (&sides[mover].0, &sides[mover].1);
+        for Move {
+            base: base_range,
+            lines: range,
+        } in &found[mover]
+        {
+            let touching: Vec<&Hunk> = hunks[other]
+                .iter()
+                .filter(|hunk| touches(hunk, base_range))
+                .collect();
+            if touching.is_empty() {
+                moves.push(MovedBlock {
+                    side: *side,
+                    base_lines: base_range.clone(),
+                    lines: range.clone(),
+                    status: MoveStatus::Untouched,
+                });
+                continue;
+            }
+            let contained = touching
+                .iter()
+                .all(|hunk| base_range.start <= hunk.old.start && hunk.old.end <= base_range.end);
+            let moved_by_both = found[other]
+                .iter()
+                .any(|other_move| overlaps(&other_move.base, base_range));
+            if !contained || moved_by_both {
+                continue;
+            }
+
+            let other_range = map_range(&hunks[other], base_range);
+            if other_range.is_empty() {
+                continue;
+            }
+            if edits[mover].iter().any(|(r, _)| overlaps(r, range))
+                || edits[other].iter().any(|(r, _)| overlaps(r, &other_range))
+            {
+                continue;
+            }
+
+            let block_base = base_lines.slice(base_range.clone());
+            let moved = lines.slice(range.clone());
+            let edited = sides[other].1.slice(other_range.clone());
+            let merged = match side {
+                MergeSide::Ours => merge_bytes(block_base, moved, edited, options, &watchdog)?,
+                MergeSide::Theirs => merge_bytes(block_base, edited, moved, options, &watchdog)?,
+            };
+
+            edits[mover].push((range.clone(), merged.content));
+            edits[other].push((other_range, block_base.to_vec()));
+            block_conflicts += merged.conflicts;
+            moves.push(MovedBlock {
+                side: *side,
+                base_lines: base_range.clone(),
+                lines: range.clone(),
+                status: if merged.conflicts > 0 {
+                    MoveStatus::Conflict
+                } else {
+                    MoveStatus::EditsApplied
+                },
+            });
+        }
+    }
+    moves.sort_by_key(|block| block.base_lines.start);
+
+    let [ours_edits, theirs_edits] = edits;
+    let ours = apply_edits(&sides[0].1, ours_edits);
+    let theirs = apply_edits(&sides[1].1, theirs_edits);
+    let merged = merge_bytes(base, &ours, &theirs, options, &watchdog)?;
+    Ok(MoveMergeResult {
+        content: decode(merged.content),
+        conflicts: merged.conflicts + block_conflicts,
+        moves,
+    })
+}
+
+fn decode(content: Vec<u8>) -> String {
+    String::from_utf8(content)
+        .unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned())
+}
+
+/// A block deleted from `base` lines and inserted at `lines` of one side.
+struct Move {
+    base: Range<usize>,
+    lines: Range<usize>,
+}
+
+/// Pairs each block a side deleted with the most similar block it inserted
+/// elsewhere.
+fn find_moves(
+    base: &Lines,
+    side: &Lines,
+    hunks: &[Hunk],
+    options: &MergeOptions,
+    watchdog: &Watchdog,
+) -> Result<Vec<Move>, MergeError> {
+    // The inserted blocks each line hash occurs in, so each deleted block is
+    // only diffed against blocks sharing enough lines with it
+    let mut blocks_by_hash: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (i, inserted) in hunks.iter().enumerate() {
+        if inserted.new.len() < MIN_MOVE_LINES {
+            continue;
+        }
+        for l in inserted.new.clone() {
+            let blocks = blocks_by_hash.entry(side.hash(l)).or_default();
+            if blocks.last() != Some(&i) {
+                blocks.push(i);
+            }
+        }
+    }
+
+    let mut used = vec![false; hunks.len()];
+    let mut moves = Vec::new();
+    for (d, deleted) in hunks.iter().enumerate() {
+        if deleted.old.len() < MIN_MOVE_LINES {
+            continue;
+        }
+        let mut shared: HashMap<usize, usize> = HashMap::new();
+        for l in deleted.old.clone() {
+            if is_blank(base.line(l)) {
+                continue;
+            }
+            for &i in blocks_by_hash.get(&base.hash(l)).into_iter().flatten() {
+                *shared.entry(i).or_default() += 1;
+            }
+        }
+        let mut candidates: Vec<usize> = shared
+            .into_iter()
+            .filter(|&(i, count)| i != d && !used[i] && count >= MIN_MOVE_LINES)
+            .map(|(i, _)| i)
+            .collect();
+        candidates.sort_unstable();
+
+        let mut best: Option<(usize, usize)> = None;
+        for i in candidates {
+            let inserted = &hunks[i];
+            let changed: usize = diff_lines(
+                base.slice(deleted.old.clone()),
+                side.slice(inserted.new.clone()),
+                options,
+                watchdog,
+            )?
+            .iter()
+            .map(|hunk| hunk.old.len())
+            .sum();
+            let common = deleted.old.len() - changed;
+            if common >= MIN_MOVE_LINES
+                && 2 * common >= deleted.old.len().max(inserted.new.len())
+                && best.is_none_or(|(c, _)| common > c)
+            {
+                best = Some((common, i));
+            }
+        }
+        if let Some((_, i)) = best {
+            used[i] = true;
+            moves.push(Move {
+                base: deleted.old.clone(),
+                lines: hunks[i].new.clone(),
+            });
+        }
+    }
+    Ok(moves)
+}
+
+fn is_blank(line: &[u8]) -> bool {
+    line.iter().all(u8::is_ascii_whitespace)
+}
+
+/// Whether a hunk changes lines inside `block`. Insertions at either edge
+/// are outside.
+fn touches(hunk: &Hunk, block: &Range<usize>) -> bool {
+    if hunk.old.is_empty() {
+        block.start < hunk.old.start && hunk.old.start < block.end
+    } else {
+        overlaps(&hunk.old, block)
+    }
+}
+
+fn overlaps(a: &Range<usize>, b: &Range<usize>) -> bool {
+    a.start < b.end && b.start < a.end
+}
+
+/// Maps a base line range whose edges no hunk straddles to the lines it
+/// became on the side described by `hunks`.
+fn map_range(hunks: &[Hunk], block: &Range<usize>) -> Range<usize> {
+    let shift = |to: usize, insert_before: bool| {
+        hunks
+            .iter()
+            .filter(|hunk| {
+                hunk.old.end < to || (hunk.old.end == to && (insert_before || !hunk.old.is_empty()))
+            })
+            .fold(to as isize, |at, hunk| {
+                at + hunk.new.len() as isize - hunk.old.len() as isize
+            }) as usize
+    };
+    shift(block.start, true)..shift(block.end, false)
+}
+
+/// Replaces non-overlapping line ranges of a text.
+fn apply_edits(lines: &Lines, mut edits: Vec<(Range<usize>, Vec<u8>)>) -> Vec<u8> {
+    edits.sort_by_key(|(range, _)| range.start);
+    let mut out = Vec::new();
+    let mut at = 0;
+    for (range, replacement) in edits {
+        out.extend_from_slice(lines.slice(at..range.start));
+        out.extend_from_slice(&replacement);
+        at = range.end;
+    }
+    out.extend_from_slice(lines.slice(at..lines.len()));
+    out
+}
@@ -0,0 +1,167 @@
+//! Where merged content goes.
+//!
+//! `xdl_merge_to` either asks for one buffer of the exact result size and
+//! fills it in place, which lets a `Vec<u8>` receive the result without a
+//! copy, or streams the result in pieces to a writer. The Rust backend
+//! does the same through [`Sink`] directly.
+
+#[cfg(not(pure_rust))]
+use crate::ffi::XmOutput;
+use crate::types::*;
+#[cfg(not(pure_rust))]
+use std::any::Any;
+use std::io::{self, Write};
+#[cfg(not(pure_rust))]
+use std::os::raw::{c_char, c_int, c_void};
+#[cfg(not(pure_rust))]
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(not(pure_rust))]
+use std::ptr;
+#[cfg(not(pure_rust))]
+use std::slice;
+
+/// The destination of one merge.
+pub(crate) enum Sink<'a> {
+    /// Appended to, after reserving exactly the room the result needs
+    Vec(&'a mut Vec<u8>),
+    /// Streamed to as the result is produced
+    Writer(&'a mut dyn Write),
+}
+
+impl Sink<'_> {
+    /// Writes content that does not come from xdiff, such as a fast-path
+    /// result.
+    pub fn put(&mut self, bytes: &[u8]) -> Result<(), MergeError> {
+        self.write_all(bytes).map_err(write_error)
+    }
+
+    /// Runs `merge` with the `xmoutput_t` for this sink, and reports a
+    /// failure of the sink in place of the merge's own error.
+    #[cfg(not(pure_rust))]
+    pub fn run(
+        &mut self,
+        merge: impl FnOnce(&XmOutput) -> Result<c_int, MergeError>,
+    ) -> Result<c_int, MergeError> {
+        match self {
+            Sink::Vec(vec) => {
+                let mut state = VecOutput {
+                    vec,
+                    size: None,
+                    out_of_memory: false,
+                };
+                let output = XmOutput {
+                    alloc: Some(alloc_vec),
+                    write: None,
+                    priv_: &mut state as *mut VecOutput as *mut c_void,
+                };
+                let ret = merge(&output)?;
+                if state.out_of_memory {
+                    return Err(MergeError::OutOfMemory);
+                }
+                if ret >= 0
+                    && let Some(size) = state.size
+                {
+                    // SAFETY: xdiff filled all `size` bytes it asked for
+                    // before reporting success.
+                    unsafe { state.vec.set_len(state.vec.len() + size) };
+                }
+                Ok(ret)
+            }
+            Sink::Writer(writer) => {
+                let mut state = WriterOutput {
+                    writer: &mut **writer,
+                    error: None,
+                    panic: None,
+                };
+                let output = XmOutput {
+                    alloc: None,
+                    write: Some(write_to_writer),
+                    priv_: &mut state as *mut WriterOutput as *mut c_void,
+                };
+                let ret = merge(&output)?;
+                if let Some(payload) = state.panic {
+                    panic::resume_unwind(payload);
+                }
+                match state.error {
+                    Some(err) => Err(write_error(err)),
+                    None => Ok(ret),
+                }
+            }
+        }
+    }
+}
+
+impl Write for Sink<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Sink::Vec(vec) => vec.write(buf),
+            Sink::Writer(writer) => writer.write(buf),
+        }
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        match self {
+            Sink::Vec(vec) => vec.write_all(buf),
+            Sink::Writer(writer) => writer.write_all(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Sink::Vec(_) => Ok(()),
+            Sink::Writer(writer) => writer.flush(),
+        }
+    }
+}
+
+pub(crate) fn write_error(err: io::Error) -> MergeError {
+    MergeError::Io(format!("writing merge result: {}", err))
+}
+
+#[cfg(not(pure_rust))]
+struct VecOutput<'a> {
+    vec: &'a mut Vec<u8>,
+    /// The bytes handed to xdiff past the end of `vec`
+    size: Option<usize>,
+    out_of_memory: bool,
+}
+
+#[cfg(not(pure_rust))]
+unsafe extern "C" fn alloc_vec(data: *mut c_void, size: usize) -> *mut c_char {
+    // SAFETY: `data` is the `VecOutput` set up by `Sink::run`, which
+    // outlives the xdiff call.
+    let state = unsafe { &mut *(data as *mut VecOutput) };
+    if state.vec.try_reserve_exact(size).is_err() {
+        state.out_of_memory = true;
+        return ptr::null_mut();
+    }
+    state.size = Some(size);
+    state.vec.spare_capacity_mut().as_mut_ptr() as *mut c_char
+}
+
+#[cfg(not(pure_rust))]
+struct WriterOutput<'a> {
+    writer: &'a mut dyn Write,
+    error: Option<io::Error>,
+    /// A panic in the writer, resumed once xdiff has returned
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+#[cfg(not(pure_rust))]
+unsafe extern "C" fn write_to_writer(data: *mut c_void, buf: *const c_char, size: usize) -> c_int {
+    // SAFETY: `data` is the `WriterOutput` set up by `Sink::run`, and xdiff
+    // passes `size` readable bytes at `buf`.
+    let state = unsafe { &mut *(data as *mut WriterOutput) };
+    let bytes = unsafe { slice::from_raw_parts(buf as *const u8, size) };
+    match panic::catch_unwind(AssertUnwindSafe(|| state.writer.write_all(bytes))) {
+        Ok(Ok(())) => 0,
+        Ok(Err(err)) => {
+            state.error = Some(err);
+            -1
+        }
+        Err(payload) => {
+            state.panic = Some(payload);
+            -1
+        }
+    }
+}
@@ -0,0 +1,260 @@
+//! Three-way application of unified diffs, like `git apply -3`.
+//!
+//! Hunks are first applied as `patch` would, each at its line number or,
+//! failing that, the nearest offset where its old lines match exactly. When
+//! one does not match anywhere, it is placed at its line number, and a
+//! pre-image and a post-image are built from the current text with the old
+//! or new lines of every hunk in place of the lines it covers. The patch
+//! then becomes a merge of the current text and the post-image over the
+//! pre-image, so only real clashes with the patch end up between conflict
+//! markers.
+
+use crate::merge::merge_strings;
+use crate::types::*;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PatchResult {
+    pub content: String,
+    pub conflicts: usize,
+    /// Whether a hunk did not apply and the patch was merged instead
+    pub three_way: bool,
+}
+
+impl PatchResult {
+    /// Returns true if there are any conflicts in the patched content
+    pub fn has_conflicts(&self) -> bool {
+        self.conflicts > 0
+    }
+
+    /// Returns true if the patch went in without conflicts
+    pub fn is_clean_merge(&self) -> bool {
+        self.conflicts == 0
+    }
+}
+
+/// One `@@` section of the patch, with lines including their newlines.
+#[derive(Debug, Default)]
+struct PatchHunk {
+    /// 0-based line in the old file where the hunk's old lines start
+    old_start: usize,
+    old: Vec<String>,
+    new: Vec<String>,
+}
+
+/// Parses `-start,count` or `+start,count` of a hunk header.
+fn parse_range(range: &str, sign: char) -> Option<(usize, usize)> {
+    let range = range.strip_prefix(sign)?;
+    let (start, count) = match range.split_once(',') {
+        Some((start, count)) => (start.parse().ok()?, count.parse().ok()?),
+        None => (range.parse().ok()?, 1),
+    };
+    Some((start, count))
+}
+
+fn parse_patch(patch: &str) -> Result<Vec<PatchHunk>, MergeError> {
+    let invalid = |line: usize, message: &str| {
+        MergeError::InvalidInput(format!("patch line {}: {}", line + 1, message))
+    };
+
+    let mut hunks = Vec::new();
+    let mut files = 0;
+    let mut lines = patch.split_inclusive('\n').enumerate().peekable();
+    while let Some((number, line)) = lines.next() {
+        if line.starts_with("GIT binary patch") || line.starts_with("Binary files ") {
+            return Err(invalid(number, "binary patches are not supported"));
+        }
+        if line.starts_with("+++ ") {
+            files += 1;
+            if files > 1 {
+                return Err(invalid(number, "patch changes more than one file"));
+            }
+            continue;
+        }
+        let Some(header) = line.strip_prefix("@@ ") else {
+            continue;
+        };
+
+        let mut ranges = header.split(' ');
+        let (Some((old_start, old_count)), Some((_, new_count))) = (
+            ranges.next().and_then(|range| parse_range(range, '-')),
+            ranges.next().and_then(|range| parse_range(range, '+')),
+        ) else {
+            return Err(invalid(number, "malformed hunk header"));
+        };
+        let mut hunk = PatchHunk {
+            // A hunk without old lines is numbered after the line it follows
+            old_start: if old_count == 0 {
+                old_start
+            } else {
+                old_start.saturating_sub(1)
+            },
+            ..PatchHunk::default()
+        };
+
+        while hunk.old.len() < old_count || hunk.new.len() < new_count {
+            let Some((number, line)) = lines.next() else {
+                return Err(invalid(number, "hunk ends early"));
+            };
+            // Some tools drop the space of empty context lines
+            let (tag, text) = if line == "\n" || line == "\r\n" {
+                (' ', line)
+            } else {
+                let mut chars = line.chars();
+                (chars.next().unwrap_or(' '), chars.as_str())
+            };
+            let text = text.to_string();
+            match tag {
+                ' ' => {
+                    hunk.old.push(text.clone());
+                    hunk.new.push(text);
+                }
+                '-' => hunk.old.push(text),
+                '+' => hunk.new.push(text),
+                '\\' => {}
+                _ => return Err(invalid(number, "hunk has fewer lines than its header")),
+            }
+            if hunk.old.len() > old_count || hunk.new.len() > new_count {
+                return Err(invalid(number, "hunk has more lines than its header"));
+            }
+            // "\ No newline at end of file" applies to the line before it
+            if let Some(&(_, next)) = lines.peek()
+                && next.starts_with('\\')
+            {
+                lines.next();
+                let last = match tag {
+                    '-' => hunk.old.last_mut(),
+                    '+' => hunk.new.last_mut(),
+                    _ => {
+                        strip_newline(hunk.old.last_mut());
+                        hunk.new.last_mut()
+                    }
+                };
+                strip_newline(last);
+            }
+        }
+        hunks.push(hunk);
+    }
+
+    if hunks.is_empty() {
+        return Err(MergeError::InvalidInput("patch has no hunks".to_string()));
+    }
+    Ok(hunks)
+}
+
+fn strip_newline(line: Option<&mut String>) {
+    if let Some(line) = line
+        && line.ends_with('\n')
+    {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+}
+
+/// Where each hunk goes in `current`, and whether its old lines match
+/// there. Hunks go at the position nearest their line number, after the
+/// shift of the hunks before them, where their old lines match exactly;
+/// hunks that match nowhere go at their line number after that shift.
+fn place_hunks(current: &[&str], hunks: &[PatchHunk]) -> Vec<(usize, bool)> {
+    let mut placed = Vec::with_capacity(hunks.len());
+    let mut done = 0;
+    let mut offset = 0isize;
+    for hunk in hunks {
+        let expected =
+            (hunk.old_start as isize + offset).clamp(done as isize, current.len() as isize);
+        let matches = |at: usize| {
+            at + hunk.old.len() <= current.len()
+                && current[at..at + hunk.old.len()]
+                    .iter()
+                    .zip(&hunk.old)
+                    .all(|(line, old)| line == old)
+        };
+        let expected = expected as usize;
+        let found = (0..=current.len()).find_map(|distance| {
+            [
+                expected.checked_sub(distance),
+                expected.checked_add(distance),
+            ]
+            .into_iter()
+            .flatten()
+            .filter(|&at| at >= done && at <= current.len())
+            .find(|&at| matches(at))
+        });
+
+        let at = found.unwrap_or(expected);
+        placed.push((at, found.is_some()));
+        done = (at + hunk.old.len()).min(current.len());
+        if found.is_some() {
+            offset = at as isize - hunk.old_start as isize;
+        }
+    }
+    placed
+}
+
+/// Strings together the lines of `current` between the hunks placed by
+/// [`place_hunks`] with the old or new lines of each hunk.
+fn splice(
+    current: &[&str],
+    hunks: &[PatchHunk],
+    placed: &[(usize, bool)],
+    side: fn(&PatchHunk) -> &[String],
+) -> String {
+    let mut out = String::new();
+    let mut done = 0;
+    for (hunk, &(at, _)) in hunks.iter().zip(placed) {
+        out.extend(current[done..at].iter().copied());
+        out.extend(side(hunk).iter().map(String::as_str));
+        done = (at + hunk.old.len()).min(current.len());
+    }
+    out.extend(current[done..].iter().copied());
+    out
+}
+
+/// Applies the single-file unified diff `patch` to `current`, falling back
+/// to a three-way merge when it was made against another version.
+///
+/// Hunks that do not match `current` exactly are merged with it, like `git
+/// apply -3` does: changes of the patch that clash with changes in
+/// `current` are left between conflict markers labelled `ours` (`current`)
+/// and `theirs` (the patch) instead of being rejected. Headers and text
+/// before the first hunk are ignored. Patches touching more than one file,
+/// binary patches and malformed hunks fail with
+/// [`MergeError::InvalidInput`].
+///
+/// ```
+/// use threeway_merge::apply_patch_3way;
+///
+/// let patch = "--- a/greeting.txt\n+++ b/greeting.txt\n@@ -1,2 +1,2 @@\n hello\n-world\n+there\n";
+/// let result = apply_patch_3way("intro\nhello\nworld\n", patch)?;
+/// assert_eq!(result.content, "intro\nhello\nthere\n");
+/// assert!(!result.three_way);
+/// # Ok::<(), threeway_merge::MergeError>(())
+/// ```
+pub fn apply_patch_3way(current: &str, patch: &str) -> Result<PatchResult, MergeError> {
+    let hunks = parse_patch(patch)?;
+    let lines: Vec<&str> = current.split_inclusive('\n').collect();
+    let placed = place_hunks(&lines, &hunks);
+    let postimage = splice(&lines, &hunks, &placed, |hunk| &hunk.new);
+    if placed.iter().all(|&(_, matched)| matched) {
+        return Ok(PatchResult {
+            content: postimage,
+            conflicts: 0,
+            three_way: false,
+        });
+    }
+
+    let preimage = splice(&lines, &hunks, &placed, |hunk| &hunk.old);
+    let options = MergeOptions {
+        ours_label: Some("ours".to_string()),
+        theirs_label: Some("theirs".to_string()),
+        ..MergeOptions::default()
+    };
+    let merged = merge_strings(&preimage, current, &postimage, &options)?;
+    Ok(PatchResult {
+        content: merged.content,
+        conflicts: merged.conflicts,
+        three_way: true,
+    })
+}
@@ -0,0 +1,230 @@
+//! Python bindings through PyO3.
+//!
+//! Options are keyword arguments with the spellings of the `serde`
+//! feature, and every `MergeError` variant has an exception class of its
+//! own, all derived from `threeway_merge.MergeError`.
+
+use crate::hunks::{Hunk, hunks};
+use crate::merge::merge_strings;
+use crate::types::{self, MergeOptions};
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use std::time::Duration;
+
+create_exception!(
+    threeway_merge,
+    MergeError,
+    PyException,
+    "Base class of the errors raised by `merge`."
+);
+create_exception!(threeway_merge, InternalError, MergeError);
+create_exception!(threeway_merge, InvalidInputError, MergeError);
+create_exception!(threeway_merge, BinaryError, MergeError);
+create_exception!(threeway_merge, OutOfMemoryError, MergeError);
+create_exception!(threeway_merge, IoError, MergeError);
+create_exception!(threeway_merge, LimitExceededError, MergeError);
+
+fn to_py_err(err: types::MergeError) -> PyErr {
+    let message = err.to_string();
+    match err {
+        types::MergeError::Internal(_) => InternalError::new_err(message),
+        types::MergeError::InvalidInput(_) => InvalidInputError::new_err(message),
+        types::MergeError::Binary(_) => BinaryError::new_err(message),
+        types::MergeError::OutOfMemory => OutOfMemoryError::new_err(message),
+        types::MergeError::Io(_) => IoError::new_err(message),
+        types::MergeError::LimitExceeded(_) => LimitExceededError::new_err(message),
+    }
+}
+
+/// A run of the merged content. `kind` is `"clean"`, with `content` set,
+/// or `"conflict"`, with `ours`, `theirs` and, for the `diff3` and
+/// `zdiff3` styles, `base`.
+#[pyclass(name = "Hunk", module = "threeway_merge", frozen, get_all)]
+struct PyHunk {
+    kind: String,
+    /// 1-based line of the merged content where the hunk starts
+    line: usize,
+    content: Option<String>,
+    ours: Option<String>,
+    base: Option<String>,
+    theirs: Option<String>,
+}
+
+#[pymethods]
+impl PyHunk {
+    fn __repr__(&self) -> String {
+        format!("Hunk(kind={:?}, line={})", self.kind, self.line)
+    }
+}
+
+impl From<Hunk> for PyHunk {
+    fn from(hunk: Hunk) -> Self {
+        match hunk {
+            Hunk::Clean { line, content } => PyHunk {
+                kind: "clean".to_string(),
+                line,
+                content: Some(content),
+                ours: None,
+                base: None,
+                theirs: None,
+            },
+            Hunk::Conflict {
+                line,
+                ours,
+                base,
+                theirs,
+            } => PyHunk {
+                kind: "conflict".to_string(),
+                line,
+                content: None,
+                ours: Some(ours),
+                base,
+                theirs: Some(theirs),
+            },
+        }
+    }
+}
+
+/// The outcome of `merge`: the merged `content`, the number of
+/// `conflicts` left in it, and its `hunks`.
+#[pyclass(name = "MergeResult", module = "threeway_merge", frozen)]
+struct PyMergeResult {
+    #[pyo3(get)]
+    content: String,
+    #[pyo3(get)]
+    conflicts: usize,
+    hunks: Vec<Py<PyHunk>>,
+}
+
+#[pymethods]
+impl PyMergeResult {
+    #[getter]
+    fn hunks<'py>(&self, py: Python<'py>) -> Vec<Bound<'py, PyHunk>> {
+        self.hunks
+            .iter()
+            .map(|hunk| hunk.bind(py).clone())
+            .collect()
+    }
+
+    #[getter]
+    fn has_conflicts(&self) -> bool {
+        self.conflicts > 0
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "MergeResult(conflicts={}, hunks={})",
+            self.conflicts,
+            self.hunks.len()
+        )
+    }
+}
+
+/// Merges `ours` and `theirs` into `base`, like `git merge-file`.
+///
+/// Keyword arguments mirror `MergeOptions`: `style` (`"merge"`, `"diff3"`,
+/// `"zdiff3"`), `algorithm`, `favor`, `level`, `marker_size`, the three
+/// `*_label`s, `binary`, `eol` and `sorted_blocks`, plus the limits
+/// `max_input_bytes`, `max_lines` and `timeout` in seconds. The GIL is
+/// released while merging.
+#[pyfunction]
+#[pyo3(signature = (
+    base, ours, theirs, *,
+    style=None, algorithm=None, favor=None, level=None, marker_size=None,
+    base_label=None, ours_label=None, theirs_label=None,
+    binary=None, eol=None, sorted_blocks=false,
+    max_input_bytes=None, max_lines=None, timeout=None,
+))]
+#[allow(clippy::too_many_arguments)]
+fn merge(
+    py: Python<'_>,
+    base: &str,
+    ours: &str,
+    theirs: &str,
+    style: Option<&str>,
+    algorithm: Option<&str>,
+    favor: Option<&str>,
+    level: Option<&str>,
+    marker_size: Option<usize>,
+    base_label: Option<String>,
+    ours_label: Option<String>,
+    theirs_label: Option<String>,
+    binary: Option<&str>,
+    eol: Option<&str>,
+    sorted_blocks: bool,
+    max_input_bytes: Option<usize>,
+    max_lines: Option<usize>,
+    timeout: Option<f64>,
+) -> PyResult<PyMergeResult> {
+    let mut options = MergeOptions {
+        base_label,
+        ours_label,
+        theirs_label,
+        sorted_blocks,
+        ..MergeOptions::default()
+    };
+    if let Some(style) = style {
+        options.style = style.parse().map_err(to_py_err)?;
+    }
+    if let Some(algorithm) = algorithm {
+        options.algorithm = algorithm.parse().map_err(to_py_err)?;
+    }
+    if let Some(favor) = favor {
+        options.favor = Some(favor.parse().map_err(to_py_err)?);
+    }
+    if let Some(level) = level {
+        options.level = level.parse().map_err(to_py_err)?;
+    }
+    if let Some(marker_size) = marker_size {
+        options.marker_size = marker_size;
+    }
+    if let Some(binary) = binary {
+        options.binary = binary.parse().map_err(to_py_err)?;
+    }
+    if let Some(eol) = eol {
+        options.eol = eol.parse().map_err(to_py_err)?;
+    }
+    options.limits.max_input_bytes = max_input_bytes;
+    options.limits.max_lines = max_lines;
+    if let Some(timeout) = timeout {
+        options.limits.timeout = Some(Duration::try_from_secs_f64(timeout).map_err(|_| {
+            to_py_err(types::MergeError::InvalidInput(format!(
+                "invalid timeout {}",
+                timeout
+            )))
+        })?);
+    }
+
+    let result = py
+        .detach(|| merge_strings(base, ours, theirs, &options))
+        .map_err(to_py_err)?;
+    let hunks = hunks(&result.content, &result.regions)
+        .into_iter()
+        .map(|hunk| Py::new(py, PyHunk::from(hunk)))
+        .collect::<PyResult<_>>()?;
+    Ok(PyMergeResult {
+        content: result.content,
+        conflicts: result.conflicts,
+        hunks,
+    })
+}
+
+/// The `threeway_merge` Python module. Extension builds export it as
+/// `PyInit_threeway_merge`; embedders can register it with
+/// `pyo3::append_to_inittab!`.
+#[pymodule(name = "threeway_merge")]
+pub fn python_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    m.add_function(wrap_pyfunction!(merge, m)?)?;
+    m.add_class::<PyMergeResult>()?;
+    m.add_class::<PyHunk>()?;
+    m.add("MergeError", py.get_type::<MergeError>())?;
+    m.add("InternalError", py.get_type::<InternalError>())?;
+    m.add("InvalidInputError", py.get_type::<InvalidInputError>())?;
+    m.add("BinaryError", py.get_type::<BinaryError>())?;
+    m.add("OutOfMemoryError", py.get_type::<OutOfMemoryError>())?;
+    m.add("IoError", py.get_type::<IoError>())?;
+    m.add("LimitExceededError", py.get_type::<LimitExceededError>())?;
+    Ok(())
+}
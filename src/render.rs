@@ -0,0 +1,486 @@
+//! Side-by-side views of merge results for people who don't read conflict
+//! markers.
+//!
+//! The merged content is split at the conflicts the merge recorded in
+//! [`MergeResult::regions`], and each conflict is shown as columns for
+//! ours, the base (when the merge style recorded it) and theirs. Words that
+//! differ between the columns are highlighted by diffing them token by
+//! token with xdiff. Clean text between conflicts is cut down to a few
+//! lines of context around them.
+
+use crate::diff::diff_lines;
+use crate::hunks::{Hunk, hunks};
+use crate::limits::Watchdog;
+use crate::types::*;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Options for [`render_ansi`] and [`render_html`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RenderOptions {
+    /// Clean lines shown before and after each conflict; the rest of each
+    /// clean region is collapsed into a line count
+    pub context: usize,
+    /// Width of each column of the terminal view, in characters, at least
+    /// 1. Longer lines are cut off with `…`
+    pub width: usize,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            context: 3,
+            width: 40,
+        }
+    }
+}
+
+/// A piece of a line, and whether the side changed it.
+type Token<'a> = (&'a str, bool);
+
+/// One column of a conflict, split into lines.
+struct Column<'a> {
+    /// `ours`, `base` or `theirs`, used for colors and CSS classes
+    class: &'static str,
+    label: &'a str,
+    lines: Vec<Vec<Token<'a>>>,
+}
+
+enum Block<'a> {
+    /// A clean line and its 1-based number in the merged content
+    Line(usize, &'a str),
+    /// Clean lines left out
+    Collapsed(usize),
+    Conflict {
+        /// 1-based position among the conflicts
+        number: usize,
+        /// 1-based line of the opening marker in the merged content
+        line: usize,
+        columns: Vec<Column<'a>>,
+    },
+}
+
+/// Splits text into words, runs of other whitespace, newlines and single
+/// punctuation characters.
+fn tokenize(text: &str) -> Vec<&str> {
+    #[derive(PartialEq)]
+    enum Class {
+        Word,
+        Space,
+        Other,
+    }
+    let class = |c: char| {
+        if c.is_alphanumeric() || c == '_' {
+            Class::Word
+        } else if c.is_whitespace() && c != '\n' {
+            Class::Space
+        } else {
+            Class::Other
+        }
+    };
+
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        let current = class(c);
+        if current != Class::Other {
+            while let Some(&(_, next)) = chars.peek()
+                && class(next) == current
+            {
+                chars.next();
+            }
+        }
+        let end = chars.peek().map_or(text.len(), |&(at, _)| at);
+        tokens.push(&text[start..end]);
+        start = end;
+    }
+    tokens
+}
+
+/// Marks the tokens of `old` and `new` that xdiff finds changed between
+/// them. Tokens are diffed as lines of their ids.
+fn changed<'a>(
+    old: &[&'a str],
+    new: &[&'a str],
+    watchdog: &Watchdog,
+) -> Result<(Vec<bool>, Vec<bool>), MergeError> {
+    let mut ids: HashMap<&'a str, usize> = HashMap::new();
+    let mut encode = |tokens: &[&'a str]| {
+        let mut text = String::new();
+        for token in tokens {
+            let next = ids.len();
+            let id = *ids.entry(*token).or_insert(next);
+            writeln!(text, "{}", id).unwrap();
+        }
+        text
+    };
+    let (old_ids, new_ids) = (encode(old), encode(new));
+
+    let mut marks = (vec![false; old.len()], vec![false; new.len()]);
+    for hunk in diff_lines(
+        old_ids.as_bytes(),
+        new_ids.as_bytes(),
+        &MergeOptions::default(),
+        watchdog,
+    )? {
+        marks.0[hunk.old].fill(true);
+        marks.1[hunk.new].fill(true);
+    }
+    Ok(marks)
+}
+
+/// Groups marked tokens into lines, dropping the newlines.
+fn lines<'a>(tokens: &[&'a str], marks: &[bool]) -> Vec<Vec<Token<'a>>> {
+    let mut lines = Vec::new();
+    let mut line = Vec::new();
+    for (&token, &mark) in tokens.iter().zip(marks) {
+        if token == "\n" {
+            lines.push(std::mem::take(&mut line));
+        } else {
+            line.push((token, mark));
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+fn conflict_columns<'a>(
+    ours: &'a str,
+    base: Option<&'a str>,
+    theirs: &'a str,
+    labels: [&'a str; 3],
+    with_base: bool,
+    watchdog: &Watchdog,
+) -> Result<Vec<Column<'a>>, MergeError> {
+    let (ours, theirs) = (tokenize(ours), tokenize(theirs));
+    let column = |class, label, tokens: &[&'a str], marks: &[bool]| Column {
+        class,
+        label,
+        lines: lines(tokens, marks),
+    };
+
+    // The sides show how they differ from each other, the base what
+    // either side changed
+    let (ours_marks, theirs_marks) = changed(&ours, &theirs, watchdog)?;
+    let mut columns = vec![column("ours", labels[0], &ours, &ours_marks)];
+    if let Some(base) = base {
+        let base = tokenize(base);
+        let (mut base_marks, _) = changed(&base, &ours, watchdog)?;
+        let (by_theirs, _) = changed(&base, &theirs, watchdog)?;
+        for (mark, by_theirs) in base_marks.iter_mut().zip(by_theirs) {
+            *mark |= by_theirs;
+        }
+        columns.push(column("base", labels[1], &base, &base_marks));
+    } else if with_base {
+        columns.push(column("base", labels[1], &[], &[]));
+    }
+    columns.push(column("theirs", labels[2], &theirs, &theirs_marks));
+    Ok(columns)
+}
+
+/// Splits a merge result into the blocks both views show.
+fn blocks<'a>(
+    hunks: &'a [Hunk],
+    options: &'a MergeOptions,
+    render: &RenderOptions,
+) -> Result<Vec<Block<'a>>, MergeError> {
+    let labels = [
+        options.ours_label.as_deref().unwrap_or("ours"),
+        options.base_label.as_deref().unwrap_or("base"),
+        options.theirs_label.as_deref().unwrap_or("theirs"),
+    ];
+    // One clock for all the token diffs of the call
+    let watchdog = options.limits.watchdog();
+    let with_base = hunks
+        .iter()
+        .any(|hunk| matches!(hunk, Hunk::Conflict { base: Some(_), .. }));
+
+    let mut blocks = Vec::new();
+    let mut number = 0;
+    for (index, hunk) in hunks.iter().enumerate() {
+        match hunk {
+            Hunk::Clean { line, content } => {
+                let lines: Vec<&str> = content.split_inclusive('\n').collect();
+                // Context only next to conflicts
+                let head = if index > 0 { render.context } else { 0 };
+                let tail = if index + 1 < hunks.len() {
+                    render.context
+                } else {
+                    0
+                };
+                let numbered = |range: std::ops::Range<usize>| {
+                    range.map(|at| Block::Line(line + at, lines[at]))
+                };
+                if head + tail >= lines.len() {
+                    blocks.extend(numbered(0..lines.len()));
+                } else {
+                    blocks.extend(numbered(0..head));
+                    blocks.push(Block::Collapsed(lines.len() - head - tail));
+                    blocks.extend(numbered(lines.len() - tail..lines.len()));
+                }
+            }
+            Hunk::Conflict {
+                line,
+                ours,
+                base,
+                theirs,
+            } => {
+                number += 1;
+                blocks.push(Block::Conflict {
+                    number,
+                    line: *line,
+                    columns: conflict_columns(
+                        ours,
+                        base.as_deref(),
+                        theirs,
+                        labels,
+                        with_base,
+                        &watchdog,
+                    )?,
+                });
+            }
+        }
+    }
+    Ok(blocks)
+}
+
+fn unchanged_lines(count: usize) -> String {
+    if count == 1 {
+        "1 unchanged line".to_string()
+    } else {
+        format!("{} unchanged lines", count)
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+const DIM: &str = "\x1b[2m";
+const BOLD_RED: &str = "\x1b[1;31m";
+
+fn ansi_color(class: &str) -> &'static str {
+    match class {
+        "ours" => "\x1b[32m",
+        "base" => "\x1b[33m",
+        _ => "\x1b[36m",
+    }
+}
+
+/// Writes one cell of the terminal view, cut off at `width` characters and
+/// padded to it unless it is the last.
+fn ansi_cell(out: &mut String, tokens: &[Token], color: &str, width: usize, pad: bool) {
+    let mut chars: Vec<(char, bool)> = Vec::new();
+    for &(token, mark) in tokens {
+        for c in token.chars() {
+            match c {
+                '\t' => chars.extend([(' ', mark); 4]),
+                c if c.is_control() => {}
+                c => chars.push((c, mark)),
+            }
+        }
+    }
+    if chars.len() > width {
+        chars.truncate(width.saturating_sub(1));
+        chars.push(('…', false));
+    }
+
+    out.push_str(color);
+    let mut marked = false;
+    for &(c, mark) in &chars {
+        if mark != marked {
+            out.push_str(if mark { "\x1b[7m" } else { "\x1b[27m" });
+            marked = mark;
+        }
+        out.push(c);
+    }
+    out.push_str(RESET);
+    if pad {
+        out.extend(std::iter::repeat_n(' ', width - chars.len()));
+    }
+}
+
+/// Renders a merge result for a terminal: clean lines with their line
+/// numbers in `result.content`, and each conflict as colored side-by-side
+/// columns for ours, the base and theirs. Words where ours and theirs
+/// differ, and words of the base that either side changed, are in reverse
+/// video. A `width` of 0 is rejected with [`MergeError::InvalidInput`].
+///
+/// `options` are the options `result` was merged with; their labels head
+/// the columns. The conflicts are the ones in `result.regions`, so lines
+/// that merely look like markers are shown as content. The base column
+/// is only filled for results merged with [`MergeStyle::Diff3`] or
+/// [`MergeStyle::ZealousDiff3`], and left out when no conflict has a base.
+pub fn render_ansi(
+    result: &MergeResult,
+    options: &MergeOptions,
+    render: &RenderOptions,
+) -> Result<String, MergeError> {
+    if render.width == 0 {
+        return Err(MergeError::InvalidInput(
+            "render width must be at least 1".to_string(),
+        ));
+    }
+    let hunks = hunks(&result.content, &result.regions);
+    let conflicts = hunks
+        .iter()
+        .filter(|hunk| matches!(hunk, Hunk::Conflict { .. }))
+        .count();
+    let mut out = String::new();
+    for block in blocks(&hunks, options, render)? {
+        match block {
+            Block::Line(number, line) => {
+                let text = line.trim_end_matches(['\n', '\r']);
+                writeln!(out, "{DIM}{:>5} │{RESET} {}", number, text).unwrap();
+            }
+            Block::Collapsed(count) => {
+                writeln!(out, "{DIM}      ┊ ⋯ {}{RESET}", unchanged_lines(count)).unwrap();
+            }
+            Block::Conflict {
+                number,
+                line,
+                columns,
+            } => {
+                writeln!(
+                    out,
+                    "{BOLD_RED}{:>5} ✖ conflict {} of {}{RESET}",
+                    line, number, conflicts
+                )
+                .unwrap();
+                let rows = columns.iter().map(|c| c.lines.len()).max().unwrap_or(0);
+                for row in 0..=rows {
+                    out.push_str("      │ ");
+                    for (index, column) in columns.iter().enumerate() {
+                        if index > 0 {
+                            out.push_str(" │ ");
+                        }
+                        let last = index + 1 == columns.len();
+                        let color = ansi_color(column.class);
+                        if row == 0 {
+                            let heading = [(column.label, false)];
+                            ansi_cell(&mut out, &heading, "\x1b[1m", render.width, !last);
+                        } else {
+                            let tokens = column.lines.get(row - 1).map_or(&[][..], |l| l);
+                            ansi_cell(&mut out, tokens, color, render.width, !last);
+                        }
+                    }
+                    out.push('\n');
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn escape_html(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\n' | '\r' => {}
+            c => out.push(c),
+        }
+    }
+}
+
+/// Renders a merge result as an HTML `<table class="merge">`, with the
+/// same layout as [`render_ansi`].
+///
+/// Each clean run and each conflict is a `<tbody>` of class `clean` or
+/// `conflict`. Line numbers are in `<td class="line">` cells, collapsed
+/// lines in `<tr class="collapsed">` rows, and the conflict columns have
+/// the classes `ours`, `base` and `theirs`, with changed words in
+/// `<mark>`. No styles are included; cells need `white-space: pre-wrap`
+/// to keep indentation.
+pub fn render_html(
+    result: &MergeResult,
+    options: &MergeOptions,
+    render: &RenderOptions,
+) -> Result<String, MergeError> {
+    let hunks = hunks(&result.content, &result.regions);
+    let blocks = blocks(&hunks, options, render)?;
+    let span = blocks
+        .iter()
+        .find_map(|block| match block {
+            Block::Conflict { columns, .. } => Some(columns.len()),
+            _ => None,
+        })
+        .unwrap_or(1);
+
+    let mut out = String::from("<table class=\"merge\">\n");
+    let mut in_clean = false;
+    for block in blocks {
+        let clean = !matches!(block, Block::Conflict { .. });
+        if clean && !in_clean {
+            out.push_str("<tbody class=\"clean\">\n");
+        } else if !clean && in_clean {
+            out.push_str("</tbody>\n");
+        }
+        in_clean = clean;
+        match block {
+            Block::Line(number, line) => {
+                write!(
+                    out,
+                    "<tr><td class=\"line\">{}</td><td colspan=\"{}\">",
+                    number, span
+                )
+                .unwrap();
+                escape_html(&mut out, line);
+                out.push_str("</td></tr>\n");
+            }
+            Block::Collapsed(count) => {
+                writeln!(
+                    out,
+                    "<tr class=\"collapsed\"><td class=\"line\"></td><td colspan=\"{}\">{}</td></tr>",
+                    span,
+                    unchanged_lines(count)
+                )
+                .unwrap();
+            }
+            Block::Conflict {
+                number,
+                line,
+                columns,
+            } => {
+                writeln!(out, "<tbody class=\"conflict\" id=\"conflict-{}\">", number).unwrap();
+                write!(out, "<tr><td class=\"line\">{}</td>", line).unwrap();
+                for column in &columns {
+                    write!(out, "<th class=\"{}\">", column.class).unwrap();
+                    escape_html(&mut out, column.label);
+                    out.push_str("</th>");
+                }
+                out.push_str("</tr>\n");
+                let rows = columns.iter().map(|c| c.lines.len()).max().unwrap_or(0);
+                for row in 0..rows {
+                    out.push_str("<tr><td class=\"line\"></td>");
+                    for column in &columns {
+                        write!(out, "<td class=\"{}\">", column.class).unwrap();
+                        let mut marked = false;
+                        for &(token, mark) in column.lines.get(row).map_or(&[][..], |l| l) {
+                            if mark != marked {
+                                out.push_str(if mark { "<mark>" } else { "</mark>" });
+                                marked = mark;
+                            }
+                            escape_html(&mut out, token);
+                        }
+                        if marked {
+                            out.push_str("</mark>");
+                        }
+                        out.push_str("</td>");
+                    }
+                    out.push_str("</tr>\n");
+                }
+                out.push_str("</tbody>\n");
+            }
+        }
+    }
+    if in_clean {
+        out.push_str("</tbody>\n");
+    }
+    out.push_str("</table>\n");
+    Ok(out)
+}
@@ -0,0 +1,40 @@
+//! Types shared by the key-aware TOML and YAML merges, and the line merge of
+//! string values they share with the JSON merge.
+
+use crate::merge::merge_strings;
+use crate::types::*;
+
+/// A key that both sides changed in incompatible ways.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeyConflict {
+    /// Keys from the document root to the conflicting value; array elements
+    /// are addressed by their index
+    pub path: Vec<String>,
+    /// Each side's value as written in the source format, or `None` when the
+    /// key is absent on that side
+    pub base: Option<String>,
+    pub ours: Option<String>,
+    pub theirs: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StructuredMergeResult {
+    /// The merged document. Conflicting values keep "ours".
+    pub content: String,
+    pub conflicts: Vec<KeyConflict>,
+}
+
+impl StructuredMergeResult {
+    /// Returns true if the merge was successful without conflicts
+    pub fn is_clean_merge(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+/// Line-merges a string scalar; `None` if the line merge conflicts.
+pub(crate) fn merge_lines(base: &str, ours: &str, theirs: &str) -> Option<String> {
+    let result = merge_strings(base, ours, theirs, &MergeOptions::default()).ok()?;
+    result.is_clean_merge().then_some(result.content)
+}
@@ -0,0 +1,236 @@
+//! Key-aware three-way merge of TOML documents.
+//!
+//! "Ours" is edited in place with `toml_edit`, so its comments, key order and
+//! formatting survive; only values taken from "theirs" are rewritten, in the
+//! formatting "theirs" used.
+
+use crate::structured::{KeyConflict, StructuredMergeResult, merge_lines};
+use crate::types::*;
+use toml_edit::{DocumentMut, Item, TableLike, Value};
+
+/// Merges three TOML documents.
+///
+/// Tables merge key by key and arrays of tables element by element when all
+/// sides have the same number of elements. String values that both sides
+/// changed fall back to the xdiff line merge. Anything else changed on both
+/// sides keeps "ours" and is listed in [`StructuredMergeResult::conflicts`].
+pub fn merge_toml(
+    base: &str,
+    ours: &str,
+    theirs: &str,
+) -> Result<StructuredMergeResult, MergeError> {
+    let parse = |text: &str, field: &str| {
+        text.parse::<DocumentMut>().map_err(|err| {
+            MergeError::InvalidInput(format!("{} is not valid TOML: {}", field, err))
+        })
+    };
+    let base_doc = parse(base, "base")?;
+    let mut merged = parse(ours, "ours")?;
+    let theirs_doc = parse(theirs, "theirs")?;
+
+    let mut conflicts = Vec::new();
+    merge_tables(
+        &mut Vec::new(),
+        Some(base_doc.as_table()),
+        merged.as_table_mut(),
+        theirs_doc.as_table(),
+        &mut conflicts,
+    );
+
+    Ok(StructuredMergeResult {
+        content: merged.to_string(),
+        conflicts,
+    })
+}
+
+fn merge_tables(
+    path: &mut Vec<String>,
+    base: Option<&dyn TableLike>,
+    ours: &mut dyn TableLike,
+    theirs: &dyn TableLike,
+    conflicts: &mut Vec<KeyConflict>,
+) {
+    let mut keys: Vec<String> = ours.iter().map(|(key, _)| key.to_string()).collect();
+    keys.extend(
+        theirs
+            .iter()
+            .map(|(key, _)| key.to_string())
+            .filter(|key| !ours.contains_key(key)),
+    );
+
+    for key in keys {
+        path.push(key.clone());
+        let b = base
+            .and_then(|table| table.get(&key))
+            .filter(|i| !i.is_none());
+        let t = theirs.get(&key).filter(|i| !i.is_none());
+        match ours.get_mut(&key).filter(|i| !i.is_none()) {
+            Some(o) => match t {
+                Some(t) => merge_item(path, b, o, t, conflicts),
+                None if b.is_some_and(|b| item_eq(b, o)) => {
+                    ours.remove(&key);
+                }
+                None if b.is_none() => {}
+                None => conflicts.push(conflict(path, b, Some(o), None)),
+            },
+            None => match (b, t) {
+                (_, None) => {}
+                (None, Some(t)) => {
+                    let (theirs_key, _) = theirs.get_key_value(&key).unwrap();
+                    ours.entry_format(theirs_key).or_insert(detached(t));
+                }
+                (Some(b), Some(t)) if item_eq(b, t) => {}
+                (Some(_), Some(_)) => conflicts.push(conflict(path, b, None, t)),
+            },
+        }
+        path.pop();
+    }
+}
+
+/// Merges an item present on both sides into `ours`.
+fn merge_item(
+    path: &mut Vec<String>,
+    base: Option<&Item>,
+    ours: &mut Item,
+    theirs: &Item,
+    conflicts: &mut Vec<KeyConflict>,
+) {
+    let unchanged = |a: &Item, b: Option<&Item>| b.is_some_and(|b| item_eq(a, b));
+    if item_eq(ours, theirs) || unchanged(theirs, base) {
+        return;
+    }
+
+    // Table equality ignores key order, so tables still merge key by key to
+    // keep the order of "ours".
+    if ours.is_table_like() && theirs.is_table_like() {
+        let base = base.and_then(Item::as_table_like);
+        let theirs = theirs.as_table_like().unwrap();
+        merge_tables(
+            path,
+            base,
+            ours.as_table_like_mut().unwrap(),
+            theirs,
+            conflicts,
+        );
+        return;
+    }
+    if unchanged(ours, base) {
+        let position = ours.as_table().and_then(|table| table.position());
+        *ours = detached(theirs);
+        if let Some(table) = ours.as_table_mut() {
+            table.set_position(position);
+        }
+        return;
+    }
+
+    if let (Item::ArrayOfTables(o), Item::ArrayOfTables(t)) = (&mut *ours, theirs) {
+        let b = base.and_then(Item::as_array_of_tables);
+        if o.len() == t.len() && b.is_some_and(|b| b.len() == t.len()) {
+            for (i, t) in t.iter().enumerate() {
+                path.push(i.to_string());
+                let b = b.and_then(|b| b.get(i)).map(|b| b as &dyn TableLike);
+                merge_tables(path, b, o.get_mut(i).unwrap(), t, conflicts);
+                path.pop();
+            }
+            return;
+        }
+    }
+
+    if let (Some(Value::String(o)), Some(Value::String(t))) = (ours.as_value(), theirs.as_value()) {
+        let b = base.and_then(Item::as_str).unwrap_or_default();
+        if let Some(merged) = merge_lines(b, o.value(), t.value()) {
+            let decor = o.decor().clone();
+            let mut value = Value::from(merged);
+            *value.decor_mut() = decor;
+            *ours = Item::Value(value);
+            return;
+        }
+    }
+
+    conflicts.push(conflict(path, base, Some(ours), Some(theirs)));
+}
+
+/// Clones an item from "theirs" for insertion into "ours". Table positions
+/// refer to "theirs", so they are cleared to place the tables after their
+/// parent.
+fn detached(item: &Item) -> Item {
+    let mut item = item.clone();
+    clear_positions(&mut item);
+    item
+}
+
+fn clear_positions(item: &mut Item) {
+    match item {
+        Item::Table(table) => {
+            table.set_position(None);
+            for (_, child) in table.iter_mut() {
+                clear_positions(child);
+            }
+        }
+        Item::ArrayOfTables(array) => {
+            for table in array.iter_mut() {
+                table.set_position(None);
+                for (_, child) in table.iter_mut() {
+                    clear_positions(child);
+                }
+            }
+        }
+        Item::None | Item::Value(_) => {}
+    }
+}
+
+/// Compares items by value, ignoring formatting, comments and key order.
+fn item_eq(a: &Item, b: &Item) -> bool {
+    match (a, b) {
+        (Item::None, Item::None) => true,
+        (Item::Value(a), Item::Value(b)) => value_eq(a, b),
+        (Item::ArrayOfTables(a), Item::ArrayOfTables(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b.iter())
+                    .all(|(a, b)| table_eq(a as &dyn TableLike, b))
+        }
+        _ => match (a.as_table_like(), b.as_table_like()) {
+            (Some(a), Some(b)) => table_eq(a, b),
+            _ => false,
+        },
+    }
+}
+
+fn value_eq(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => a.value() == b.value(),
+        (Value::Integer(a), Value::Integer(b)) => a.value() == b.value(),
+        (Value::Float(a), Value::Float(b)) => a.value().to_bits() == b.value().to_bits(),
+        (Value::Boolean(a), Value::Boolean(b)) => a.value() == b.value(),
+        (Value::Datetime(a), Value::Datetime(b)) => a.value() == b.value(),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| value_eq(a, b))
+        }
+        (Value::InlineTable(a), Value::InlineTable(b)) => table_eq(a, b),
+        _ => false,
+    }
+}
+
+fn table_eq(a: &dyn TableLike, b: &dyn TableLike) -> bool {
+    let len = |table: &dyn TableLike| table.iter().filter(|(_, i)| !i.is_none()).count();
+    len(a) == len(b)
+        && a.iter()
+            .filter(|(_, i)| !i.is_none())
+            .all(|(key, a)| b.get(key).is_some_and(|b| item_eq(a, b)))
+}
+
+fn conflict(
+    path: &[String],
+    base: Option<&Item>,
+    ours: Option<&Item>,
+    theirs: Option<&Item>,
+) -> KeyConflict {
+    let render = |item: Option<&Item>| item.map(|item| item.to_string().trim().to_string());
+    KeyConflict {
+        path: path.to_vec(),
+        base: render(base),
+        ours: render(ours),
+        theirs: render(theirs),
+    }
+}
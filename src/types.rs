@@ -1,4 +1,10 @@
+use crate::limits::MergeLimits;
+use std::ops::Range;
+use std::str::FromStr;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum DiffAlgorithm {
     Myers,
     Minimal,
@@ -7,20 +13,46 @@ pub enum DiffAlgorithm {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MergeStyle {
+    /// Spelled `merge`, as in git's `merge.conflictStyle`
+    #[cfg_attr(feature = "serde", serde(rename = "merge"))]
     Normal,
+    #[cfg_attr(feature = "serde", serde(rename = "diff3"))]
     Diff3,
+    #[cfg_attr(feature = "serde", serde(rename = "zdiff3"))]
     ZealousDiff3,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum MergeFavor {
     Ours,
     Theirs,
     Union,
+    /// Like `Union`, but leaves out lines of "theirs" that "ours" already
+    /// has in the same conflict hunk
+    #[cfg_attr(feature = "serde", serde(rename = "union-dedup"))]
+    UnionDedup,
+    /// Like `UnionDedup`, and interleaves the two sides of each hunk in
+    /// byte order, so sorted files (`.gitignore`, `CODEOWNERS`) stay sorted
+    #[cfg_attr(feature = "serde", serde(rename = "union-sorted"))]
+    UnionSorted,
+}
+
+/// One of the two sides being merged into the base.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum MergeSide {
+    Ours,
+    Theirs,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum MergeLevel {
     Minimal,
     Eager,
@@ -28,7 +60,268 @@ pub enum MergeLevel {
     ZealousAlnum,
 }
 
+/// What to do when an input looks binary (a NUL byte in its first 8000 bytes,
+/// git's heuristic).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum BinaryPolicy {
+    /// Refuse with [`MergeError::Binary`], like `git merge-file`
+    #[default]
+    Error,
+    /// Take "ours" as a clean result
+    TakeOurs,
+    /// Take "theirs" as a clean result
+    TakeTheirs,
+    /// Keep "ours" and report one conflict, like git's binary merge driver
+    Conflict,
+}
+
+/// How line endings are treated around the merge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum EolPolicy {
+    /// Merge the inputs as-is, like `git merge-file`
+    #[default]
+    Preserve,
+    /// Normalize inputs to LF and produce LF output
+    Lf,
+    /// Normalize inputs to LF and produce CRLF output
+    Crlf,
+    /// Normalize inputs to LF and produce the dominant line ending of "ours"
+    MatchOurs,
+}
+
+/// The line-ending style detected in an input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum LineEnding {
+    /// The input contains no line breaks
+    #[default]
+    None,
+    Lf,
+    Crlf,
+    /// Both LF-only and CRLF line breaks
+    Mixed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LineEndings {
+    pub base: LineEnding,
+    pub ours: LineEnding,
+    pub theirs: LineEnding,
+}
+
+/// Accepts git's `diff.algorithm` spellings, case-insensitively.
+impl FromStr for DiffAlgorithm {
+    type Err = MergeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "myers" | "default" => Ok(DiffAlgorithm::Myers),
+            "minimal" => Ok(DiffAlgorithm::Minimal),
+            "patience" => Ok(DiffAlgorithm::Patience),
+            "histogram" => Ok(DiffAlgorithm::Histogram),
+            _ => Err(MergeError::InvalidInput(format!(
+                "unknown diff algorithm '{}'",
+                s
+            ))),
+        }
+    }
+}
+
+/// Accepts git's `merge.conflictStyle` spellings.
+impl FromStr for MergeStyle {
+    type Err = MergeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "merge" => Ok(MergeStyle::Normal),
+            "diff3" => Ok(MergeStyle::Diff3),
+            "zdiff3" => Ok(MergeStyle::ZealousDiff3),
+            _ => Err(MergeError::InvalidInput(format!(
+                "unknown conflict style '{}'",
+                s
+            ))),
+        }
+    }
+}
+
+/// Accepts the names of git's `--ours`, `--theirs` and `--union` options.
+impl FromStr for MergeFavor {
+    type Err = MergeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ours" => Ok(MergeFavor::Ours),
+            "theirs" => Ok(MergeFavor::Theirs),
+            "union" => Ok(MergeFavor::Union),
+            "union-dedup" => Ok(MergeFavor::UnionDedup),
+            "union-sorted" => Ok(MergeFavor::UnionSorted),
+            _ => Err(MergeError::InvalidInput(format!(
+                "unknown merge favor '{}'",
+                s
+            ))),
+        }
+    }
+}
+
+/// Accepts the `serde` spellings.
+impl FromStr for MergeLevel {
+    type Err = MergeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "minimal" => Ok(MergeLevel::Minimal),
+            "eager" => Ok(MergeLevel::Eager),
+            "zealous" => Ok(MergeLevel::Zealous),
+            "zealous-alnum" => Ok(MergeLevel::ZealousAlnum),
+            _ => Err(MergeError::InvalidInput(format!(
+                "unknown merge level '{}'",
+                s
+            ))),
+        }
+    }
+}
+
+/// Accepts the `serde` spellings.
+impl FromStr for BinaryPolicy {
+    type Err = MergeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(BinaryPolicy::Error),
+            "take-ours" => Ok(BinaryPolicy::TakeOurs),
+            "take-theirs" => Ok(BinaryPolicy::TakeTheirs),
+            "conflict" => Ok(BinaryPolicy::Conflict),
+            _ => Err(MergeError::InvalidInput(format!(
+                "unknown binary policy '{}'",
+                s
+            ))),
+        }
+    }
+}
+
+/// Accepts the `serde` spellings.
+impl FromStr for EolPolicy {
+    type Err = MergeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "preserve" => Ok(EolPolicy::Preserve),
+            "lf" => Ok(EolPolicy::Lf),
+            "crlf" => Ok(EolPolicy::Crlf),
+            "match-ours" => Ok(EolPolicy::MatchOurs),
+            _ => Err(MergeError::InvalidInput(format!(
+                "unknown line-ending policy '{}'",
+                s
+            ))),
+        }
+    }
+}
+
+/// Text written around one kind of conflict marker line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct MarkerTemplate {
+    /// Written before the marker characters, e.g. `"-- "` for SQL
+    pub prefix: String,
+    /// Written after the marker and its label, before the line ending
+    pub suffix: String,
+}
+
+/// How conflict marker lines are written, so that conflicted output can
+/// stay valid in formats where `<<<<<<<` is not, e.g. as `# <<<<<<< ours`
+/// comments in YAML. The default writes git's markers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ConflictMarkers {
+    /// The `<<<<<<<` line
+    pub start: MarkerTemplate,
+    /// The `|||||||` line of the `diff3` and `zdiff3` styles
+    pub base: MarkerTemplate,
+    /// The `=======` line
+    pub separator: MarkerTemplate,
+    /// The `>>>>>>>` line
+    pub end: MarkerTemplate,
+    /// How labels follow the marker characters, with `{label}` standing for
+    /// the label, which it must contain once. `" {label}"` by default.
+    pub label: String,
+}
+
+impl Default for ConflictMarkers {
+    fn default() -> Self {
+        Self {
+            start: MarkerTemplate::default(),
+            base: MarkerTemplate::default(),
+            separator: MarkerTemplate::default(),
+            end: MarkerTemplate::default(),
+            label: " {label}".to_string(),
+        }
+    }
+}
+
+impl ConflictMarkers {
+    /// Writes `prefix` before every marker, e.g. `"-- "` to keep the
+    /// markers SQL comments.
+    pub fn with_prefix(prefix: &str) -> Self {
+        let template = MarkerTemplate {
+            prefix: prefix.to_string(),
+            suffix: String::new(),
+        };
+        Self {
+            start: template.clone(),
+            base: template.clone(),
+            separator: template.clone(),
+            end: template,
+            ..Self::default()
+        }
+    }
+
+    /// The templates in marker order: start, base, separator, end.
+    pub(crate) fn templates(&self) -> [&MarkerTemplate; 4] {
+        [&self.start, &self.base, &self.separator, &self.end]
+    }
+
+    /// The text before and after `{label}` in the label format.
+    pub(crate) fn label_parts(&self) -> (&str, &str) {
+        self.label
+            .split_once("{label}")
+            .unwrap_or((&self.label, ""))
+    }
+
+    /// Rejects templates that would split a marker line, or that xdiff
+    /// cannot take, and label formats without exactly one `{label}`.
+    pub(crate) fn validate(&self) -> Result<(), MergeError> {
+        let names = ["start", "base", "separator", "end"];
+        for (name, template) in names.into_iter().zip(self.templates()) {
+            for (part, text) in [("prefix", &template.prefix), ("suffix", &template.suffix)] {
+                if text.contains(['\0', '\r', '\n']) {
+                    return Err(MergeError::InvalidInput(format!(
+                        "{} marker {} contains a NUL or line break",
+                        name, part
+                    )));
+                }
+            }
+        }
+        if self.label.contains(['\0', '\r', '\n']) || self.label.matches("{label}").count() != 1 {
+            return Err(MergeError::InvalidInput(format!(
+                "invalid marker label format '{}'",
+                self.label.escape_debug()
+            )));
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct MergeOptions {
     pub style: MergeStyle,
     pub favor: Option<MergeFavor>,
@@ -38,6 +331,17 @@ pub struct MergeOptions {
     pub base_label: Option<String>,
     pub ours_label: Option<String>,
     pub theirs_label: Option<String>,
+    pub binary: BinaryPolicy,
+    pub eol: EolPolicy,
+    /// Resolves conflicts where base, ours and theirs are all sorted sets of
+    /// lines (import blocks, sorted manifests) with their sorted union,
+    /// minus the lines either side deleted. Lines sort by bytes and must also
+    /// sort between the neighbouring lines, unless those are blank; hunks
+    /// where both sides removed the same line still conflict.
+    pub sorted_blocks: bool,
+    pub limits: MergeLimits,
+    /// Prefixes, suffixes and label format of the conflict markers
+    pub markers: ConflictMarkers,
 }
 
 impl Default for MergeOptions {
@@ -51,14 +355,43 @@ impl Default for MergeOptions {
             base_label: None,
             ours_label: None,
             theirs_label: None,
+            binary: BinaryPolicy::Error,
+            eol: EolPolicy::Preserve,
+            sorted_blocks: false,
+            limits: MergeLimits::default(),
+            markers: ConflictMarkers::default(),
         }
     }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MergeResult {
     pub content: String,
     pub conflicts: usize,
+    /// The policy applied when an input was detected as binary
+    pub binary: Option<BinaryPolicy>,
+    /// The line endings detected in each input
+    pub line_endings: LineEndings,
+    /// Where each conflict was written in `content`, as recorded by the
+    /// merge, so its sides can be told apart without re-reading markers
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub regions: Vec<ConflictRegion>,
+}
+
+/// The lines of one conflict in the merged content, as 0-based line
+/// indices. Markers are not part of the sides' ranges.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConflictRegion {
+    /// The line of the opening marker
+    pub start: usize,
+    pub ours: Range<usize>,
+    /// Only with the `diff3` and `zdiff3` styles
+    pub base: Option<Range<usize>>,
+    pub theirs: Range<usize>,
+    /// The line after the closing marker
+    pub end: usize,
 }
 
 impl MergeResult {
@@ -73,12 +406,44 @@ impl MergeResult {
     }
 }
 
+/// The outcome of a merge whose content went straight to a writer or
+/// buffer, as with [`merge_into`](crate::merge_into).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MergeSummary {
+    pub conflicts: usize,
+    /// The policy applied when an input was detected as binary
+    pub binary: Option<BinaryPolicy>,
+    /// The line endings detected in each input
+    pub line_endings: LineEndings,
+}
+
+impl MergeSummary {
+    /// Returns true if there are any conflicts in the merge result
+    pub fn has_conflicts(&self) -> bool {
+        self.conflicts > 0
+    }
+
+    /// Returns true if the merge was successful without conflicts
+    pub fn is_clean_merge(&self) -> bool {
+        self.conflicts == 0
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum MergeError {
     #[error("Internal merge error: {0}")]
     Internal(String),
     #[error("Invalid input: {0}")]
     InvalidInput(String),
+    #[error("Cannot merge binary content: {0}")]
+    Binary(String),
     #[error("Memory allocation failed")]
     OutOfMemory,
+    #[error("I/O error: {0}")]
+    Io(String),
+    #[error("Merge limit exceeded: {0}")]
+    LimitExceeded(String),
 }
@@ -0,0 +1,55 @@
+//! JavaScript bindings through wasm-bindgen.
+//!
+//! Options and results cross the boundary as plain objects with the field
+//! names and enum spellings of the `serde` feature.
+
+use crate::hunks::{Hunk, hunks};
+use crate::merge::merge_strings;
+use crate::types::*;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+#[derive(Serialize)]
+struct JsMergeResult {
+    #[serde(flatten)]
+    result: MergeResult,
+    hunks: Vec<Hunk>,
+}
+
+/// Merges three strings, like [`merge_strings`]; exported to JavaScript as
+/// `merge`.
+///
+/// `options` is a plain object with any of the fields of [`MergeOptions`],
+/// e.g. `{ style: "zdiff3", algorithm: "histogram" }`; missing fields and
+/// `undefined` take the defaults. The result has `content`, `conflicts`,
+/// `binary`, `line_endings`, `regions` and `hunks`, the merged content split
+/// at the conflicts the merge recorded. Errors are thrown as `Error`s.
+///
+/// `limits.timeout` needs a clock, which `wasm32-unknown-unknown` lacks, so
+/// setting it there throws instead of merging.
+#[wasm_bindgen(js_name = merge)]
+pub fn merge_js(
+    base: &str,
+    ours: &str,
+    theirs: &str,
+    options: JsValue,
+) -> Result<JsValue, JsError> {
+    let options: MergeOptions = if options.is_undefined() || options.is_null() {
+        MergeOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options)?
+    };
+    // `Instant::now` panics without a clock, which would abort the module
+    if cfg!(all(target_arch = "wasm32", target_os = "unknown")) && options.limits.timeout.is_some()
+    {
+        return Err(MergeError::InvalidInput(
+            "limits.timeout is not supported on wasm32-unknown-unknown".to_string(),
+        )
+        .into());
+    }
+    let result = merge_strings(base, ours, theirs, &options)?;
+    let hunks = hunks(&result.content, &result.regions);
+
+    let serializer = serde_wasm_bindgen::Serializer::new().serialize_maps_as_objects(true);
+    Ok(JsMergeResult { result, hunks }.serialize(&serializer)?)
+}
@@ -39,10 +39,19 @@
 # define XDL_UNUSED
 #endif
 
-#define xdl_malloc(x) malloc(x)
-#define xdl_calloc(n, sz) calloc(n, sz)
-#define xdl_free(ptr) free(ptr)
-#define xdl_realloc(ptr, x) realloc(ptr, x)
+/*
+ * Allocations go through the Rust global allocator (see src/allocator.rs),
+ * so they show up in the application's accounting.
+ */
+extern void *threeway_merge_xdl_malloc(size_t size);
+extern void *threeway_merge_xdl_calloc(size_t count, size_t size);
+extern void *threeway_merge_xdl_realloc(void *ptr, size_t size);
+extern void threeway_merge_xdl_free(void *ptr);
+
+#define xdl_malloc(x) threeway_merge_xdl_malloc(x)
+#define xdl_calloc(n, sz) threeway_merge_xdl_calloc(n, sz)
+#define xdl_free(ptr) threeway_merge_xdl_free(ptr)
+#define xdl_realloc(ptr, x) threeway_merge_xdl_realloc(ptr, x)
 
 #define XDL_BUG(msg) do { fprintf(stderr, "fatal: %s\n", msg); exit(128); } while(0)
 
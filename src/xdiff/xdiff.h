@@ -65,6 +65,14 @@ extern "C" {
 #define XDL_MERGE_FAVOR_OURS 1
 #define XDL_MERGE_FAVOR_THEIRS 2
 #define XDL_MERGE_FAVOR_UNION 3
+#define XDL_MERGE_FAVOR_UNION_DEDUP 4
+#define XDL_MERGE_FAVOR_UNION_SORTED 5
+
+/* xdl_merge() error: the result would not fit in an mmbuffer_t */
+#define XDL_MERGE_ETOOLARGE (-2)
+
+/* merge resolvers */
+#define XDL_MERGE_RESOLVE_SORTED (1 << 0)
 
 /* merge output styles */
 #define XDL_MERGE_DIFF3 1
@@ -90,6 +98,10 @@ typedef struct s_xpparam {
 	/* See Documentation/diff-options.txt. */
 	char **anchors;
 	size_t anchors_nr;
+
+	/* polled while diffing; a nonzero return makes the diff fail */
+	int (*abort_func)(void *priv);
+	void *abort_priv;
 } xpparam_t;
 
 typedef struct s_xdemitcb {
@@ -127,6 +139,19 @@ long xdl_mmfile_size(mmfile_t *mmf);
 int xdl_diff(mmfile_t *mf1, mmfile_t *mf2, xpparam_t const *xpp,
 	     xdemitconf_t const *xecfg, xdemitcb_t *ecb);
 
+/*
+ * Where a conflict was written in the merge result, in lines counted from
+ * 0. Each side is the range [side, side_end) between its markers; base
+ * and base_end are -1 unless the style writes the base.
+ */
+typedef struct s_xmconflict {
+	long start;		/* the opening marker */
+	long ours, ours_end;
+	long base, base_end;
+	long theirs, theirs_end;
+	long end;		/* the line after the closing marker */
+} xmconflict_t;
+
 typedef struct s_xmparam {
 	xpparam_t xpp;
 	int marker_size;
@@ -136,12 +161,45 @@ typedef struct s_xmparam {
 	const char *ancestor;	/* label for orig */
 	const char *file1;	/* label for mf1 */
 	const char *file2;	/* label for mf2 */
+	int resolve;		/* XDL_MERGE_RESOLVE_* */
+	/*
+	 * Written before the marker characters and after the label of each
+	 * conflict marker, indexed by XDL_MARKER_*; NULL writes nothing.
+	 */
+	const char *marker_prefix[4];
+	const char *marker_suffix[4];
+	/* Written around labels; NULL for " " and "" */
+	const char *label_prefix;
+	const char *label_suffix;
+	/* Told about each conflict written with markers, if set */
+	void (*conflict_func)(void *priv, xmconflict_t const *conflict);
+	void *conflict_priv;
 } xmparam_t;
 
+/* conflict markers */
+#define XDL_MARKER_START 0
+#define XDL_MARKER_BASE 1
+#define XDL_MARKER_SEPARATOR 2
+#define XDL_MARKER_END 3
+
 #define DEFAULT_CONFLICT_MARKER_SIZE 7
 
+/*
+ * Destination of xdl_merge_to(). If write is set, the result is streamed
+ * to it in pieces, and a negative return fails the merge. Otherwise the
+ * result is sized first, alloc is asked for a buffer of exactly that many
+ * bytes (returning NULL fails the merge), and the result is filled in.
+ */
+typedef struct s_xmoutput {
+	char *(*alloc)(void *priv, size_t size);
+	int (*write)(void *priv, const char *buf, size_t size);
+	void *priv;
+} xmoutput_t;
+
 int xdl_merge(mmfile_t *orig, mmfile_t *mf1, mmfile_t *mf2,
 		xmparam_t const *xmp, mmbuffer_t *result);
+int xdl_merge_to(mmfile_t *orig, mmfile_t *mf1, mmfile_t *mf2,
+		 xmparam_t const *xmp, xmoutput_t const *out);
 
 #ifdef __cplusplus
 }
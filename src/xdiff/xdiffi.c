@@ -63,6 +63,9 @@ static long xdl_split(unsigned long const *ha1, long off1, long lim1,
 	for (ec = 1;; ec++) {
 		int got_snake = 0;
 
+		if (XDL_ABORTED(xenv->xpp))
+			return -1;
+
 		/*
 		 * We need to extend the diagonal "domain" by one. If the next
 		 * values exits the box boundaries we need to change it in the
@@ -353,6 +356,7 @@ int xdl_do_diff(mmfile_t *mf1, mmfile_t *mf2, xpparam_t const *xpp,
 		xenv.mxcost = XDL_MAX_COST_MIN;
 	xenv.snake_cnt = XDL_SNAKE_CNT;
 	xenv.heur_min = XDL_HEUR_MIN_COST;
+	xenv.xpp = xpp;
 
 	dd1.nrec = xe->xdf1.nreff;
 	dd1.ha = xe->xdf1.ha;
@@ -233,6 +233,8 @@ static int fall_back_to_classic_diff(xpparam_t const *xpp, xdfenv_t *env,
 
 	memset(&xpparam, 0, sizeof(xpparam));
 	xpparam.flags = xpp->flags & ~XDF_DIFF_ALGORITHM_MASK;
+	xpparam.abort_func = xpp->abort_func;
+	xpparam.abort_priv = xpp->abort_priv;
 
 	return xdl_fall_back_diff(env, &xpparam,
 				  line1, count1, line2, count2);
@@ -313,6 +315,9 @@ redo:
 	if (count1 <= 0 && count2 <= 0)
 		return 0;
 
+	if (XDL_ABORTED(xpp))
+		return -1;
+
 	if (LINE_END(1) >= MAX_PTR)
 		return -1;
 
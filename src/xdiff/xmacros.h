@@ -31,6 +31,7 @@
 #define XDL_ABS(v) ((v) >= 0 ? (v): -(v))
 #define XDL_ISDIGIT(c) ((c) >= '0' && (c) <= '9')
 #define XDL_ISSPACE(c) (isspace((unsigned char)(c)))
+#define XDL_ABORTED(xpp) ((xpp)->abort_func && (xpp)->abort_func((xpp)->abort_priv))
 #define XDL_ADDBITS(v,b)	((v) + ((v) >> (b)))
 #define XDL_MASKBITS(b)		((1UL << (b)) - 1)
 #define XDL_HASHLONG(v,b)	(XDL_ADDBITS((unsigned long)(v), b) & XDL_MASKBITS(b))
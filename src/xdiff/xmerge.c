@@ -29,6 +29,7 @@ typedef struct s_xdmerge {
 	 * 1 = no conflict, take first,
 	 * 2 = no conflict, take second.
 	 * 3 = no conflict, take both.
+	 * 5 = no conflict, sorted union of both sides.
 	 */
 	int mode;
 	/*
@@ -83,9 +84,9 @@ static int xdl_cleanup_merge(xdmerge_t *c)
 	int count = 0;
 	xdmerge_t *next_c;
 
-	/* were there conflicts? */
+	/* were there conflicts? (saturating, as the count is returned) */
 	for (; c; c = next_c) {
-		if (c->mode == 0)
+		if (c->mode == 0 && count < INT_MAX)
 			count++;
 		next_c = c->next;
 		xdl_free(c);
@@ -93,10 +94,10 @@ static int xdl_cleanup_merge(xdmerge_t *c)
 	return count;
 }
 
-static int xdl_merge_cmp_lines(xdfenv_t *xe1, int i1, xdfenv_t *xe2, int i2,
-		int line_count, long flags)
+static int xdl_merge_cmp_lines(xdfenv_t *xe1, long i1, xdfenv_t *xe2, long i2,
+		long line_count, long flags)
 {
-	int i;
+	long i;
 	xrecord_t **rec1 = xe1->xdf2.recs + i1;
 	xrecord_t **rec2 = xe2->xdf2.recs + i2;
 
@@ -109,44 +110,90 @@ static int xdl_merge_cmp_lines(xdfenv_t *xe1, int i1, xdfenv_t *xe2, int i2,
 	return 0;
 }
 
-static int xdl_recs_copy_0(int use_orig, xdfenv_t *xe, int i, int count, int needs_cr, int add_nl, char *dest)
+/*
+ * The merge result as it is produced. Without a destination only size is
+ * counted, which is how the buffer for the result is sized; with a write
+ * callback the pieces are streamed to it instead of being stored. Sizes
+ * are size_t and saturate at SIZE_MAX, so a result too large to count
+ * fails to allocate instead of wrapping around to a short buffer. Lines
+ * are only counted for a conflict_func.
+ */
+typedef struct s_xdmergeout {
+	char *dest;
+	xmoutput_t const *stream;
+	size_t size;
+	int err;
+	int count_lines;
+	long lines;
+} xdmergeout_t;
+
+static void out_put(xdmergeout_t *out, const char *ptr, size_t len)
+{
+	const char *nl, *end = ptr + len;
+
+	if (!len || out->err)
+		return;
+	if (out->count_lines)
+		for (nl = ptr; (nl = memchr(nl, '\n', end - nl)); nl++)
+			out->lines++;
+	if (out->stream) {
+		if (out->stream->write(out->stream->priv, ptr, len) < 0)
+			out->err = -1;
+	} else if (out->dest)
+		memcpy(out->dest + out->size, ptr, len);
+	out->size = xdl_size_add(out->size, len);
+}
+
+static void out_fill(xdmergeout_t *out, int c, size_t len)
+{
+	char chunk[64];
+
+	if (!out->stream) {
+		if (out->dest)
+			memset(out->dest + out->size, c, len);
+		out->size = xdl_size_add(out->size, len);
+		return;
+	}
+	memset(chunk, c, len < sizeof(chunk) ? len : sizeof(chunk));
+	while (len) {
+		size_t n = len < sizeof(chunk) ? len : sizeof(chunk);
+
+		out_put(out, chunk, n);
+		len -= n;
+	}
+}
+
+static void out_eol(xdmergeout_t *out, int needs_cr)
+{
+	out_put(out, needs_cr ? "\r\n" : "\n", needs_cr ? 2 : 1);
+}
+
+static void xdl_recs_copy_0(int use_orig, xdfenv_t *xe, long i, long count, int needs_cr, int add_nl, xdmergeout_t *out)
 {
 	xrecord_t **recs;
-	int size = 0;
+	long last;
 
 	recs = (use_orig ? xe->xdf1.recs : xe->xdf2.recs) + i;
 
 	if (count < 1)
-		return 0;
-
-	for (i = 0; i < count; size += recs[i++]->size)
-		if (dest)
-			memcpy(dest + size, recs[i]->ptr, recs[i]->size);
-	if (add_nl) {
-		i = recs[count - 1]->size;
-		if (i == 0 || recs[count - 1]->ptr[i - 1] != '\n') {
-			if (needs_cr) {
-				if (dest)
-					dest[size] = '\r';
-				size++;
-			}
-
-			if (dest)
-				dest[size] = '\n';
-			size++;
-		}
-	}
-	return size;
+		return;
+
+	/* Records point into their file one after another */
+	last = recs[count - 1]->size;
+	out_put(out, recs[0]->ptr,
+		(size_t)(recs[count - 1]->ptr + last - recs[0]->ptr));
+	if (add_nl && (last == 0 || recs[count - 1]->ptr[last - 1] != '\n'))
+		out_eol(out, needs_cr);
 }
 
-static int xdl_recs_copy(xdfenv_t *xe, int i, int count, int needs_cr, int add_nl, char *dest)
+static void xdl_recs_copy(xdfenv_t *xe, long i, long count, int needs_cr, int add_nl, xdmergeout_t *out)
 {
-	return xdl_recs_copy_0(0, xe, i, count, needs_cr, add_nl, dest);
+	xdl_recs_copy_0(0, xe, i, count, needs_cr, add_nl, out);
 }
 
-static int xdl_orig_copy(xdfenv_t *xe, int i, int count, int needs_cr, int add_nl, char *dest)
+static void xdl_orig_copy(xdfenv_t *xe, long i, long count, int needs_cr, int add_nl, xdmergeout_t *out)
 {
-	return xdl_recs_copy_0(1, xe, i, count, needs_cr, add_nl, dest);
+	xdl_recs_copy_0(1, xe, i, count, needs_cr, add_nl, out);
 }
 
 /*
@@ -154,7 +201,7 @@ static int xdl_orig_copy(xdfenv_t *xe, int i, int count, int needs_cr, int add_n
  * has no eol, the preceding line, if any), 0 if it ends in LF-only, and
  * -1 if the line ending cannot be determined.
  */
-static int is_eol_crlf(xdfile_t *file, int i)
+static int is_eol_crlf(xdfile_t *file, long i)
 {
 	long size;
 
@@ -193,133 +240,316 @@ static int is_cr_needed(xdfenv_t *xe1, xdfenv_t *xe2, xdmerge_t *m)
 	return needs_cr < 0 ? 0 : needs_cr;
 }
 
-static int fill_conflict_hunk(xdfenv_t *xe1, const char *name1,
-			      xdfenv_t *xe2, const char *name2,
-			      const char *name3,
-			      int size, int i, int style,
-			      xdmerge_t *m, char *dest, int marker_size)
+static void out_str(xdmergeout_t *out, const char *s)
+{
+	if (s)
+		out_put(out, s, strlen(s));
+}
+
+static void out_marker(xdmergeout_t *out, xmparam_t const *xmp, int kind,
+		       int marker_size, const char *name, int needs_cr)
+{
+	out_str(out, xmp->marker_prefix[kind]);
+	out_fill(out, "<|=>"[kind], marker_size);
+	if (name) {
+		out_str(out, xmp->label_prefix ? xmp->label_prefix : " ");
+		out_str(out, name);
+		out_str(out, xmp->label_suffix);
+	}
+	out_str(out, xmp->marker_suffix[kind]);
+	out_eol(out, needs_cr);
+}
+
+static void fill_conflict_hunk(xdfenv_t *xe1, const char *name1,
+			       xdfenv_t *xe2, const char *name2,
+			       const char *name3,
+			       long i, int style,
+			       xdmerge_t *m, xdmergeout_t *out,
+			       xmparam_t const *xmp)
 {
-	int marker1_size = (name1 ? strlen(name1) + 1 : 0);
-	int marker2_size = (name2 ? strlen(name2) + 1 : 0);
-	int marker3_size = (name3 ? strlen(name3) + 1 : 0);
 	int needs_cr = is_cr_needed(xe1, xe2, m);
+	int marker_size = xmp->marker_size;
+	xmconflict_t c = { 0, 0, 0, -1, -1, 0, 0, 0 };
 
 	if (marker_size <= 0)
 		marker_size = DEFAULT_CONFLICT_MARKER_SIZE;
 
 	/* Before conflicting part */
-	size += xdl_recs_copy(xe1, i, m->i1 - i, 0, 0,
-			      dest ? dest + size : NULL);
-
-	if (!dest) {
-		size += marker_size + 1 + needs_cr + marker1_size;
-	} else {
-		memset(dest + size, '<', marker_size);
-		size += marker_size;
-		if (marker1_size) {
-			dest[size] = ' ';
-			memcpy(dest + size + 1, name1, marker1_size - 1);
-			size += marker1_size;
-		}
-		if (needs_cr)
-			dest[size++] = '\r';
-		dest[size++] = '\n';
-	}
+	xdl_recs_copy(xe1, i, m->i1 - i, 0, 0, out);
+	c.start = out->lines;
+	out_marker(out, xmp, XDL_MARKER_START, marker_size, name1, needs_cr);
 
 	/* Postimage from side #1 */
-	size += xdl_recs_copy(xe1, m->i1, m->chg1, needs_cr, 1,
-			      dest ? dest + size : NULL);
+	c.ours = out->lines;
+	xdl_recs_copy(xe1, m->i1, m->chg1, needs_cr, 1, out);
+	c.ours_end = out->lines;
 
 	if (style == XDL_MERGE_DIFF3 || style == XDL_MERGE_ZEALOUS_DIFF3) {
 		/* Shared preimage */
-		if (!dest) {
-			size += marker_size + 1 + needs_cr + marker3_size;
+		out_marker(out, xmp, XDL_MARKER_BASE, marker_size, name3, needs_cr);
+		c.base = out->lines;
+		xdl_orig_copy(xe1, m->i0, m->chg0, needs_cr, 1, out);
+		c.base_end = out->lines;
+	}
+	out_marker(out, xmp, XDL_MARKER_SEPARATOR, marker_size, NULL, needs_cr);
+
+	/* Postimage from side #2 */
+	c.theirs = out->lines;
+	xdl_recs_copy(xe2, m->i2, m->chg2, needs_cr, 1, out);
+	c.theirs_end = out->lines;
+	out_marker(out, xmp, XDL_MARKER_END, marker_size, name2, needs_cr);
+	c.end = out->lines;
+
+	/* Only once the result is really written, not while it is sized */
+	if (xmp->conflict_func && (out->dest || out->stream))
+		xmp->conflict_func(xmp->conflict_priv, &c);
+}
+
+/* Size of a record without its line ending. */
+static long rec_content_size(xrecord_t *rec)
+{
+	long size = rec->size;
+
+	if (size && rec->ptr[size - 1] == '\n')
+		size--;
+	if (size && rec->ptr[size - 1] == '\r')
+		size--;
+	return size;
+}
+
+static int rec_content_cmp(xrecord_t *rec1, xrecord_t *rec2)
+{
+	long size1 = rec_content_size(rec1), size2 = rec_content_size(rec2);
+	int cmp = memcmp(rec1->ptr, rec2->ptr, size1 < size2 ? size1 : size2);
+
+	if (cmp)
+		return cmp;
+	return size1 < size2 ? -1 : size1 > size2;
+}
+
+/* Returns the first line of side #2 at or after i2 that side #1 lacks. */
+static long next_union_line(xdfenv_t *xe1, xdfenv_t *xe2, xdmerge_t *m, long i2)
+{
+	long i1;
+
+	for (; i2 < m->i2 + m->chg2; i2++) {
+		for (i1 = m->i1; i1 < m->i1 + m->chg1; i1++)
+			if (!rec_content_cmp(xe1->xdf2.recs[i1], xe2->xdf2.recs[i2]))
+				break;
+		if (i1 == m->i1 + m->chg1)
+			break;
+	}
+	return i2;
+}
+
+/*
+ * Union of both sides of a conflict, leaving out the lines of side #2
+ * that side #1 already has. If sorted, the sides are interleaved like
+ * two sorted lists, so sorted input gives sorted output.
+ */
+static void fill_union_dedup(xdfenv_t *xe1, xdfenv_t *xe2, xdmerge_t *m,
+			     int sorted, xdmergeout_t *out)
+{
+	int needs_cr = is_cr_needed(xe1, xe2, m);
+	long i1 = m->i1, end1 = m->i1 + m->chg1;
+	long i2 = next_union_line(xe1, xe2, m, m->i2), end2 = m->i2 + m->chg2;
+
+	while (i1 < end1 || i2 < end2) {
+		int last;
+
+		if (i2 >= end2 || (i1 < end1 && (!sorted ||
+		    rec_content_cmp(xe1->xdf2.recs[i1], xe2->xdf2.recs[i2]) <= 0))) {
+			last = i1 + 1 == end1 && i2 >= end2;
+			xdl_recs_copy(xe1, i1++, 1, needs_cr, !last, out);
 		} else {
-			memset(dest + size, '|', marker_size);
-			size += marker_size;
-			if (marker3_size) {
-				dest[size] = ' ';
-				memcpy(dest + size + 1, name3, marker3_size - 1);
-				size += marker3_size;
-			}
-			if (needs_cr)
-				dest[size++] = '\r';
-			dest[size++] = '\n';
+			long next = next_union_line(xe1, xe2, m, i2 + 1);
+
+			last = next >= end2 && i1 >= end1;
+			xdl_recs_copy(xe2, i2, 1, needs_cr, !last, out);
+			i2 = next;
 		}
-		size += xdl_orig_copy(xe1, m->i0, m->chg0, needs_cr, 1,
-				      dest ? dest + size : NULL);
 	}
+}
 
-	if (!dest) {
-		size += marker_size + 1 + needs_cr;
-	} else {
-		memset(dest + size, '=', marker_size);
-		size += marker_size;
-		if (needs_cr)
-			dest[size++] = '\r';
-		dest[size++] = '\n';
+typedef struct s_xdsortwalk {
+	xdfenv_t *xe1, *xe2;
+	xdmerge_t *m;
+	long i0, i1, i2;
+} xdsortwalk_t;
+
+/*
+ * Steps through the sorted union of both sides of a hunk, skipping lines
+ * of the preimage that either side deleted. Returns 0 at the end.
+ */
+static int sorted_union_next(xdsortwalk_t *w, xdfenv_t **xe, long *i)
+{
+	xdmerge_t *m = w->m;
+	xrecord_t **rec0 = w->xe1->xdf1.recs;
+	xrecord_t **rec1 = w->xe1->xdf2.recs, **rec2 = w->xe2->xdf2.recs;
+
+	while (w->i1 < m->i1 + m->chg1 || w->i2 < m->i2 + m->chg2) {
+		xrecord_t *rec;
+		int cmp, in_base;
+
+		if (w->i1 == m->i1 + m->chg1)
+			cmp = 1;
+		else if (w->i2 == m->i2 + m->chg2)
+			cmp = -1;
+		else
+			cmp = rec_content_cmp(rec1[w->i1], rec2[w->i2]);
+		rec = cmp <= 0 ? rec1[w->i1] : rec2[w->i2];
+
+		while (w->i0 < m->i0 + m->chg0 &&
+		       rec_content_cmp(rec0[w->i0], rec) < 0)
+			w->i0++;
+		in_base = w->i0 < m->i0 + m->chg0 &&
+			  !rec_content_cmp(rec0[w->i0], rec);
+
+		*xe = cmp <= 0 ? w->xe1 : w->xe2;
+		*i = cmp <= 0 ? w->i1 : w->i2;
+		if (cmp <= 0)
+			w->i1++;
+		if (cmp >= 0)
+			w->i2++;
+		/* a preimage line survives only if both sides kept it */
+		if (!in_base || !cmp)
+			return 1;
 	}
+	return 0;
+}
 
-	/* Postimage from side #2 */
-	size += xdl_recs_copy(xe2, m->i2, m->chg2, needs_cr, 1,
-			      dest ? dest + size : NULL);
-	if (!dest) {
-		size += marker_size + 1 + needs_cr + marker2_size;
-	} else {
-		memset(dest + size, '>', marker_size);
-		size += marker_size;
-		if (marker2_size) {
-			dest[size] = ' ';
-			memcpy(dest + size + 1, name2, marker2_size - 1);
-			size += marker2_size;
-		}
-		if (needs_cr)
-			dest[size++] = '\r';
-		dest[size++] = '\n';
+static void fill_sorted_union(xdfenv_t *xe1, xdfenv_t *xe2, xdmerge_t *m,
+			      xdmergeout_t *out)
+{
+	int needs_cr = is_cr_needed(xe1, xe2, m);
+	xdsortwalk_t w = { xe1, xe2, m, m->i0, m->i1, m->i2 };
+	xdfenv_t *xe, *next_xe;
+	long i, next_i;
+	int more = sorted_union_next(&w, &xe, &i);
+
+	while (more) {
+		int next = sorted_union_next(&w, &next_xe, &next_i);
+
+		xdl_recs_copy(xe, i, 1, needs_cr, next, out);
+		xe = next_xe;
+		i = next_i;
+		more = next;
 	}
-	return size;
 }
 
-static int xdl_fill_merge_buffer(xdfenv_t *xe1, const char *name1,
-				 xdfenv_t *xe2, const char *name2,
-				 const char *ancestor_name,
-				 int favor,
-				 xdmerge_t *m, char *dest, int style,
-				 int marker_size)
+static int is_blank(xrecord_t *rec)
+{
+	return !rec_content_size(rec);
+}
+
+/*
+ * Is recs[i..i+chg) a sorted set of lines that also sorts between the
+ * context lines around the hunk? Blank context lines end a block and do
+ * not constrain it.
+ */
+static int is_sorted_block(xrecord_t **recs, long i, long chg,
+			   xrecord_t *prev, xrecord_t *next)
+{
+	long k;
+
+	if (!chg)
+		return 1;
+	if (prev && !is_blank(prev) && rec_content_cmp(prev, recs[i]) >= 0)
+		return 0;
+	if (next && !is_blank(next) && rec_content_cmp(recs[i + chg - 1], next) >= 0)
+		return 0;
+	for (k = i; k + 1 < i + chg; k++)
+		if (rec_content_cmp(recs[k], recs[k + 1]) >= 0)
+			return 0;
+	return 1;
+}
+
+static int chunk_has(xrecord_t **recs, long i, long chg, xrecord_t *rec)
+{
+	for (; chg; chg--, i++)
+		if (!rec_content_cmp(recs[i], rec))
+			return 1;
+	return 0;
+}
+
+/*
+ * Resolves conflicts whose preimage and both postimages are sorted sets of
+ * lines, such as import blocks where both sides added an entry. A hunk
+ * where both sides removed the same line (e.g. both changed one entry) is
+ * left as a conflict.
+ */
+static void xdl_resolve_sorted_conflicts(xdfenv_t *xe1, xdfenv_t *xe2, xdmerge_t *m)
+{
+	xrecord_t **rec0 = xe1->xdf1.recs;
+	xrecord_t **rec1 = xe1->xdf2.recs, **rec2 = xe2->xdf2.recs;
+
+	for (; m; m = m->next) {
+		xrecord_t *prev, *next;
+		long k;
+
+		if (m->mode)
+			continue;
+		prev = m->i0 > 0 ? rec0[m->i0 - 1] : NULL;
+		next = m->i0 + m->chg0 < xe1->xdf1.nrec ? rec0[m->i0 + m->chg0] : NULL;
+		if (!is_sorted_block(rec0, m->i0, m->chg0, prev, next) ||
+		    !is_sorted_block(rec1, m->i1, m->chg1, prev, next) ||
+		    !is_sorted_block(rec2, m->i2, m->chg2, prev, next))
+			continue;
+		for (k = m->i0; k < m->i0 + m->chg0; k++)
+			if (!chunk_has(rec1, m->i1, m->chg1, rec0[k]) &&
+			    !chunk_has(rec2, m->i2, m->chg2, rec0[k]))
+				break;
+		if (k == m->i0 + m->chg0)
+			m->mode = 5;
+	}
+}
+
+static void xdl_fill_merge_buffer(xdfenv_t *xe1, const char *name1,
+				  xdfenv_t *xe2, const char *name2,
+				  const char *ancestor_name,
+				  int favor,
+				  xdmerge_t *m, xdmergeout_t *out, int style,
+				  xmparam_t const *xmp)
 {
-	int size, i;
+	long i;
+	int dedup = favor == XDL_MERGE_FAVOR_UNION_DEDUP ||
+		    favor == XDL_MERGE_FAVOR_UNION_SORTED;
 
-	for (size = i = 0; m; m = m->next) {
+	for (i = 0; m; m = m->next) {
 		if (favor && !m->mode)
-			m->mode = favor;
+			m->mode = dedup ? XDL_MERGE_FAVOR_UNION : favor;
 
 		if (m->mode == 0)
-			size = fill_conflict_hunk(xe1, name1, xe2, name2,
-						  ancestor_name,
-						  size, i, style, m, dest,
-						  marker_size);
-		else if (m->mode & 3) {
+			fill_conflict_hunk(xe1, name1, xe2, name2,
+					   ancestor_name,
+					   i, style, m, out, xmp);
+		else if (m->mode == 5) {
+			/* Before conflicting part */
+			xdl_recs_copy(xe1, i, m->i1 - i, 0, 0, out);
+			fill_sorted_union(xe1, xe2, m, out);
+		} else if (dedup && m->mode == 3) {
 			/* Before conflicting part */
-			size += xdl_recs_copy(xe1, i, m->i1 - i, 0, 0,
-					      dest ? dest + size : NULL);
+			xdl_recs_copy(xe1, i, m->i1 - i, 0, 0, out);
+			fill_union_dedup(xe1, xe2, m,
+					 favor == XDL_MERGE_FAVOR_UNION_SORTED,
+					 out);
+		} else if (m->mode & 3) {
+			/* Before conflicting part */
+			xdl_recs_copy(xe1, i, m->i1 - i, 0, 0, out);
 			/* Postimage from side #1 */
 			if (m->mode & 1) {
 				int needs_cr = is_cr_needed(xe1, xe2, m);
 
-				size += xdl_recs_copy(xe1, m->i1, m->chg1, needs_cr, (m->mode & 2),
-						      dest ? dest + size : NULL);
+				xdl_recs_copy(xe1, m->i1, m->chg1, needs_cr, (m->mode & 2), out);
 			}
 			/* Postimage from side #2 */
 			if (m->mode & 2)
-				size += xdl_recs_copy(xe2, m->i2, m->chg2, 0, 0,
-						      dest ? dest + size : NULL);
+				xdl_recs_copy(xe2, m->i2, m->chg2, 0, 0, out);
 		} else
 			continue;
 		i = m->i1 + m->chg1;
 	}
-	size += xdl_recs_copy(xe1, i, xe1->xdf2.nrec - i, 0, 0,
-			      dest ? dest + size : NULL);
-	return size;
+	xdl_recs_copy(xe1, i, xe1->xdf2.nrec - i, 0, 0, out);
 }
 
 static int recmatch(xrecord_t *rec1, xrecord_t *rec2, unsigned long flags)
@@ -367,7 +597,7 @@ static int xdl_refine_conflicts(xdfenv_t *xe1, xdfenv_t *xe2, xdmerge_t *m,
 		mmfile_t t1, t2;
 		xdfenv_t xe;
 		xdchange_t *xscr, *x;
-		int i1 = m->i1, i2 = m->i2;
+		long i1 = m->i1, i2 = m->i2;
 
 		/* let's handle just the conflicts */
 		if (m->mode)
@@ -437,7 +667,7 @@ static int line_contains_alnum(const char *ptr, long size)
 	return 0;
 }
 
-static int lines_contain_alnum(xdfenv_t *xe, int i, int chg)
+static int lines_contain_alnum(xdfenv_t *xe, long i, long chg)
 {
 	for (; chg; chg--, i++)
 		if (line_contains_alnum(xe->xdf2.recs[i]->ptr,
@@ -473,7 +703,7 @@ static int xdl_simplify_non_conflicts(xdfenv_t *xe1, xdmerge_t *m,
 		return result;
 	for (;;) {
 		xdmerge_t *next_m = m->next;
-		int begin, end;
+		long begin, end;
 
 		if (!next_m)
 			return result;
@@ -504,14 +734,14 @@ static int xdl_simplify_non_conflicts(xdfenv_t *xe1, xdmerge_t *m,
  */
 static int xdl_do_merge(xdfenv_t *xe1, xdchange_t *xscr1,
 		xdfenv_t *xe2, xdchange_t *xscr2,
-		xmparam_t const *xmp, mmbuffer_t *result)
+		xmparam_t const *xmp, xmoutput_t const *result)
 {
 	xdmerge_t *changes, *c;
 	xpparam_t const *xpp = &xmp->xpp;
 	const char *const ancestor_name = xmp->ancestor;
 	const char *const name1 = xmp->file1;
 	const char *const name2 = xmp->file2;
-	int i0, i1, i2, chg0, chg1, chg2;
+	long i0, i1, i2, chg0, chg1, chg2;
 	int level = xmp->level;
 	int style = xmp->style;
 	int favor = xmp->favor;
@@ -582,8 +812,8 @@ static int xdl_do_merge(xdfenv_t *xe1, xdchange_t *xscr1,
 					xe2, xscr2->i2,
 					xscr1->chg2, xpp->flags)) {
 			/* conflict */
-			int off = xscr1->i1 - xscr2->i1;
-			int ffo = off + xscr1->chg1 - xscr2->chg1;
+			long off = xscr1->i1 - xscr2->i1;
+			long ffo = off + xscr1->chg1 - xscr2->chg1;
 
 			i0 = xscr1->i1;
 			i1 = xscr1->i2;
@@ -651,6 +881,8 @@ static int xdl_do_merge(xdfenv_t *xe1, xdchange_t *xscr1,
 	}
 	if (!changes)
 		changes = c;
+	if (xmp->resolve & XDL_MERGE_RESOLVE_SORTED)
+		xdl_resolve_sorted_conflicts(xe1, xe2, changes);
 	/* refine conflicts */
 	if (style == XDL_MERGE_ZEALOUS_DIFF3) {
 		xdl_refine_zdiff3_conflicts(xe1, xe2, changes, xpp);
@@ -663,35 +895,58 @@ static int xdl_do_merge(xdfenv_t *xe1, xdchange_t *xscr1,
 	}
 	/* output */
 	if (result) {
-		int marker_size = xmp->marker_size;
-		int size = xdl_fill_merge_buffer(xe1, name1, xe2, name2,
-						 ancestor_name,
-						 favor, changes, NULL, style,
-						 marker_size);
-		result->ptr = xdl_malloc(size);
-		if (!result->ptr) {
-			xdl_cleanup_merge(changes);
-			return -1;
+		xdmergeout_t out = { NULL, NULL, 0, 0, 0, 0 };
+
+		out.count_lines = xmp->conflict_func != NULL;
+
+		if (result->write)
+			out.stream = result;
+		else {
+			/* size the result, then fill it in */
+			xdl_fill_merge_buffer(xe1, name1, xe2, name2,
+					      ancestor_name, favor, changes,
+					      &out, style, xmp);
+			out.dest = result->alloc(result->priv, out.size);
+			if (!out.dest) {
+				xdl_cleanup_merge(changes);
+				return -1;
+			}
+			out.size = 0;
+			out.lines = 0;
 		}
-		result->size = size;
 		xdl_fill_merge_buffer(xe1, name1, xe2, name2,
 				      ancestor_name, favor, changes,
-				      result->ptr, style, marker_size);
+				      &out, style, xmp);
+		if (out.err) {
+			xdl_cleanup_merge(changes);
+			return -1;
+		}
 	}
 	return xdl_cleanup_merge(changes);
 }
 
-int xdl_merge(mmfile_t *orig, mmfile_t *mf1, mmfile_t *mf2,
-		xmparam_t const *xmp, mmbuffer_t *result)
+/* Hands one side over unchanged when the other made no changes. */
+static int xdl_output_file(mmfile_t *mf, xmoutput_t const *out)
+{
+	char *dest;
+
+	if (out->write)
+		return !mf->size || out->write(out->priv, mf->ptr, mf->size) >= 0 ? 0 : -1;
+	dest = out->alloc(out->priv, mf->size);
+	if (!dest)
+		return -1;
+	memcpy(dest, mf->ptr, mf->size);
+	return 0;
+}
+
+int xdl_merge_to(mmfile_t *orig, mmfile_t *mf1, mmfile_t *mf2,
+		 xmparam_t const *xmp, xmoutput_t const *out)
 {
 	xdchange_t *xscr1 = NULL, *xscr2 = NULL;
 	xdfenv_t xe1, xe2;
 	int status = -1;
 	xpparam_t const *xpp = &xmp->xpp;
 
-	result->ptr = NULL;
-	result->size = 0;
-
 	if (xdl_do_diff(orig, mf1, xpp, &xe1) < 0)
 		return -1;
 
@@ -708,25 +963,14 @@ int xdl_merge(mmfile_t *orig, mmfile_t *mf1, mmfile_t *mf2,
 	    xdl_build_script(&xe2, &xscr2) < 0)
 		goto out;
 
-	if (!xscr1) {
-		result->ptr = xdl_malloc(mf2->size);
-		if (!result->ptr)
-			goto out;
-		status = 0;
-		memcpy(result->ptr, mf2->ptr, mf2->size);
-		result->size = mf2->size;
-	} else if (!xscr2) {
-		result->ptr = xdl_malloc(mf1->size);
-		if (!result->ptr)
-			goto out;
-		status = 0;
-		memcpy(result->ptr, mf1->ptr, mf1->size);
-		result->size = mf1->size;
-	} else {
+	if (!xscr1)
+		status = xdl_output_file(mf2, out);
+	else if (!xscr2)
+		status = xdl_output_file(mf1, out);
+	else
 		status = xdl_do_merge(&xe1, xscr1,
 				      &xe2, xscr2,
-				      xmp, result);
-	}
+				      xmp, out);
  out:
 	xdl_free_script(xscr1);
 	xdl_free_script(xscr2);
@@ -737,3 +981,38 @@ int xdl_merge(mmfile_t *orig, mmfile_t *mf1, mmfile_t *mf2,
 
 	return status;
 }
+
+typedef struct s_xdmmbufout {
+	mmbuffer_t *result;
+	int too_large;
+} xdmmbufout_t;
+
+static char *xdl_alloc_mmbuffer(void *priv, size_t size)
+{
+	xdmmbufout_t *buf = priv;
+
+	if (size > LONG_MAX) {
+		buf->too_large = 1;
+		return NULL;
+	}
+	buf->result->ptr = xdl_malloc(size);
+	if (buf->result->ptr)
+		buf->result->size = size;
+	return buf->result->ptr;
+}
+
+int xdl_merge(mmfile_t *orig, mmfile_t *mf1, mmfile_t *mf2,
+		xmparam_t const *xmp, mmbuffer_t *result)
+{
+	xdmmbufout_t buf = { result, 0 };
+	xmoutput_t out = { xdl_alloc_mmbuffer, NULL, &buf };
+	int status;
+
+	result->ptr = NULL;
+	result->size = 0;
+
+	status = xdl_merge_to(orig, mf1, mf2, xmp, &out);
+	if (status < 0 && buf.too_large)
+		return XDL_MERGE_ETOOLARGE;
+	return status;
+}
@@ -309,6 +309,8 @@ static int fall_back_to_classic_diff(struct hashmap *map,
 
 	memset(&xpp, 0, sizeof(xpp));
 	xpp.flags = map->xpp->flags & ~XDF_DIFF_ALGORITHM_MASK;
+	xpp.abort_func = map->xpp->abort_func;
+	xpp.abort_priv = map->xpp->abort_priv;
 
 	return xdl_fall_back_diff(map->env, &xpp,
 				  line1, count1, line2, count2);
@@ -327,6 +329,9 @@ static int patience_diff(xpparam_t const *xpp, xdfenv_t *env,
 	struct entry *first;
 	int result = 0;
 
+	if (XDL_ABORTED(xpp))
+		return -1;
+
 	/* trivial case: one side is empty */
 	if (!count1) {
 		while(count2--)
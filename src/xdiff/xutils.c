@@ -36,6 +36,15 @@ long xdl_bogosqrt(long n) {
 }
 
 
+/*
+ * Adds two sizes, saturating at SIZE_MAX: a size that cannot be counted
+ * is one that cannot be allocated either.
+ */
+size_t xdl_size_add(size_t a, size_t b) {
+	return b > SIZE_MAX - a ? SIZE_MAX : a + b;
+}
+
+
 int xdl_emit_diffrec(char const *rec, long size, char const *pre, long psize,
 		     xdemitcb_t *ecb) {
 	int i = 2;
@@ -26,6 +26,7 @@
 
 
 long xdl_bogosqrt(long n);
+size_t xdl_size_add(size_t a, size_t b);
 int xdl_emit_diffrec(char const *rec, long size, char const *pre, long psize,
 		     xdemitcb_t *ecb);
 int xdl_cha_init(chastore_t *cha, long isize, long icount);
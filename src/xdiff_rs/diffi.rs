@@ -0,0 +1,658 @@
+//! Myers' diff, change compaction and edit scripts (xdiffi.c).
+
+use super::prepare::{XdEnv, XdFile, prepare_env};
+use super::{DiffParams, Failed, bogosqrt, histogram, patience};
+use crate::diff::Hunk;
+use crate::types::DiffAlgorithm;
+
+const MAX_COST_MIN: isize = 256;
+const HEUR_MIN_COST: isize = 256;
+const LINE_MAX: isize = isize::MAX;
+const SNAKE_CNT: isize = 20;
+const K_HEUR: isize = 4;
+
+/// A run of changed records (`xdchange_t`).
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Change {
+    pub i1: isize,
+    pub i2: isize,
+    pub chg1: isize,
+    pub chg2: isize,
+}
+
+struct Split {
+    i1: isize,
+    i2: isize,
+    min_lo: bool,
+    min_hi: bool,
+}
+
+struct AlgoEnv<'p> {
+    mxcost: isize,
+    snake_cnt: isize,
+    heur_min: isize,
+    params: &'p DiffParams<'p>,
+}
+
+/// The forward and backward K vectors, indexed by diagonal.
+struct KVecs {
+    kvd: Vec<isize>,
+    /// Where diagonal 0 sits in `kvd` for each vector
+    fo: isize,
+    bo: isize,
+}
+
+impl KVecs {
+    fn f(&self, d: isize) -> isize {
+        self.kvd[(self.fo + d) as usize]
+    }
+
+    fn set_f(&mut self, d: isize, v: isize) {
+        self.kvd[(self.fo + d) as usize] = v;
+    }
+
+    fn b(&self, d: isize) -> isize {
+        self.kvd[(self.bo + d) as usize]
+    }
+
+    fn set_b(&mut self, d: isize, v: isize) {
+        self.kvd[(self.bo + d) as usize] = v;
+    }
+}
+
+/// Finds the middle snake of the box, or a good enough split once the
+/// cost gets too high (`xdl_split`).
+#[allow(clippy::too_many_arguments)]
+fn split(
+    ha1: &[usize],
+    off1: isize,
+    lim1: isize,
+    ha2: &[usize],
+    off2: isize,
+    lim2: isize,
+    kv: &mut KVecs,
+    need_min: bool,
+    xenv: &AlgoEnv,
+) -> Result<Split, Failed> {
+    let (dmin, dmax) = (off1 - lim2, lim1 - off2);
+    let (fmid, bmid) = (off1 - off2, lim1 - lim2);
+    let odd = (fmid - bmid) & 1 != 0;
+    let (mut fmin, mut fmax) = (fmid, fmid);
+    let (mut bmin, mut bmax) = (bmid, bmid);
+    let h1 = |i: isize| ha1[i as usize];
+    let h2 = |i: isize| ha2[i as usize];
+
+    // Initial diagonal values for both forward and backward path
+    kv.set_f(fmid, off1);
+    kv.set_b(bmid, lim1);
+
+    let mut ec = 1;
+    loop {
+        let mut got_snake = false;
+
+        if xenv.params.aborted() {
+            return Err(Failed);
+        }
+
+        // Extend the diagonal domain by one, initializing the outer K
+        // value so the core loop needs no extra conditions
+        if fmin > dmin {
+            fmin -= 1;
+            kv.set_f(fmin - 1, -1);
+        } else {
+            fmin += 1;
+        }
+        if fmax < dmax {
+            fmax += 1;
+            kv.set_f(fmax + 1, -1);
+        } else {
+            fmax -= 1;
+        }
+
+        let mut d = fmax;
+        while d >= fmin {
+            let mut i1 = if kv.f(d - 1) >= kv.f(d + 1) {
+                kv.f(d - 1) + 1
+            } else {
+                kv.f(d + 1)
+            };
+            let prev1 = i1;
+            let mut i2 = i1 - d;
+            while i1 < lim1 && i2 < lim2 && h1(i1) == h2(i2) {
+                i1 += 1;
+                i2 += 1;
+            }
+            if i1 - prev1 > xenv.snake_cnt {
+                got_snake = true;
+            }
+            kv.set_f(d, i1);
+            if odd && bmin <= d && d <= bmax && kv.b(d) <= i1 {
+                return Ok(Split {
+                    i1,
+                    i2,
+                    min_lo: true,
+                    min_hi: true,
+                });
+            }
+            d -= 2;
+        }
+
+        if bmin > dmin {
+            bmin -= 1;
+            kv.set_b(bmin - 1, LINE_MAX);
+        } else {
+            bmin += 1;
+        }
+        if bmax < dmax {
+            bmax += 1;
+            kv.set_b(bmax + 1, LINE_MAX);
+        } else {
+            bmax -= 1;
+        }
+
+        let mut d = bmax;
+        while d >= bmin {
+            let mut i1 = if kv.b(d - 1) < kv.b(d + 1) {
+                kv.b(d - 1)
+            } else {
+                kv.b(d + 1) - 1
+            };
+            let prev1 = i1;
+            let mut i2 = i1 - d;
+            while i1 > off1 && i2 > off2 && h1(i1 - 1) == h2(i2 - 1) {
+                i1 -= 1;
+                i2 -= 1;
+            }
+            if prev1 - i1 > xenv.snake_cnt {
+                got_snake = true;
+            }
+            kv.set_b(d, i1);
+            if !odd && fmin <= d && d <= fmax && i1 <= kv.f(d) {
+                return Ok(Split {
+                    i1,
+                    i2,
+                    min_lo: true,
+                    min_hi: true,
+                });
+            }
+            d -= 2;
+        }
+
+        if need_min {
+            ec += 1;
+            continue;
+        }
+
+        // Past the heuristic trigger with a good snake, look for a
+        // diagonal that has reached far enough to split there
+        if got_snake && ec > xenv.heur_min {
+            let mut best = 0;
+            let mut spl = Split {
+                i1: 0,
+                i2: 0,
+                min_lo: true,
+                min_hi: false,
+            };
+            let mut d = fmax;
+            while d >= fmin {
+                let dd = if d > fmid { d - fmid } else { fmid - d };
+                let i1 = kv.f(d);
+                let i2 = i1 - d;
+                let v = (i1 - off1) + (i2 - off2) - dd;
+
+                if v > K_HEUR * ec
+                    && v > best
+                    && off1 + xenv.snake_cnt <= i1
+                    && i1 < lim1
+                    && off2 + xenv.snake_cnt <= i2
+                    && i2 < lim2
+                {
+                    let mut k = 1;
+                    while h1(i1 - k) == h2(i2 - k) {
+                        if k == xenv.snake_cnt {
+                            best = v;
+                            spl.i1 = i1;
+                            spl.i2 = i2;
+                            break;
+                        }
+                        k += 1;
+                    }
+                }
+                d -= 2;
+            }
+            if best > 0 {
+                return Ok(spl);
+            }
+
+            best = 0;
+            let mut spl = Split {
+                i1: 0,
+                i2: 0,
+                min_lo: false,
+                min_hi: true,
+            };
+            let mut d = bmax;
+            while d >= bmin {
+                let dd = if d > bmid { d - bmid } else { bmid - d };
+                let i1 = kv.b(d);
+                let i2 = i1 - d;
+                let v = (lim1 - i1) + (lim2 - i2) - dd;
+
+                if v > K_HEUR * ec
+                    && v > best
+                    && off1 < i1
+                    && i1 <= lim1 - xenv.snake_cnt
+                    && off2 < i2
+                    && i2 <= lim2 - xenv.snake_cnt
+                {
+                    let mut k = 0;
+                    while h1(i1 + k) == h2(i2 + k) {
+                        if k == xenv.snake_cnt - 1 {
+                            best = v;
+                            spl.i1 = i1;
+                            spl.i2 = i2;
+                            break;
+                        }
+                        k += 1;
+                    }
+                }
+                d -= 2;
+            }
+            if best > 0 {
+                return Ok(spl);
+            }
+        }
+
+        // Enough is enough: take the furthest reaching path
+        if ec >= xenv.mxcost {
+            let (mut fbest, mut fbest1) = (-1, -1);
+            let mut d = fmax;
+            while d >= fmin {
+                let mut i1 = kv.f(d).min(lim1);
+                let mut i2 = i1 - d;
+                if lim2 < i2 {
+                    i1 = lim2 + d;
+                    i2 = lim2;
+                }
+                if fbest < i1 + i2 {
+                    fbest = i1 + i2;
+                    fbest1 = i1;
+                }
+                d -= 2;
+            }
+
+            let (mut bbest, mut bbest1) = (LINE_MAX, LINE_MAX);
+            let mut d = bmax;
+            while d >= bmin {
+                let mut i1 = off1.max(kv.b(d));
+                let mut i2 = i1 - d;
+                if i2 < off2 {
+                    i1 = off2 + d;
+                    i2 = off2;
+                }
+                if i1 + i2 < bbest {
+                    bbest = i1 + i2;
+                    bbest1 = i1;
+                }
+                d -= 2;
+            }
+
+            return Ok(if (lim1 + lim2) - bbest < fbest - (off1 + off2) {
+                Split {
+                    i1: fbest1,
+                    i2: fbest - fbest1,
+                    min_lo: true,
+                    min_hi: false,
+                }
+            } else {
+                Split {
+                    i1: bbest1,
+                    i2: bbest - bbest1,
+                    min_lo: false,
+                    min_hi: true,
+                }
+            });
+        }
+        ec += 1;
+    }
+}
+
+/// The records of one side as `xdl_recs_cmp` sees them (`diffdata_t`).
+struct DiffData<'x> {
+    ha: &'x [usize],
+    rindex: &'x [isize],
+    /// Changed flags of the file, from record 0
+    rchg: &'x mut [bool],
+}
+
+/// Divides the box at a middle snake and conquers both halves
+/// (`xdl_recs_cmp`).
+#[allow(clippy::too_many_arguments)]
+fn recs_cmp(
+    dd1: &mut DiffData,
+    mut off1: isize,
+    mut lim1: isize,
+    dd2: &mut DiffData,
+    mut off2: isize,
+    mut lim2: isize,
+    kv: &mut KVecs,
+    need_min: bool,
+    xenv: &AlgoEnv,
+) -> Result<(), Failed> {
+    let (ha1, ha2) = (dd1.ha, dd2.ha);
+
+    // Shrink the box by walking through each diagonal snake
+    while off1 < lim1 && off2 < lim2 && ha1[off1 as usize] == ha2[off2 as usize] {
+        off1 += 1;
+        off2 += 1;
+    }
+    while off1 < lim1 && off2 < lim2 && ha1[lim1 as usize - 1] == ha2[lim2 as usize - 1] {
+        lim1 -= 1;
+        lim2 -= 1;
+    }
+
+    // If one dimension is empty, every record on the other one changed
+    if off1 == lim1 {
+        for off in off2..lim2 {
+            dd2.rchg[dd2.rindex[off as usize] as usize] = true;
+        }
+    } else if off2 == lim2 {
+        for off in off1..lim1 {
+            dd1.rchg[dd1.rindex[off as usize] as usize] = true;
+        }
+    } else {
+        let spl = split(ha1, off1, lim1, ha2, off2, lim2, kv, need_min, xenv)?;
+        recs_cmp(dd1, off1, spl.i1, dd2, off2, spl.i2, kv, spl.min_lo, xenv)?;
+        recs_cmp(dd1, spl.i1, lim1, dd2, spl.i2, lim2, kv, spl.min_hi, xenv)?;
+    }
+    Ok(())
+}
+
+/// Marks the changed records of both files (`xdl_do_diff`).
+pub(super) fn do_diff<'a>(
+    mf1: &'a [u8],
+    mf2: &'a [u8],
+    params: &DiffParams,
+) -> Result<XdEnv<'a>, Failed> {
+    let mut xe = prepare_env(mf1, mf2, params.algorithm);
+
+    match params.algorithm {
+        DiffAlgorithm::Patience => patience::do_patience_diff(params, &mut xe)?,
+        DiffAlgorithm::Histogram => histogram::do_histogram_diff(params, &mut xe)?,
+        DiffAlgorithm::Myers | DiffAlgorithm::Minimal => {
+            let (nreff1, nreff2) = (xe.xdf1.rindex.len() as isize, xe.xdf2.rindex.len() as isize);
+            let ndiags = nreff1 + nreff2 + 3;
+            let mut kv = KVecs {
+                kvd: vec![0; 2 * ndiags as usize + 2],
+                fo: nreff2 + 1,
+                bo: ndiags + nreff2 + 1,
+            };
+            let xenv = AlgoEnv {
+                mxcost: bogosqrt(ndiags).max(MAX_COST_MIN),
+                snake_cnt: SNAKE_CNT,
+                heur_min: HEUR_MIN_COST,
+                params,
+            };
+
+            let (ha1, rindex1, rchg1) = xe.xdf1.diff_data();
+            let (ha2, rindex2, rchg2) = xe.xdf2.diff_data();
+            recs_cmp(
+                &mut DiffData {
+                    ha: ha1,
+                    rindex: rindex1,
+                    rchg: rchg1,
+                },
+                0,
+                nreff1,
+                &mut DiffData {
+                    ha: ha2,
+                    rindex: rindex2,
+                    rchg: rchg2,
+                },
+                0,
+                nreff2,
+                &mut kv,
+                params.algorithm == DiffAlgorithm::Minimal,
+                &xenv,
+            )?;
+        }
+    }
+    Ok(xe)
+}
+
+/// Diffs a range of lines with Myers' algorithm, for patience and histogram
+/// diffs that find no better anchor (`xdl_fall_back_diff`). Lines are
+/// 1-based.
+pub(super) fn fall_back_diff(
+    diff_env: &mut XdEnv,
+    params: &DiffParams,
+    line1: isize,
+    count1: isize,
+    line2: isize,
+    count2: isize,
+) -> Result<(), Failed> {
+    let subfile1 = diff_env.xdf1.recs(line1 - 1, count1);
+    let subfile2 = diff_env.xdf2.recs(line2 - 1, count2);
+    let params = DiffParams {
+        algorithm: DiffAlgorithm::Myers,
+        abort: params.abort,
+    };
+    let env = do_diff(subfile1, subfile2, &params)?;
+
+    diff_env
+        .xdf1
+        .changes_mut(line1 - 1, count1)
+        .copy_from_slice(env.xdf1.changes(0, count1));
+    diff_env
+        .xdf2
+        .changes_mut(line2 - 1, count2)
+        .copy_from_slice(env.xdf2.changes(0, count2));
+    Ok(())
+}
+
+/// A run of changed lines: `start` is the first changed line, or the line
+/// an empty group sits above; `end` the first unchanged line after it.
+struct Group {
+    start: isize,
+    end: isize,
+}
+
+impl Group {
+    fn init(xdf: &XdFile) -> Self {
+        let mut end = 0;
+        while xdf.changed(end) {
+            end += 1;
+        }
+        Group { start: 0, end }
+    }
+
+    /// Moves to the next group; false at the end of the file.
+    fn next(&mut self, xdf: &XdFile) -> bool {
+        if self.end == xdf.nrec() {
+            return false;
+        }
+        self.start = self.end + 1;
+        self.end = self.start;
+        while xdf.changed(self.end) {
+            self.end += 1;
+        }
+        true
+    }
+
+    /// Moves to the previous group; false at the start of the file.
+    fn previous(&mut self, xdf: &XdFile) -> bool {
+        if self.start == 0 {
+            return false;
+        }
+        self.end = self.start - 1;
+        self.start = self.end;
+        while xdf.changed(self.start - 1) {
+            self.start -= 1;
+        }
+        true
+    }
+
+    /// Slides the group down by a line if the line after it matches its
+    /// first, merging any group it runs into.
+    fn slide_down(&mut self, xdf: &mut XdFile) -> bool {
+        if self.end < xdf.nrec() && xdf.ha[self.start as usize] == xdf.ha[self.end as usize] {
+            xdf.set_changed(self.start, false);
+            self.start += 1;
+            xdf.set_changed(self.end, true);
+            self.end += 1;
+            while xdf.changed(self.end) {
+                self.end += 1;
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Slides the group up by a line if the line before it matches its
+    /// last, merging any group it runs into.
+    fn slide_up(&mut self, xdf: &mut XdFile) -> bool {
+        if self.start > 0 && xdf.ha[self.start as usize - 1] == xdf.ha[self.end as usize - 1] {
+            self.start -= 1;
+            xdf.set_changed(self.start, true);
+            self.end -= 1;
+            xdf.set_changed(self.end, false);
+            while xdf.changed(self.start - 1) {
+                self.start -= 1;
+            }
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Slides each group of changes in `xdf` as far down as it goes, or to line
+/// up with a group in `xdfo` (`xdl_change_compact` without the indent
+/// heuristic).
+pub(super) fn change_compact(xdf: &mut XdFile, xdfo: &XdFile) {
+    let mut g = Group::init(xdf);
+    let mut go = Group::init(xdfo);
+
+    loop {
+        if g.end != g.start {
+            let mut earliest_end;
+            let mut end_matching_other;
+
+            // Shift the change up and then down as far as possible,
+            // merging any changes it bumps into
+            loop {
+                let groupsize = g.end - g.start;
+                end_matching_other = -1;
+
+                while g.slide_up(xdf) {
+                    if !go.previous(xdfo) {
+                        panic!("group sync broken sliding up");
+                    }
+                }
+
+                earliest_end = g.end;
+                if go.end > go.start {
+                    end_matching_other = g.end;
+                }
+
+                while g.slide_down(xdf) {
+                    if !go.next(xdfo) {
+                        panic!("group sync broken sliding down");
+                    }
+                    if go.end > go.start {
+                        end_matching_other = g.end;
+                    }
+                }
+
+                if groupsize == g.end - g.start {
+                    break;
+                }
+            }
+
+            // Line the group back up with the last group of changes in the
+            // other file it can align with
+            if g.end != earliest_end && end_matching_other != -1 {
+                while go.end == go.start {
+                    if !g.slide_up(xdf) {
+                        panic!("match disappeared");
+                    }
+                    if !go.previous(xdfo) {
+                        panic!("group sync broken sliding to match");
+                    }
+                }
+            }
+        }
+
+        if !g.next(xdf) {
+            break;
+        }
+        if !go.next(xdfo) {
+            panic!("group sync broken moving to next group");
+        }
+    }
+
+    if go.next(xdfo) {
+        panic!("group sync broken at end of file");
+    }
+}
+
+/// Compacts the changes of both files.
+pub(super) fn compact(xe: &mut XdEnv) {
+    change_compact(&mut xe.xdf1, &xe.xdf2);
+    change_compact(&mut xe.xdf2, &xe.xdf1);
+}
+
+/// Collects the groups of changes, in order (`xdl_build_script`).
+pub(super) fn build_script(xe: &XdEnv) -> Vec<Change> {
+    let (xdf1, xdf2) = (&xe.xdf1, &xe.xdf2);
+    let mut script = Vec::new();
+    let (mut i1, mut i2) = (xdf1.nrec(), xdf2.nrec());
+
+    while i1 >= 0 || i2 >= 0 {
+        if xdf1.changed(i1 - 1) || xdf2.changed(i2 - 1) {
+            let (l1, l2) = (i1, i2);
+            while xdf1.changed(i1 - 1) {
+                i1 -= 1;
+            }
+            while xdf2.changed(i2 - 1) {
+                i2 -= 1;
+            }
+            script.push(Change {
+                i1,
+                i2,
+                chg1: l1 - i1,
+                chg2: l2 - i2,
+            });
+        }
+        i1 -= 1;
+        i2 -= 1;
+    }
+    script.reverse();
+    script
+}
+
+/// A two-way diff as hunks of changed lines with no context, the way
+/// `xdl_diff` reports them to a hunk callback.
+pub(crate) fn diff(old: &[u8], new: &[u8], params: &DiffParams) -> Result<Vec<Hunk>, Failed> {
+    let mut xe = do_diff(old, new, params)?;
+    compact(&mut xe);
+
+    let mut hunks: Vec<Hunk> = Vec::new();
+    for xch in build_script(&xe) {
+        let (i1, i2) = (xch.i1 as usize, xch.i2 as usize);
+        let (end1, end2) = (i1 + xch.chg1 as usize, i2 + xch.chg2 as usize);
+        // With no context, only changes that touch share a hunk
+        match hunks.last_mut() {
+            Some(last) if last.old.end == i1 => {
+                last.old.end = end1;
+                last.new.end = end2;
+            }
+            _ => hunks.push(Hunk {
+                old: i1..end1,
+                new: i2..end2,
+            }),
+        }
+    }
+    Ok(hunks)
+}
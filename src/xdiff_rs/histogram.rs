@@ -0,0 +1,274 @@
+//! Histogram diff (xhistogram.c).
+//!
+//! The lines of the first range are indexed by how often they occur. The
+//! longest common run that contains the rarest lines splits the ranges,
+//! and both sides of it are diffed the same way. Line numbers are 1-based.
+
+use super::diffi::fall_back_diff;
+use super::prepare::XdEnv;
+use super::{DiffParams, Failed, hashbits};
+use std::collections::HashMap;
+
+/// xdiff gives up on a range once a hash chain holds this many classes.
+const MAX_CHAIN_LENGTH: usize = 64;
+
+/// The occurrences of one class in the first range.
+struct Record {
+    /// The first occurrence; the rest follow through `next_ptrs`
+    ptr: usize,
+    cnt: usize,
+}
+
+struct HistIndex {
+    records: Vec<Record>,
+    by_class: HashMap<usize, usize>,
+    /// Classes in each chain of xdiff's hash table, which caps them
+    chain_lens: Vec<usize>,
+    table_bits: u32,
+    /// Record of each line
+    line_map: Vec<usize>,
+    /// Next occurrence of each line's class, or 0
+    next_ptrs: Vec<usize>,
+    ptr_shift: usize,
+    cnt: usize,
+    has_common: bool,
+}
+
+/// The common run found so far.
+#[derive(Default)]
+struct Region {
+    begin1: usize,
+    end1: usize,
+    begin2: usize,
+    end2: usize,
+}
+
+impl HistIndex {
+    fn table_hash(&self, class: usize) -> usize {
+        let bits = self.table_bits;
+        (class + (class >> bits)) & ((1 << bits) - 1)
+    }
+
+    fn cnt(&self, ptr: usize) -> usize {
+        self.records[self.line_map[ptr - self.ptr_shift]].cnt
+    }
+
+    fn next_ptr(&self, ptr: usize) -> usize {
+        self.next_ptrs[ptr - self.ptr_shift]
+    }
+
+    /// Indexes the first range from its end, so each record ends up
+    /// pointing at the first occurrence.
+    fn scan_a(&mut self, env: &XdEnv, line1: usize, count1: usize) -> Result<(), Failed> {
+        for ptr in (line1..line1 + count1).rev() {
+            let class = env.xdf1.ha[ptr - 1];
+            if let Some(&rec) = self.by_class.get(&class) {
+                // Insert it onto the front of the existing chain
+                self.next_ptrs[ptr - self.ptr_shift] = self.records[rec].ptr;
+                self.records[rec].ptr = ptr;
+                self.records[rec].cnt += 1;
+                self.line_map[ptr - self.ptr_shift] = rec;
+                continue;
+            }
+
+            let chain = self.table_hash(class);
+            if self.chain_lens[chain] == MAX_CHAIN_LENGTH {
+                return Err(Failed);
+            }
+            self.chain_lens[chain] += 1;
+            self.by_class.insert(class, self.records.len());
+            self.line_map[ptr - self.ptr_shift] = self.records.len();
+            self.records.push(Record { ptr, cnt: 1 });
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn try_lcs(
+        &mut self,
+        env: &XdEnv,
+        lcs: &mut Region,
+        b_ptr: usize,
+        line1: usize,
+        count1: usize,
+        line2: usize,
+        count2: usize,
+    ) -> usize {
+        let mut b_next = b_ptr + 1;
+        let (end1, end2) = (line1 + count1 - 1, line2 + count2 - 1);
+        let cmp = |a: usize, b: usize| env.xdf1.ha[a - 1] == env.xdf2.ha[b - 1];
+
+        // Only the record of the same class can match
+        let Some(&rec) = self.by_class.get(&env.xdf2.ha[b_ptr - 1]) else {
+            return b_next;
+        };
+        if self.records[rec].cnt > self.cnt {
+            self.has_common = true;
+            return b_next;
+        }
+
+        let mut as_ = self.records[rec].ptr;
+        self.has_common = true;
+        loop {
+            let mut np = self.next_ptr(as_);
+            let mut bs = b_ptr;
+            let mut ae = as_;
+            let mut be = bs;
+            let mut rc = self.records[rec].cnt;
+
+            while line1 < as_ && line2 < bs && cmp(as_ - 1, bs - 1) {
+                as_ -= 1;
+                bs -= 1;
+                if 1 < rc {
+                    rc = rc.min(self.cnt(as_));
+                }
+            }
+            while ae < end1 && be < end2 && cmp(ae + 1, be + 1) {
+                ae += 1;
+                be += 1;
+                if 1 < rc {
+                    rc = rc.min(self.cnt(ae));
+                }
+            }
+
+            if b_next <= be {
+                b_next = be + 1;
+            }
+            if lcs.end1 - lcs.begin1 < ae - as_ || rc < self.cnt {
+                *lcs = Region {
+                    begin1: as_,
+                    end1: ae,
+                    begin2: bs,
+                    end2: be,
+                };
+                self.cnt = rc;
+            }
+
+            if np == 0 {
+                break;
+            }
+            while np <= ae {
+                np = self.next_ptr(np);
+                if np == 0 {
+                    return b_next;
+                }
+            }
+            as_ = np;
+        }
+        b_next
+    }
+}
+
+enum Lcs {
+    Found(Region),
+    /// Lines in common, but all too frequent to split on
+    FallBack,
+}
+
+fn find_lcs(
+    env: &XdEnv,
+    line1: usize,
+    count1: usize,
+    line2: usize,
+    count2: usize,
+) -> Result<Lcs, Failed> {
+    let table_bits = hashbits(count1);
+    let mut index = HistIndex {
+        records: Vec::new(),
+        by_class: HashMap::new(),
+        chain_lens: vec![0; 1 << table_bits],
+        table_bits,
+        line_map: vec![0; count1],
+        next_ptrs: vec![0; count1],
+        ptr_shift: line1,
+        cnt: 0,
+        has_common: false,
+    };
+    index.scan_a(env, line1, count1)?;
+
+    index.cnt = MAX_CHAIN_LENGTH + 1;
+    let mut lcs = Region::default();
+    let mut b_ptr = line2;
+    while b_ptr < line2 + count2 {
+        b_ptr = index.try_lcs(env, &mut lcs, b_ptr, line1, count1, line2, count2);
+    }
+
+    if index.has_common && MAX_CHAIN_LENGTH < index.cnt {
+        Ok(Lcs::FallBack)
+    } else {
+        Ok(Lcs::Found(lcs))
+    }
+}
+
+fn histogram_diff(
+    params: &DiffParams,
+    env: &mut XdEnv,
+    mut line1: usize,
+    mut count1: usize,
+    mut line2: usize,
+    mut count2: usize,
+) -> Result<(), Failed> {
+    loop {
+        if count1 == 0 && count2 == 0 {
+            return Ok(());
+        }
+        if params.aborted() {
+            return Err(Failed);
+        }
+
+        if count1 == 0 {
+            env.xdf2
+                .changes_mut(line2 as isize - 1, count2 as isize)
+                .fill(true);
+            return Ok(());
+        } else if count2 == 0 {
+            env.xdf1
+                .changes_mut(line1 as isize - 1, count1 as isize)
+                .fill(true);
+            return Ok(());
+        }
+
+        let lcs = match find_lcs(env, line1, count1, line2, count2)? {
+            Lcs::FallBack => {
+                return fall_back_diff(
+                    env,
+                    params,
+                    line1 as isize,
+                    count1 as isize,
+                    line2 as isize,
+                    count2 as isize,
+                );
+            }
+            Lcs::Found(lcs) => lcs,
+        };
+
+        if lcs.begin1 == 0 && lcs.begin2 == 0 {
+            env.xdf1
+                .changes_mut(line1 as isize - 1, count1 as isize)
+                .fill(true);
+            env.xdf2
+                .changes_mut(line2 as isize - 1, count2 as isize)
+                .fill(true);
+            return Ok(());
+        }
+
+        histogram_diff(
+            params,
+            env,
+            line1,
+            lcs.begin1 - line1,
+            line2,
+            lcs.begin2 - line2,
+        )?;
+        // Tail recursion on the lines after the common run
+        count1 = line1 + count1 - 1 - lcs.end1;
+        line1 = lcs.end1 + 1;
+        count2 = line2 + count2 - 1 - lcs.end2;
+        line2 = lcs.end2 + 1;
+    }
+}
+
+pub(super) fn do_histogram_diff(params: &DiffParams, env: &mut XdEnv) -> Result<(), Failed> {
+    let (count1, count2) = (env.xdf1.nrec() as usize, env.xdf2.nrec() as usize);
+    histogram_diff(params, env, 1, count1, 1, count2)
+}
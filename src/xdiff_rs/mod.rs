@@ -0,0 +1,68 @@
+//! A Rust port of the parts of xdiff this crate uses, for targets the C
+//! sources do not build for, such as `wasm32-unknown-unknown`.
+//!
+//! Each module follows its C counterpart function by function, down to
+//! the heuristics, so both backends give the same diffs and the same merged
+//! output byte for byte. Line numbers are kept signed as in C, where the
+//! algorithms step one past either end.
+
+mod diffi;
+mod histogram;
+mod patience;
+mod prepare;
+mod xmerge;
+
+pub(crate) use diffi::diff;
+pub(crate) use xmerge::{MergeParams, merge};
+
+use crate::types::DiffAlgorithm;
+
+/// What `xpparam_t` carries for the algorithms in use.
+#[derive(Clone, Copy)]
+pub(crate) struct DiffParams<'a> {
+    pub algorithm: DiffAlgorithm,
+    /// Polled while diffing, like xdiff's `abort_func`
+    pub abort: &'a dyn Fn() -> bool,
+}
+
+impl DiffParams<'_> {
+    fn aborted(&self) -> bool {
+        (self.abort)()
+    }
+}
+
+/// A diff that could not finish: it was aborted, or the histogram
+/// algorithm met more distinct lines than its hash table takes.
+#[derive(Debug)]
+pub(crate) struct Failed;
+
+/// xdiff's `xdl_hash_record` without whitespace flags: a hash of the line
+/// up to its newline. Returns the hash and the start of the next line.
+pub(crate) fn hash_record(text: &[u8], start: usize) -> (u64, usize) {
+    let mut ha: u64 = 5381;
+    let mut end = start;
+    while end < text.len() && text[end] != b'\n' {
+        ha = ha.wrapping_add(ha << 5) ^ u64::from(text[end]);
+        end += 1;
+    }
+    (ha, if end < text.len() { end + 1 } else { end })
+}
+
+/// Integer square root approximation (`xdl_bogosqrt`).
+fn bogosqrt(mut n: isize) -> isize {
+    let mut i = 1;
+    while n > 0 {
+        n >>= 2;
+        i <<= 1;
+    }
+    i
+}
+
+/// Bits for a hash table with at least `size` slots (`xdl_hashbits`).
+fn hashbits(size: usize) -> u32 {
+    let mut bits = 0;
+    while (1u64 << bits) < size as u64 && bits < u32::BITS {
+        bits += 1;
+    }
+    bits.max(1)
+}
@@ -0,0 +1,232 @@
+//! Patience diff (xpatience.c).
+//!
+//! Lines that occur once in both ranges anchor the diff: the longest
+//! sequence of them in the same order in both files is taken as common,
+//! and the ranges between those lines are diffed recursively. Ranges
+//! without such lines fall back to Myers. Line numbers are 1-based.
+
+use super::diffi::fall_back_diff;
+use super::prepare::XdEnv;
+use super::{DiffParams, Failed};
+use std::collections::HashMap;
+
+/// `line2` of a line that is not unique in one of the ranges
+const NON_UNIQUE: isize = isize::MAX;
+
+struct Entry {
+    line1: isize,
+    /// 0 while the line is not seen in the second range
+    line2: isize,
+    /// Initially the next entry in the order of the first file; after
+    /// `find_longest_common_sequence`, the next line of the sequence
+    next: Option<usize>,
+    previous: Option<usize>,
+}
+
+/// The lines of the first range by class, in order of appearance.
+struct LineMap {
+    entries: Vec<Entry>,
+    by_class: HashMap<usize, usize>,
+    /// Were common records found?
+    has_matches: bool,
+}
+
+impl LineMap {
+    fn insert_record(&mut self, env: &XdEnv, line: isize, pass: u8) {
+        let class = if pass == 1 {
+            env.xdf1.ha[line as usize - 1]
+        } else {
+            env.xdf2.ha[line as usize - 1]
+        };
+        if let Some(&index) = self.by_class.get(&class) {
+            let entry = &mut self.entries[index];
+            if pass == 2 {
+                self.has_matches = true;
+            }
+            entry.line2 = if pass == 1 || entry.line2 != 0 {
+                NON_UNIQUE
+            } else {
+                line
+            };
+            return;
+        }
+        if pass == 2 {
+            return;
+        }
+        let index = self.entries.len();
+        if let Some(last) = self.entries.last_mut() {
+            last.next = Some(index);
+        }
+        self.entries.push(Entry {
+            line1: line,
+            line2: 0,
+            next: None,
+            previous: index.checked_sub(1),
+        });
+        self.by_class.insert(class, index);
+    }
+
+    /// Builds the map for one recursion, where lines that were not unique
+    /// in the whole file can be unique in the smaller ranges.
+    fn fill(env: &XdEnv, line1: isize, count1: isize, line2: isize, count2: isize) -> Self {
+        let mut map = LineMap {
+            entries: Vec::with_capacity(count1 as usize),
+            by_class: HashMap::with_capacity(count1 as usize),
+            has_matches: false,
+        };
+        for line in line1..line1 + count1 {
+            map.insert_record(env, line, 1);
+        }
+        for line in line2..line2 + count2 {
+            map.insert_record(env, line, 2);
+        }
+        map
+    }
+
+    /// The longest sequence in `sequence` whose last element has a smaller
+    /// `line2` than `entry`, as an index into `sequence`.
+    fn binary_search(&self, sequence: &[usize], entry: usize) -> isize {
+        let (mut left, mut right) = (-1isize, sequence.len() as isize);
+        while left + 1 < right {
+            let middle = left + (right - left) / 2;
+            // By construction, no two entries can be equal
+            if self.entries[sequence[middle as usize]].line2 > self.entries[entry].line2 {
+                right = middle;
+            } else {
+                left = middle;
+            }
+        }
+        left
+    }
+
+    /// Finds the longest sequence of unique common lines in the order of
+    /// both files, keeping for each length the sequence with the smallest
+    /// last `line2`. Links it through `next` and returns its first entry.
+    fn find_longest_common_sequence(&mut self) -> Option<usize> {
+        let mut sequence: Vec<usize> = Vec::new();
+        let mut entry = (!self.entries.is_empty()).then_some(0);
+        while let Some(index) = entry {
+            entry = self.entries[index].next;
+            let line2 = self.entries[index].line2;
+            if line2 == 0 || line2 == NON_UNIQUE {
+                continue;
+            }
+            let i = self.binary_search(&sequence, index);
+            self.entries[index].previous = (i >= 0).then(|| sequence[i as usize]);
+            let i = (i + 1) as usize;
+            if i == sequence.len() {
+                sequence.push(index);
+            } else {
+                sequence[i] = index;
+            }
+        }
+
+        // Walk back from the last element, adjusting the `next` members
+        let mut entry = *sequence.last()?;
+        self.entries[entry].next = None;
+        while let Some(previous) = self.entries[entry].previous {
+            self.entries[previous].next = Some(entry);
+            entry = previous;
+        }
+        Some(entry)
+    }
+}
+
+fn matches(env: &XdEnv, line1: isize, line2: isize) -> bool {
+    env.xdf1.ha[line1 as usize - 1] == env.xdf2.ha[line2 as usize - 1]
+}
+
+#[allow(clippy::too_many_arguments)]
+fn walk_common_sequence(
+    params: &DiffParams,
+    env: &mut XdEnv,
+    map: &LineMap,
+    mut first: Option<usize>,
+    mut line1: isize,
+    count1: isize,
+    mut line2: isize,
+    count2: isize,
+) -> Result<(), Failed> {
+    let (end1, end2) = (line1 + count1, line2 + count2);
+
+    loop {
+        // Try to grow the line ranges of common lines
+        let (mut next1, mut next2);
+        if let Some(index) = first {
+            next1 = map.entries[index].line1;
+            next2 = map.entries[index].line2;
+            while next1 > line1 && next2 > line2 && matches(env, next1 - 1, next2 - 1) {
+                next1 -= 1;
+                next2 -= 1;
+            }
+        } else {
+            next1 = end1;
+            next2 = end2;
+        }
+        while line1 < next1 && line2 < next2 && matches(env, line1, line2) {
+            line1 += 1;
+            line2 += 1;
+        }
+
+        // Recurse
+        if next1 > line1 || next2 > line2 {
+            patience_diff(params, env, line1, next1 - line1, line2, next2 - line2)?;
+        }
+
+        let Some(mut index) = first else {
+            return Ok(());
+        };
+        while let Some(next) = map.entries[index].next
+            && map.entries[next].line1 == map.entries[index].line1 + 1
+            && map.entries[next].line2 == map.entries[index].line2 + 1
+        {
+            index = next;
+        }
+        line1 = map.entries[index].line1 + 1;
+        line2 = map.entries[index].line2 + 1;
+        first = map.entries[index].next;
+    }
+}
+
+fn patience_diff(
+    params: &DiffParams,
+    env: &mut XdEnv,
+    line1: isize,
+    count1: isize,
+    line2: isize,
+    count2: isize,
+) -> Result<(), Failed> {
+    if params.aborted() {
+        return Err(Failed);
+    }
+
+    // Trivial case: one side is empty
+    if count1 == 0 {
+        env.xdf2.changes_mut(line2 - 1, count2).fill(true);
+        return Ok(());
+    } else if count2 == 0 {
+        env.xdf1.changes_mut(line1 - 1, count1).fill(true);
+        return Ok(());
+    }
+
+    let mut map = LineMap::fill(env, line1, count1, line2, count2);
+
+    // Are there any matching lines at all?
+    if !map.has_matches {
+        env.xdf1.changes_mut(line1 - 1, count1).fill(true);
+        env.xdf2.changes_mut(line2 - 1, count2).fill(true);
+        return Ok(());
+    }
+
+    match map.find_longest_common_sequence() {
+        Some(first) => {
+            walk_common_sequence(params, env, &map, Some(first), line1, count1, line2, count2)
+        }
+        None => fall_back_diff(env, params, line1, count1, line2, count2),
+    }
+}
+
+pub(super) fn do_patience_diff(params: &DiffParams, env: &mut XdEnv) -> Result<(), Failed> {
+    let (count1, count2) = (env.xdf1.nrec(), env.xdf2.nrec());
+    patience_diff(params, env, 1, count1, 1, count2)
+}
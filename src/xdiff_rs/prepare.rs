@@ -0,0 +1,238 @@
+//! Splitting files into records and classifying them (xprepare.c).
+
+use super::bogosqrt;
+use crate::types::DiffAlgorithm;
+use std::collections::HashMap;
+
+const KPDIS_RUN: isize = 4;
+const MAX_EQLIMIT: isize = 1024;
+const SIMSCAN_WINDOW: isize = 100;
+
+/// One side of a diff (`xdfile_t`).
+pub(super) struct XdFile<'a> {
+    data: &'a [u8],
+    /// Byte offset of each record, plus the end of the data
+    starts: Vec<usize>,
+    /// Class of each record: records with equal bytes share one
+    pub ha: Vec<usize>,
+    /// Changed flags, with an unchanged sentinel on either side
+    rchg: Vec<bool>,
+    /// Myers only: the records left to diff after trimming and discarding
+    pub rindex: Vec<isize>,
+    pub reff_ha: Vec<usize>,
+    pub dstart: isize,
+    pub dend: isize,
+}
+
+impl<'a> XdFile<'a> {
+    pub fn nrec(&self) -> isize {
+        self.ha.len() as isize
+    }
+
+    /// Record `i` with its newline
+    pub fn rec(&self, i: isize) -> &'a [u8] {
+        let i = i as usize;
+        &self.data[self.starts[i]..self.starts[i + 1]]
+    }
+
+    /// `count` records from `i`, which lie one after another in the file
+    pub fn recs(&self, i: isize, count: isize) -> &'a [u8] {
+        &self.data[self.starts[i as usize]..self.starts[(i + count) as usize]]
+    }
+
+    pub fn changed(&self, i: isize) -> bool {
+        self.rchg[(i + 1) as usize]
+    }
+
+    pub fn set_changed(&mut self, i: isize, changed: bool) {
+        self.rchg[(i + 1) as usize] = changed;
+    }
+
+    /// The flags of records `i..i + count`
+    pub fn changes(&self, i: isize, count: isize) -> &[bool] {
+        &self.rchg[(i + 1) as usize..(i + 1 + count) as usize]
+    }
+
+    pub fn changes_mut(&mut self, i: isize, count: isize) -> &mut [bool] {
+        &mut self.rchg[(i + 1) as usize..(i + 1 + count) as usize]
+    }
+
+    /// The reduced records, their indices and the changed flags, borrowed
+    /// apart for `xdl_recs_cmp`
+    pub fn diff_data(&mut self) -> (&[usize], &[isize], &mut [bool]) {
+        (&self.reff_ha, &self.rindex, &mut self.rchg[1..])
+    }
+}
+
+/// Both sides of a diff (`xdfenv_t`).
+pub(super) struct XdEnv<'a> {
+    pub xdf1: XdFile<'a>,
+    pub xdf2: XdFile<'a>,
+}
+
+/// Assigns each distinct record a class, numbered in order of first
+/// appearance across both files (`xdlclassifier_t`).
+#[derive(Default)]
+struct Classifier<'a> {
+    classes: HashMap<&'a [u8], usize>,
+    /// Records of each class in the first and second file
+    len1: Vec<isize>,
+    len2: Vec<isize>,
+}
+
+impl<'a> Classifier<'a> {
+    fn prepare_ctx(&mut self, pass: u8, data: &'a [u8]) -> XdFile<'a> {
+        let mut starts = vec![0];
+        let mut ha = Vec::new();
+        let mut cur = 0;
+        while cur < data.len() {
+            let end = data[cur..]
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(data.len(), |i| cur + i + 1);
+            let next = self.classes.len();
+            let class = *self.classes.entry(&data[cur..end]).or_insert(next);
+            if class == next {
+                self.len1.push(0);
+                self.len2.push(0);
+            }
+            if pass == 1 {
+                self.len1[class] += 1;
+            } else {
+                self.len2[class] += 1;
+            }
+            ha.push(class);
+            starts.push(end);
+            cur = end;
+        }
+        let nrec = ha.len() as isize;
+        XdFile {
+            data,
+            starts,
+            ha,
+            rchg: vec![false; nrec as usize + 2],
+            rindex: Vec::new(),
+            reff_ha: Vec::new(),
+            dstart: 0,
+            dend: nrec - 1,
+        }
+    }
+}
+
+pub(super) fn prepare_env<'a>(mf1: &'a [u8], mf2: &'a [u8], algorithm: DiffAlgorithm) -> XdEnv<'a> {
+    let mut cf = Classifier::default();
+    let mut xdf1 = cf.prepare_ctx(1, mf1);
+    let mut xdf2 = cf.prepare_ctx(2, mf2);
+
+    if !matches!(
+        algorithm,
+        DiffAlgorithm::Patience | DiffAlgorithm::Histogram
+    ) {
+        trim_ends(&mut xdf1, &mut xdf2);
+        cleanup_records(&cf, &mut xdf1, &mut xdf2);
+    }
+    XdEnv { xdf1, xdf2 }
+}
+
+fn clean_mmatch(dis: &[u8], i: isize, mut s: isize, mut e: isize) -> bool {
+    // Limits the window that is examined during the similar-lines scan
+    if i - s > SIMSCAN_WINDOW {
+        s = i - SIMSCAN_WINDOW;
+    }
+    if e - i > SIMSCAN_WINDOW {
+        e = i + SIMSCAN_WINDOW;
+    }
+
+    // Scans the lines before `i` for a run of lines that have no match or
+    // have multiple matches
+    let (mut rdis0, mut rpdis0) = (0, 1);
+    let mut r = 1;
+    while i - r >= s {
+        match dis[(i - r) as usize] {
+            0 => rdis0 += 1,
+            2 => rpdis0 += 1,
+            _ => break,
+        }
+        r += 1;
+    }
+    // Multimatch lines are discarded only in the middle of runs of lines
+    // with no match
+    if rdis0 == 0 {
+        return false;
+    }
+    let (mut rdis1, mut rpdis1) = (0, 1);
+    r = 1;
+    while i + r <= e {
+        match dis[(i + r) as usize] {
+            0 => rdis1 += 1,
+            2 => rpdis1 += 1,
+            _ => break,
+        }
+        r += 1;
+    }
+    if rdis1 == 0 {
+        return false;
+    }
+    rdis1 += rdis0;
+    rpdis1 += rpdis0;
+
+    rpdis1 * KPDIS_RUN < rpdis1 + rdis1
+}
+
+/// Discards records with no match in the other file, and runs of records
+/// with many, before Myers sees them.
+fn cleanup_records(cf: &Classifier, xdf1: &mut XdFile, xdf2: &mut XdFile) {
+    fn discards(xdf: &XdFile, other_len: &[isize]) -> Vec<u8> {
+        let mlim = bogosqrt(xdf.nrec()).min(MAX_EQLIMIT);
+        let mut dis = vec![0; xdf.nrec() as usize + 1];
+        for i in xdf.dstart..=xdf.dend {
+            let nm = other_len[xdf.ha[i as usize]];
+            dis[i as usize] = if nm == 0 {
+                0
+            } else if nm >= mlim {
+                2
+            } else {
+                1
+            };
+        }
+        dis
+    }
+
+    fn keep(xdf: &mut XdFile, dis: &[u8]) {
+        for i in xdf.dstart..=xdf.dend {
+            let d = dis[i as usize];
+            if d == 1 || (d == 2 && !clean_mmatch(dis, i, xdf.dstart, xdf.dend)) {
+                xdf.rindex.push(i);
+                xdf.reff_ha.push(xdf.ha[i as usize]);
+            } else {
+                xdf.set_changed(i, true);
+            }
+        }
+    }
+
+    let dis1 = discards(xdf1, &cf.len2);
+    let dis2 = discards(xdf2, &cf.len1);
+    keep(xdf1, &dis1);
+    keep(xdf2, &dis2);
+}
+
+/// Leaves the common head and tail out of the records to diff.
+fn trim_ends(xdf1: &mut XdFile, xdf2: &mut XdFile) {
+    let mut lim = xdf1.nrec().min(xdf2.nrec());
+    let mut i = 0;
+    while i < lim && xdf1.ha[i as usize] == xdf2.ha[i as usize] {
+        i += 1;
+    }
+    xdf1.dstart = i;
+    xdf2.dstart = i;
+
+    lim -= i;
+    i = 0;
+    while i < lim
+        && xdf1.ha[(xdf1.nrec() - 1 - i) as usize] == xdf2.ha[(xdf2.nrec() - 1 - i) as usize]
+    {
+        i += 1;
+    }
+    xdf1.dend = xdf1.nrec() - i - 1;
+    xdf2.dend = xdf2.nrec() - i - 1;
+}
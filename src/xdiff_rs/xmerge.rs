@@ -0,0 +1,762 @@
+//! Three-way merge of two diffs against the same base (xmerge.c).
+
+use super::diffi::{Change, build_script, compact, do_diff};
+use super::prepare::{XdEnv, XdFile};
+use super::{DiffParams, Failed};
+use crate::output::{Sink, write_error};
+use crate::types::*;
+use std::cmp::Ordering;
+use std::io::{self, Write};
+
+const DEFAULT_CONFLICT_MARKER_SIZE: usize = 7;
+
+// Indices of the marker kinds (`XDL_MARKER_*`)
+const MARKER_START: usize = 0;
+const MARKER_BASE: usize = 1;
+const MARKER_SEPARATOR: usize = 2;
+const MARKER_END: usize = 3;
+
+/// What `xmparam_t` carries.
+pub(crate) struct MergeParams<'a> {
+    pub diff: DiffParams<'a>,
+    /// 0 for the default
+    pub marker_size: usize,
+    pub level: MergeLevel,
+    pub favor: Option<MergeFavor>,
+    pub style: MergeStyle,
+    pub ancestor: Option<&'a str>,
+    pub file1: Option<&'a str>,
+    pub file2: Option<&'a str>,
+    pub resolve_sorted: bool,
+    pub markers: &'a ConflictMarkers,
+}
+
+/// A hunk of the merge (`xdmerge_t`).
+#[derive(Debug, Clone, Copy)]
+struct XdMerge {
+    /// 0 = conflict, 1 = take first, 2 = take second, 3 = take both,
+    /// 4 = both sides identical after refining, 5 = sorted union
+    mode: u8,
+    /// The hunk in the postimages
+    i1: isize,
+    i2: isize,
+    chg1: isize,
+    chg2: isize,
+    /// The hunk in the preimage
+    i0: isize,
+    chg0: isize,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn append_merge(
+    merge: &mut Vec<XdMerge>,
+    mode: u8,
+    i0: isize,
+    chg0: isize,
+    i1: isize,
+    chg1: isize,
+    i2: isize,
+    chg2: isize,
+) {
+    if let Some(m) = merge.last_mut()
+        && (i1 <= m.i1 + m.chg1 || i2 <= m.i2 + m.chg2)
+    {
+        if mode != m.mode {
+            m.mode = 0;
+        }
+        m.chg0 = i0 + chg0 - m.i0;
+        m.chg1 = i1 + chg1 - m.i1;
+        m.chg2 = i2 + chg2 - m.i2;
+    } else {
+        merge.push(XdMerge {
+            mode,
+            i1,
+            i2,
+            chg1,
+            chg2,
+            i0,
+            chg0,
+        });
+    }
+}
+
+fn merge_cmp_lines(xe1: &XdEnv, i1: isize, xe2: &XdEnv, i2: isize, line_count: isize) -> bool {
+    (0..line_count).all(|i| xe1.xdf2.rec(i1 + i) == xe2.xdf2.rec(i2 + i))
+}
+
+/// The merge result as it is produced. Without a writer only the size is
+/// counted, which is how a `Vec` sink is sized. Lines are only counted
+/// while conflicts are recorded in `regions`.
+struct Out<'w> {
+    writer: Option<&'w mut dyn Write>,
+    size: usize,
+    err: Option<io::Error>,
+    regions: Option<&'w mut Vec<ConflictRegion>>,
+    lines: usize,
+}
+
+impl Out<'_> {
+    fn put(&mut self, bytes: &[u8]) {
+        if bytes.is_empty() || self.err.is_some() {
+            return;
+        }
+        if let Some(writer) = &mut self.writer
+            && let Err(err) = writer.write_all(bytes)
+        {
+            self.err = Some(err);
+        }
+        if self.regions.is_some() {
+            self.lines += bytes.iter().filter(|&&b| b == b'\n').count();
+        }
+        self.size += bytes.len();
+    }
+
+    fn fill(&mut self, c: u8, len: usize) {
+        let chunk = [c; 64];
+        let mut len = len;
+        while len > 0 {
+            let n = len.min(chunk.len());
+            self.put(&chunk[..n]);
+            len -= n;
+        }
+    }
+
+    fn eol(&mut self, needs_cr: bool) {
+        self.put(if needs_cr { b"\r\n" } else { b"\n" });
+    }
+
+    /// Writes the `kind`th marker of start, base, separator and end.
+    fn marker(
+        &mut self,
+        markers: &ConflictMarkers,
+        kind: usize,
+        marker_size: usize,
+        name: Option<&str>,
+        needs_cr: bool,
+    ) {
+        let template = markers.templates()[kind];
+        self.put(template.prefix.as_bytes());
+        self.fill(b"<|=>"[kind], marker_size);
+        if let Some(name) = name {
+            let (label_prefix, label_suffix) = markers.label_parts();
+            self.put(label_prefix.as_bytes());
+            self.put(name.as_bytes());
+            self.put(label_suffix.as_bytes());
+        }
+        self.put(template.suffix.as_bytes());
+        self.eol(needs_cr);
+    }
+}
+
+fn recs_copy_0(xdf: &XdFile, i: isize, count: isize, needs_cr: bool, add_nl: bool, out: &mut Out) {
+    if count < 1 {
+        return;
+    }
+    let recs = xdf.recs(i, count);
+    out.put(recs);
+    if add_nl && !recs.ends_with(b"\n") {
+        out.eol(needs_cr);
+    }
+}
+
+/// Copies lines of the postimage of `xe`.
+fn recs_copy(xe: &XdEnv, i: isize, count: isize, needs_cr: bool, add_nl: bool, out: &mut Out) {
+    recs_copy_0(&xe.xdf2, i, count, needs_cr, add_nl, out);
+}
+
+/// Copies lines of the preimage.
+fn orig_copy(xe: &XdEnv, i: isize, count: isize, needs_cr: bool, add_nl: bool, out: &mut Out) {
+    recs_copy_0(&xe.xdf1, i, count, needs_cr, add_nl, out);
+}
+
+/// Whether the `i`th line ends in CR/LF (for a last line without eol, the
+/// line before it), or `None` if that cannot be determined.
+fn is_eol_crlf(file: &XdFile, i: isize) -> Option<bool> {
+    let crlf = |i: isize| {
+        let rec = file.rec(i);
+        rec.len() > 1 && rec[rec.len() - 2] == b'\r'
+    };
+    if i < file.nrec() - 1 {
+        // All lines before the last must end in LF
+        return Some(crlf(i));
+    }
+    if file.nrec() == 0 {
+        return None;
+    }
+    if file.rec(i).ends_with(b"\n") {
+        return Some(crlf(i));
+    }
+    if i == 0 {
+        // The only line has no eol
+        return None;
+    }
+    Some(crlf(i - 1))
+}
+
+fn is_cr_needed(xe1: &XdEnv, xe2: &XdEnv, m: &XdMerge) -> bool {
+    // Match the postimages' preceding, or first, lines' end-of-line style,
+    // then the preimage's first line; if still undecided, use LF
+    let mut needs_cr = is_eol_crlf(&xe1.xdf2, if m.i1 > 0 { m.i1 - 1 } else { 0 });
+    if needs_cr != Some(false) {
+        needs_cr = is_eol_crlf(&xe2.xdf2, if m.i2 > 0 { m.i2 - 1 } else { 0 });
+    }
+    if needs_cr != Some(false) {
+        needs_cr = is_eol_crlf(&xe1.xdf1, 0);
+    }
+    needs_cr.unwrap_or(false)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn fill_conflict_hunk(
+    xe1: &XdEnv,
+    name1: Option<&str>,
+    xe2: &XdEnv,
+    name2: Option<&str>,
+    name3: Option<&str>,
+    i: isize,
+    style: MergeStyle,
+    m: &XdMerge,
+    out: &mut Out,
+    xmp: &MergeParams,
+) {
+    let needs_cr = is_cr_needed(xe1, xe2, m);
+    let marker_size = if xmp.marker_size == 0 {
+        DEFAULT_CONFLICT_MARKER_SIZE
+    } else {
+        xmp.marker_size
+    };
+    let markers = xmp.markers;
+
+    // Before conflicting part
+    recs_copy(xe1, i, m.i1 - i, false, false, out);
+    let start = out.lines;
+    out.marker(markers, MARKER_START, marker_size, name1, needs_cr);
+
+    // Postimage from side #1
+    let ours = out.lines;
+    recs_copy(xe1, m.i1, m.chg1, needs_cr, true, out);
+    let ours = ours..out.lines;
+
+    let mut base = None;
+    if style != MergeStyle::Normal {
+        // Shared preimage
+        out.marker(markers, MARKER_BASE, marker_size, name3, needs_cr);
+        let from = out.lines;
+        orig_copy(xe1, m.i0, m.chg0, needs_cr, true, out);
+        base = Some(from..out.lines);
+    }
+    out.marker(markers, MARKER_SEPARATOR, marker_size, None, needs_cr);
+
+    // Postimage from side #2
+    let theirs = out.lines;
+    recs_copy(xe2, m.i2, m.chg2, needs_cr, true, out);
+    let theirs = theirs..out.lines;
+    out.marker(markers, MARKER_END, marker_size, name2, needs_cr);
+
+    // Sizing a `Vec` sink records nothing; only the pass that writes does
+    let end = out.lines;
+    if let Some(regions) = &mut out.regions {
+        regions.push(ConflictRegion {
+            start,
+            ours,
+            base,
+            theirs,
+            end,
+        });
+    }
+}
+
+/// A record without its line ending.
+fn rec_content(rec: &[u8]) -> &[u8] {
+    let rec = rec.strip_suffix(b"\n").unwrap_or(rec);
+    rec.strip_suffix(b"\r").unwrap_or(rec)
+}
+
+fn rec_content_cmp(rec1: &[u8], rec2: &[u8]) -> Ordering {
+    rec_content(rec1).cmp(rec_content(rec2))
+}
+
+/// The first line of side #2 at or after `i2` that side #1 lacks.
+fn next_union_line(xe1: &XdEnv, xe2: &XdEnv, m: &XdMerge, mut i2: isize) -> isize {
+    while i2 < m.i2 + m.chg2 {
+        let rec2 = xe2.xdf2.rec(i2);
+        if !(m.i1..m.i1 + m.chg1).any(|i1| rec_content_cmp(xe1.xdf2.rec(i1), rec2).is_eq()) {
+            break;
+        }
+        i2 += 1;
+    }
+    i2
+}
+
+/// Union of both sides of a conflict, leaving out the lines of side #2
+/// that side #1 already has. If sorted, the sides are interleaved like two
+/// sorted lists.
+fn fill_union_dedup(xe1: &XdEnv, xe2: &XdEnv, m: &XdMerge, sorted: bool, out: &mut Out) {
+    let needs_cr = is_cr_needed(xe1, xe2, m);
+    let (mut i1, end1) = (m.i1, m.i1 + m.chg1);
+    let (mut i2, end2) = (next_union_line(xe1, xe2, m, m.i2), m.i2 + m.chg2);
+
+    while i1 < end1 || i2 < end2 {
+        if i2 >= end2
+            || (i1 < end1
+                && (!sorted || rec_content_cmp(xe1.xdf2.rec(i1), xe2.xdf2.rec(i2)).is_le()))
+        {
+            let last = i1 + 1 == end1 && i2 >= end2;
+            recs_copy(xe1, i1, 1, needs_cr, !last, out);
+            i1 += 1;
+        } else {
+            let next = next_union_line(xe1, xe2, m, i2 + 1);
+            let last = next >= end2 && i1 >= end1;
+            recs_copy(xe2, i2, 1, needs_cr, !last, out);
+            i2 = next;
+        }
+    }
+}
+
+/// Steps through the sorted union of both sides of a hunk, skipping lines
+/// of the preimage that either side deleted (`xdsortwalk_t`).
+struct SortWalk<'e, 'a> {
+    xe1: &'e XdEnv<'a>,
+    xe2: &'e XdEnv<'a>,
+    m: XdMerge,
+    i0: isize,
+    i1: isize,
+    i2: isize,
+}
+
+impl<'e, 'a> Iterator for SortWalk<'e, 'a> {
+    type Item = (&'e XdEnv<'a>, isize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let m = self.m;
+        while self.i1 < m.i1 + m.chg1 || self.i2 < m.i2 + m.chg2 {
+            let cmp = if self.i1 == m.i1 + m.chg1 {
+                Ordering::Greater
+            } else if self.i2 == m.i2 + m.chg2 {
+                Ordering::Less
+            } else {
+                rec_content_cmp(self.xe1.xdf2.rec(self.i1), self.xe2.xdf2.rec(self.i2))
+            };
+            let rec = if cmp.is_le() {
+                self.xe1.xdf2.rec(self.i1)
+            } else {
+                self.xe2.xdf2.rec(self.i2)
+            };
+
+            let rec0 = |i0: isize| self.xe1.xdf1.rec(i0);
+            while self.i0 < m.i0 + m.chg0 && rec_content_cmp(rec0(self.i0), rec).is_lt() {
+                self.i0 += 1;
+            }
+            let in_base = self.i0 < m.i0 + m.chg0 && rec_content_cmp(rec0(self.i0), rec).is_eq();
+
+            let item = if cmp.is_le() {
+                (self.xe1, self.i1)
+            } else {
+                (self.xe2, self.i2)
+            };
+            if cmp.is_le() {
+                self.i1 += 1;
+            }
+            if cmp.is_ge() {
+                self.i2 += 1;
+            }
+            // A preimage line survives only if both sides kept it
+            if !in_base || cmp.is_eq() {
+                return Some(item);
+            }
+        }
+        None
+    }
+}
+
+fn fill_sorted_union(xe1: &XdEnv, xe2: &XdEnv, m: &XdMerge, out: &mut Out) {
+    let needs_cr = is_cr_needed(xe1, xe2, m);
+    let mut walk = SortWalk {
+        xe1,
+        xe2,
+        m: *m,
+        i0: m.i0,
+        i1: m.i1,
+        i2: m.i2,
+    }
+    .peekable();
+    while let Some((xe, i)) = walk.next() {
+        recs_copy(xe, i, 1, needs_cr, walk.peek().is_some(), out);
+    }
+}
+
+fn is_blank(rec: &[u8]) -> bool {
+    rec_content(rec).is_empty()
+}
+
+/// Is `recs[i..i + chg]` a sorted set of lines that also sorts between the
+/// context lines around the hunk? Blank context lines end a block and do
+/// not constrain it.
+fn is_sorted_block(
+    xdf: &XdFile,
+    i: isize,
+    chg: isize,
+    prev: Option<&[u8]>,
+    next: Option<&[u8]>,
+) -> bool {
+    if chg == 0 {
+        return true;
+    }
+    if let Some(prev) = prev
+        && !is_blank(prev)
+        && rec_content_cmp(prev, xdf.rec(i)).is_ge()
+    {
+        return false;
+    }
+    if let Some(next) = next
+        && !is_blank(next)
+        && rec_content_cmp(xdf.rec(i + chg - 1), next).is_ge()
+    {
+        return false;
+    }
+    (i..i + chg - 1).all(|k| rec_content_cmp(xdf.rec(k), xdf.rec(k + 1)).is_lt())
+}
+
+fn chunk_has(xdf: &XdFile, i: isize, chg: isize, rec: &[u8]) -> bool {
+    (i..i + chg).any(|k| rec_content_cmp(xdf.rec(k), rec).is_eq())
+}
+
+/// Resolves conflicts whose preimage and both postimages are sorted sets
+/// of lines. A hunk where both sides removed the same line is left as a
+/// conflict.
+fn resolve_sorted_conflicts(xe1: &XdEnv, xe2: &XdEnv, changes: &mut [XdMerge]) {
+    let xdf0 = &xe1.xdf1;
+    for m in changes.iter_mut().filter(|m| m.mode == 0) {
+        let prev = (m.i0 > 0).then(|| xdf0.rec(m.i0 - 1));
+        let next = (m.i0 + m.chg0 < xdf0.nrec()).then(|| xdf0.rec(m.i0 + m.chg0));
+        if !is_sorted_block(xdf0, m.i0, m.chg0, prev, next)
+            || !is_sorted_block(&xe1.xdf2, m.i1, m.chg1, prev, next)
+            || !is_sorted_block(&xe2.xdf2, m.i2, m.chg2, prev, next)
+        {
+            continue;
+        }
+        if (m.i0..m.i0 + m.chg0).all(|k| {
+            chunk_has(&xe1.xdf2, m.i1, m.chg1, xdf0.rec(k))
+                || chunk_has(&xe2.xdf2, m.i2, m.chg2, xdf0.rec(k))
+        }) {
+            m.mode = 5;
+        }
+    }
+}
+
+fn fill_merge_buffer(
+    xe1: &XdEnv,
+    xe2: &XdEnv,
+    xmp: &MergeParams,
+    changes: &mut [XdMerge],
+    out: &mut Out,
+) {
+    let dedup = matches!(
+        xmp.favor,
+        Some(MergeFavor::UnionDedup | MergeFavor::UnionSorted)
+    );
+    let favor_mode = match xmp.favor {
+        None => 0,
+        Some(MergeFavor::Ours) => 1,
+        Some(MergeFavor::Theirs) => 2,
+        Some(_) => 3,
+    };
+
+    let mut i = 0;
+    for m in changes {
+        if favor_mode != 0 && m.mode == 0 {
+            m.mode = favor_mode;
+        }
+
+        if m.mode == 0 {
+            fill_conflict_hunk(
+                xe1,
+                xmp.file1,
+                xe2,
+                xmp.file2,
+                xmp.ancestor,
+                i,
+                xmp.style,
+                m,
+                out,
+                xmp,
+            );
+        } else if m.mode == 5 {
+            // Before conflicting part
+            recs_copy(xe1, i, m.i1 - i, false, false, out);
+            fill_sorted_union(xe1, xe2, m, out);
+        } else if dedup && m.mode == 3 {
+            recs_copy(xe1, i, m.i1 - i, false, false, out);
+            fill_union_dedup(xe1, xe2, m, xmp.favor == Some(MergeFavor::UnionSorted), out);
+        } else if m.mode & 3 != 0 {
+            recs_copy(xe1, i, m.i1 - i, false, false, out);
+            // Postimage from side #1
+            if m.mode & 1 != 0 {
+                let needs_cr = is_cr_needed(xe1, xe2, m);
+                recs_copy(xe1, m.i1, m.chg1, needs_cr, m.mode & 2 != 0, out);
+            }
+            // Postimage from side #2
+            if m.mode & 2 != 0 {
+                recs_copy(xe2, m.i2, m.chg2, false, false, out);
+            }
+        } else {
+            continue;
+        }
+        i = m.i1 + m.chg1;
+    }
+    recs_copy(xe1, i, xe1.xdf2.nrec() - i, false, false, out);
+}
+
+/// Removes any common lines from the beginning and end of conflicts.
+fn refine_zdiff3_conflicts(xe1: &XdEnv, xe2: &XdEnv, changes: &mut [XdMerge]) {
+    let (xdf1, xdf2) = (&xe1.xdf2, &xe2.xdf2);
+    for m in changes.iter_mut().filter(|m| m.mode == 0) {
+        while m.chg1 > 0 && m.chg2 > 0 && xdf1.rec(m.i1) == xdf2.rec(m.i2) {
+            m.chg1 -= 1;
+            m.chg2 -= 1;
+            m.i1 += 1;
+            m.i2 += 1;
+        }
+        while m.chg1 > 0 && m.chg2 > 0 && xdf1.rec(m.i1 + m.chg1 - 1) == xdf2.rec(m.i2 + m.chg2 - 1)
+        {
+            m.chg1 -= 1;
+            m.chg2 -= 1;
+        }
+    }
+}
+
+/// Diffs the two sides of each conflict to show only the lines where they
+/// differ as conflicting.
+fn refine_conflicts(
+    xe1: &XdEnv,
+    xe2: &XdEnv,
+    changes: Vec<XdMerge>,
+    params: &DiffParams,
+) -> Result<Vec<XdMerge>, Failed> {
+    let mut refined = Vec::with_capacity(changes.len());
+    for mut m in changes {
+        // Only conflicts with lines on both sides
+        if m.mode != 0 || m.chg1 == 0 || m.chg2 == 0 {
+            refined.push(m);
+            continue;
+        }
+
+        let t1 = xe1.xdf2.recs(m.i1, m.chg1);
+        let t2 = xe2.xdf2.recs(m.i2, m.chg2);
+        let mut xe = do_diff(t1, t2, params)?;
+        compact(&mut xe);
+        let xscr = build_script(&xe);
+        if xscr.is_empty() {
+            // The changes are identical
+            m.mode = 4;
+            refined.push(m);
+            continue;
+        }
+        for x in xscr {
+            refined.push(XdMerge {
+                mode: 0,
+                i1: x.i1 + m.i1,
+                chg1: x.chg1,
+                i2: x.i2 + m.i2,
+                chg2: x.chg2,
+                ..m
+            });
+        }
+    }
+    Ok(refined)
+}
+
+fn lines_contain_alnum(xe: &XdEnv, i: isize, chg: isize) -> bool {
+    (i..i + chg).any(|i| xe.xdf2.rec(i).iter().any(u8::is_ascii_alphanumeric))
+}
+
+/// Merges conflicts with at most three lines between them, or for
+/// `simplify_if_no_alnum` with no letters or digits between them, marking
+/// the lines between as conflicting too.
+fn simplify_non_conflicts(
+    xe1: &XdEnv,
+    changes: Vec<XdMerge>,
+    simplify_if_no_alnum: bool,
+) -> Vec<XdMerge> {
+    let mut simplified: Vec<XdMerge> = Vec::with_capacity(changes.len());
+    for next_m in changes {
+        if let Some(m) = simplified.last_mut() {
+            let (begin, end) = (m.i1 + m.chg1, next_m.i1);
+            if m.mode == 0
+                && next_m.mode == 0
+                && (end - begin <= 3
+                    || (simplify_if_no_alnum && !lines_contain_alnum(xe1, begin, end - begin)))
+            {
+                m.chg1 = next_m.i1 + next_m.chg1 - m.i1;
+                m.chg2 = next_m.i2 + next_m.chg2 - m.i2;
+                continue;
+            }
+        }
+        simplified.push(next_m);
+    }
+    simplified
+}
+
+/// Merges the two scripts into hunks, refines the conflicts and writes the
+/// result (`xdl_do_merge`). Returns the number of conflicts.
+fn do_merge(
+    xe1: &XdEnv,
+    xscr1: &[Change],
+    xe2: &XdEnv,
+    xscr2: &[Change],
+    xmp: &MergeParams,
+    sink: &mut Sink,
+    regions: Option<&mut Vec<ConflictRegion>>,
+) -> Result<usize, MergeError> {
+    let mut level = xmp.level;
+    // diff3 output does not make sense for anything more aggressive than
+    // eager, as the base does not match the refined sides
+    if xmp.style != MergeStyle::Normal
+        && matches!(level, MergeLevel::Zealous | MergeLevel::ZealousAlnum)
+    {
+        level = MergeLevel::Eager;
+    }
+
+    let mut changes: Vec<XdMerge> = Vec::new();
+    let (mut s1, mut s2) = (xscr1.iter().peekable(), xscr2.iter().peekable());
+    while let (Some(&&x1), Some(&&x2)) = (s1.peek(), s2.peek()) {
+        if x1.i1 + x1.chg1 < x2.i1 {
+            let i2 = x2.i2 - x2.i1 + x1.i1;
+            append_merge(&mut changes, 1, x1.i1, x1.chg1, x1.i2, x1.chg2, i2, x1.chg1);
+            s1.next();
+            continue;
+        }
+        if x2.i1 + x2.chg1 < x1.i1 {
+            let i1 = x1.i2 - x1.i1 + x2.i1;
+            append_merge(&mut changes, 2, x2.i1, x2.chg1, i1, x2.chg1, x2.i2, x2.chg2);
+            s2.next();
+            continue;
+        }
+        if level == MergeLevel::Minimal
+            || x1.i1 != x2.i1
+            || x1.chg1 != x2.chg1
+            || x1.chg2 != x2.chg2
+            || !merge_cmp_lines(xe1, x1.i2, xe2, x2.i2, x1.chg2)
+        {
+            // Conflict
+            let off = x1.i1 - x2.i1;
+            let ffo = off + x1.chg1 - x2.chg1;
+
+            let (mut i0, mut i1, mut i2) = (x1.i1, x1.i2, x2.i2);
+            if off > 0 {
+                i0 -= off;
+                i1 -= off;
+            } else {
+                i2 += off;
+            }
+            let mut chg0 = x1.i1 + x1.chg1 - i0;
+            let mut chg1 = x1.i2 + x1.chg2 - i1;
+            let mut chg2 = x2.i2 + x2.chg2 - i2;
+            if ffo < 0 {
+                chg0 -= ffo;
+                chg1 -= ffo;
+            } else {
+                chg2 += ffo;
+            }
+            append_merge(&mut changes, 0, i0, chg0, i1, chg1, i2, chg2);
+        }
+
+        let i1 = x1.i1 + x1.chg1;
+        let i2 = x2.i1 + x2.chg1;
+        if i1 >= i2 {
+            s2.next();
+        }
+        if i2 >= i1 {
+            s1.next();
+        }
+    }
+    for x1 in s1 {
+        let i2 = x1.i1 + xe2.xdf2.nrec() - xe2.xdf1.nrec();
+        append_merge(&mut changes, 1, x1.i1, x1.chg1, x1.i2, x1.chg2, i2, x1.chg1);
+    }
+    for x2 in s2 {
+        let i1 = x2.i1 + xe1.xdf2.nrec() - xe1.xdf1.nrec();
+        append_merge(&mut changes, 2, x2.i1, x2.chg1, i1, x2.chg1, x2.i2, x2.chg2);
+    }
+
+    if xmp.resolve_sorted {
+        resolve_sorted_conflicts(xe1, xe2, &mut changes);
+    }
+    // Refine conflicts
+    if xmp.style == MergeStyle::ZealousDiff3 {
+        refine_zdiff3_conflicts(xe1, xe2, &mut changes);
+    } else if matches!(level, MergeLevel::Zealous | MergeLevel::ZealousAlnum) {
+        changes = refine_conflicts(xe1, xe2, changes, &xmp.diff).map_err(|_| failed())?;
+        changes = simplify_non_conflicts(xe1, changes, level == MergeLevel::ZealousAlnum);
+    }
+
+    // Output: a Vec is sized first and then filled, a writer is streamed to
+    let mut out = match sink {
+        Sink::Vec(vec) => {
+            let mut sizing = Out {
+                writer: None,
+                size: 0,
+                err: None,
+                regions: None,
+                lines: 0,
+            };
+            fill_merge_buffer(xe1, xe2, xmp, &mut changes, &mut sizing);
+            vec.try_reserve_exact(sizing.size)
+                .map_err(|_| MergeError::OutOfMemory)?;
+            Out {
+                writer: Some(&mut **vec),
+                size: 0,
+                err: None,
+                regions,
+                lines: 0,
+            }
+        }
+        Sink::Writer(writer) => Out {
+            writer: Some(&mut **writer),
+            size: 0,
+            err: None,
+            regions,
+            lines: 0,
+        },
+    };
+    fill_merge_buffer(xe1, xe2, xmp, &mut changes, &mut out);
+    if let Some(err) = out.err {
+        return Err(write_error(err));
+    }
+
+    Ok(changes.iter().filter(|m| m.mode == 0).count())
+}
+
+fn failed() -> MergeError {
+    MergeError::Internal("xdl_merge failed with code -1".to_string())
+}
+
+/// Merges `mf1` and `mf2`, both changed from `orig`, into `sink`
+/// (`xdl_merge_to`), recording where conflicts are written in `regions`.
+/// Returns the number of conflicts.
+pub(crate) fn merge(
+    orig: &[u8],
+    mf1: &[u8],
+    mf2: &[u8],
+    xmp: &MergeParams,
+    sink: &mut Sink,
+    regions: Option<&mut Vec<ConflictRegion>>,
+) -> Result<usize, MergeError> {
+    let mut xe1 = do_diff(orig, mf1, &xmp.diff).map_err(|_| failed())?;
+    let mut xe2 = do_diff(orig, mf2, &xmp.diff).map_err(|_| failed())?;
+    compact(&mut xe1);
+    let xscr1 = build_script(&xe1);
+    compact(&mut xe2);
+    let xscr2 = build_script(&xe2);
+
+    // One side unchanged: the other is the result
+    if xscr1.is_empty() {
+        sink.put(mf2)?;
+        return Ok(0);
+    }
+    if xscr2.is_empty() {
+        sink.put(mf1)?;
+        return Ok(0);
+    }
+    do_merge(&xe1, &xscr1, &xe2, &xscr2, xmp, sink, regions)
+}
@@ -0,0 +1,205 @@
+//! Key-aware three-way merge of YAML documents.
+//!
+//! There is no comment-preserving YAML editor to build on, so the structured
+//! merge decides the result and the xdiff line merge supplies the formatting:
+//! when the line merge is clean and parses to the same documents, its text is
+//! returned as-is. Otherwise the merged documents are re-emitted, which drops
+//! comments.
+
+use crate::merge::merge_strings;
+use crate::structured::{KeyConflict, StructuredMergeResult, merge_lines};
+use crate::types::*;
+use yaml_rust2::yaml::Hash;
+use yaml_rust2::{Yaml, YamlEmitter, YamlLoader};
+
+/// Merges three YAML streams.
+///
+/// Mappings merge key by key and sequences element by element when all sides
+/// have the same length. String values that both sides changed fall back to
+/// the xdiff line merge. Anything else changed on both sides keeps "ours" and
+/// is listed in [`StructuredMergeResult::conflicts`].
+///
+/// A stream with several documents is merged document by document, and
+/// conflict paths start with the document index.
+pub fn merge_yaml(
+    base: &str,
+    ours: &str,
+    theirs: &str,
+) -> Result<StructuredMergeResult, MergeError> {
+    let parse = |text: &str, field: &str| {
+        YamlLoader::load_from_str(text).map_err(|err| {
+            MergeError::InvalidInput(format!("{} is not valid YAML: {}", field, err))
+        })
+    };
+    let base_docs = parse(base, "base")?;
+    let ours_docs = parse(ours, "ours")?;
+    let theirs_docs = parse(theirs, "theirs")?;
+
+    let mut conflicts = Vec::new();
+    let merged = if ours_docs.len() == 1 && base_docs.len() == 1 && theirs_docs.len() == 1 {
+        let doc = merge_value(
+            &mut Vec::new(),
+            base_docs.first(),
+            ours_docs.first(),
+            theirs_docs.first(),
+            &mut conflicts,
+        );
+        doc.into_iter().collect()
+    } else {
+        let stream = |docs: Vec<Yaml>| Yaml::Array(docs);
+        match merge_value(
+            &mut Vec::new(),
+            Some(&stream(base_docs)),
+            Some(&stream(ours_docs)),
+            Some(&stream(theirs_docs)),
+            &mut conflicts,
+        ) {
+            Some(Yaml::Array(docs)) => docs,
+            _ => Vec::new(),
+        }
+    };
+
+    // Prefer the line merge, which keeps comments and formatting.
+    let line = merge_strings(base, ours, theirs, &MergeOptions::default())?;
+    if line.is_clean_merge()
+        && YamlLoader::load_from_str(&line.content).is_ok_and(|docs| docs == merged)
+    {
+        return Ok(StructuredMergeResult {
+            content: line.content,
+            conflicts,
+        });
+    }
+
+    let mut content = String::new();
+    for (i, doc) in merged.iter().enumerate() {
+        let text = emit(doc)?;
+        if i == 0 && !ours.starts_with("---") {
+            content.push_str(strip_header(&text));
+        } else {
+            content.push_str(&text);
+        }
+        content.push('\n');
+    }
+    Ok(StructuredMergeResult { content, conflicts })
+}
+
+fn merge_value(
+    path: &mut Vec<String>,
+    base: Option<&Yaml>,
+    ours: Option<&Yaml>,
+    theirs: Option<&Yaml>,
+    conflicts: &mut Vec<KeyConflict>,
+) -> Option<Yaml> {
+    if ours == theirs || theirs == base {
+        return ours.cloned();
+    }
+    if ours == base {
+        return theirs.cloned();
+    }
+
+    let merged = match (ours, theirs) {
+        (Some(Yaml::Hash(o)), Some(Yaml::Hash(t))) => {
+            let empty = Hash::new();
+            let b = match base {
+                Some(Yaml::Hash(b)) => b,
+                _ => &empty,
+            };
+            return Some(Yaml::Hash(merge_hashes(path, b, o, t, conflicts)));
+        }
+        (Some(Yaml::Array(o)), Some(Yaml::Array(t))) => match base {
+            Some(Yaml::Array(b)) if b.len() == o.len() && o.len() == t.len() => {
+                let mut merged = Vec::with_capacity(o.len());
+                for (i, ((b, o), t)) in b.iter().zip(o).zip(t).enumerate() {
+                    path.push(i.to_string());
+                    merged.extend(merge_value(path, Some(b), Some(o), Some(t), conflicts));
+                    path.pop();
+                }
+                return Some(Yaml::Array(merged));
+            }
+            _ => None,
+        },
+        (Some(Yaml::String(o)), Some(Yaml::String(t))) => {
+            let b = match base {
+                Some(Yaml::String(b)) => b.as_str(),
+                _ => "",
+            };
+            merge_lines(b, o, t).map(Yaml::String)
+        }
+        _ => None,
+    };
+
+    match merged {
+        Some(value) => Some(value),
+        None => {
+            conflicts.push(KeyConflict {
+                path: path.clone(),
+                base: base.map(render),
+                ours: ours.map(render),
+                theirs: theirs.map(render),
+            });
+            ours.cloned()
+        }
+    }
+}
+
+fn merge_hashes(
+    path: &mut Vec<String>,
+    base: &Hash,
+    ours: &Hash,
+    theirs: &Hash,
+    conflicts: &mut Vec<KeyConflict>,
+) -> Hash {
+    let keys = ours
+        .keys()
+        .chain(theirs.keys().filter(|key| !ours.contains_key(*key)));
+
+    let mut merged = Hash::new();
+    for key in keys {
+        path.push(key_name(key));
+        if let Some(value) = merge_value(
+            path,
+            base.get(key),
+            ours.get(key),
+            theirs.get(key),
+            conflicts,
+        ) {
+            merged.insert(key.clone(), value);
+        }
+        path.pop();
+    }
+    merged
+}
+
+fn key_name(key: &Yaml) -> String {
+    match key {
+        Yaml::String(s) | Yaml::Real(s) => s.clone(),
+        Yaml::Integer(i) => i.to_string(),
+        Yaml::Boolean(b) => b.to_string(),
+        Yaml::Null => "~".to_string(),
+        _ => render(key),
+    }
+}
+
+fn emit(doc: &Yaml) -> Result<String, MergeError> {
+    let mut out = String::new();
+    let mut emitter = YamlEmitter::new(&mut out);
+    emitter.multiline_strings(true);
+    emitter
+        .dump(doc)
+        .map_err(|err| MergeError::Internal(format!("failed to emit YAML: {:?}", err)))?;
+    Ok(out)
+}
+
+fn strip_header(text: &str) -> &str {
+    text.strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("--- "))
+        .unwrap_or(text)
+}
+
+/// Renders a single value for [`KeyConflict`].
+fn render(value: &Yaml) -> String {
+    match emit(value) {
+        Ok(text) => strip_header(&text).trim().to_string(),
+        Err(_) => format!("{:?}", value),
+    }
+}
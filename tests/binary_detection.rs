@@ -0,0 +1,88 @@
+use threeway_merge::{BinaryPolicy, MergeError, MergeOptions, merge_strings};
+
+const BASE: &str = "header\0\x01\x02\nline\n";
+const OURS: &str = "header\0\x01\x02\nours\n";
+const THEIRS: &str = "header\0\x01\x02\ntheirs\n";
+
+fn with_policy(binary: BinaryPolicy) -> MergeOptions {
+    MergeOptions {
+        binary,
+        ..MergeOptions::default()
+    }
+}
+
+#[test]
+fn refuses_binary_input_by_default() {
+    let err = merge_strings(BASE, OURS, THEIRS, &MergeOptions::default()).unwrap_err();
+    assert!(matches!(err, MergeError::Binary(_)));
+}
+
+#[test]
+fn binary_on_any_side_is_detected() {
+    let err = merge_strings("a\n", "b\n", "c\0\n", &MergeOptions::default()).unwrap_err();
+    assert!(matches!(err, MergeError::Binary(msg) if msg.contains("theirs")));
+}
+
+#[test]
+fn applies_take_and_conflict_policies() {
+    let result = merge_strings(BASE, OURS, THEIRS, &with_policy(BinaryPolicy::TakeOurs)).unwrap();
+    assert_eq!(result.content, OURS);
+    assert_eq!(result.conflicts, 0);
+    assert_eq!(result.binary, Some(BinaryPolicy::TakeOurs));
+
+    let result = merge_strings(BASE, OURS, THEIRS, &with_policy(BinaryPolicy::TakeTheirs)).unwrap();
+    assert_eq!(result.content, THEIRS);
+    assert_eq!(result.conflicts, 0);
+    assert_eq!(result.binary, Some(BinaryPolicy::TakeTheirs));
+
+    let result = merge_strings(BASE, OURS, THEIRS, &with_policy(BinaryPolicy::Conflict)).unwrap();
+    assert_eq!(result.content, OURS);
+    assert_eq!(result.conflicts, 1);
+    assert_eq!(result.binary, Some(BinaryPolicy::Conflict));
+}
+
+#[test]
+fn nul_after_first_8000_bytes_is_text() {
+    let prefix = "x\n".repeat(4000);
+    let base = format!("{}base\n\0", prefix);
+    let ours = format!("{}ours\n\0", prefix);
+
+    let result = merge_strings(&base, &ours, &base, &MergeOptions::default()).unwrap();
+    assert_eq!(result.content, ours);
+    assert_eq!(result.binary, None);
+
+    let theirs = format!("{}base\ntheirs\n\0", prefix);
+    let result = merge_strings(&base, &ours, &theirs, &MergeOptions::default()).unwrap();
+    assert_eq!(result.binary, None);
+}
+
+#[test]
+fn text_merges_report_no_binary_policy() {
+    let result = merge_strings("a\n", "b\n", "c\n", &with_policy(BinaryPolicy::TakeOurs)).unwrap();
+    assert_eq!(result.binary, None);
+    assert_eq!(result.conflicts, 1);
+}
+
+#[test]
+fn identical_binary_sides_are_refused() {
+    let err = merge_strings("x\n", "a\0b\n", "a\0b\n", &MergeOptions::default()).unwrap_err();
+    assert!(matches!(err, MergeError::Binary(msg) if msg.contains("ours")));
+
+    let result = merge_strings(OURS, OURS, OURS, &with_policy(BinaryPolicy::TakeTheirs)).unwrap();
+    assert_eq!(result.content, OURS);
+    assert_eq!(result.binary, Some(BinaryPolicy::TakeTheirs));
+}
+
+#[test]
+fn binary_with_one_side_unchanged_is_refused() {
+    let err = merge_strings(BASE, BASE, THEIRS, &MergeOptions::default()).unwrap_err();
+    assert!(matches!(err, MergeError::Binary(msg) if msg.contains("base")));
+
+    let err = merge_strings(BASE, OURS, BASE, &MergeOptions::default()).unwrap_err();
+    assert!(matches!(err, MergeError::Binary(_)));
+
+    let result = merge_strings(BASE, OURS, BASE, &with_policy(BinaryPolicy::Conflict)).unwrap();
+    assert_eq!(result.content, OURS);
+    assert_eq!(result.conflicts, 1);
+    assert_eq!(result.binary, Some(BinaryPolicy::Conflict));
+}
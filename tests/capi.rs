@@ -0,0 +1,60 @@
+//! Builds `tests/capi/merge.c` against the static library and runs it.
+#![cfg(all(feature = "capi", target_os = "linux"))]
+
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn c_program_merges_through_the_c_api() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let tmp = Path::new(env!("CARGO_TARGET_TMPDIR"));
+
+    // Builds with other features write a library of the same name, so this
+    // test builds its own
+    let features = if cfg!(feature = "pure-rust") {
+        "capi pure-rust"
+    } else {
+        "capi"
+    };
+    let target_dir = tmp.join("capi");
+    let status = Command::new(env!("CARGO"))
+        .args(["rustc", "--lib", "--crate-type", "staticlib"])
+        .args(["--features", features, "--manifest-path"])
+        .arg(root.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .status()
+        .expect("failed to run cargo");
+    assert!(status.success(), "building the static library failed");
+    let library = target_dir.join("debug/libthreeway_merge.a");
+
+    let program = tmp.join("capi_merge");
+    let compiler = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let status = Command::new(compiler)
+        .args(["-std=c99", "-Wall", "-Wextra", "-Werror", "-I"])
+        .arg(root.join("include"))
+        .arg(root.join("tests/capi/merge.c"))
+        .arg(&library)
+        // From `cargo rustc -- --print native-static-libs`
+        .args([
+            "-lgcc_s",
+            "-lutil",
+            "-lrt",
+            "-lpthread",
+            "-lm",
+            "-ldl",
+            "-lc",
+        ])
+        .arg("-o")
+        .arg(&program)
+        .status()
+        .expect("failed to run the C compiler");
+    assert!(status.success(), "compiling tests/capi/merge.c failed");
+
+    let output = Command::new(&program).output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,83 @@
+/* Exercises the C API; built and run by tests/capi.rs. */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "threeway_merge.h"
+
+#define CHECK(cond)                                                  \
+    do {                                                             \
+        if (!(cond)) {                                               \
+            fprintf(stderr, "%s:%d: %s\n", __FILE__, __LINE__, #cond); \
+            return 1;                                                \
+        }                                                            \
+    } while (0)
+
+static int merge(const char *base, const char *ours, const char *theirs,
+                 const ThreewayMergeOptions *options, ThreewayMergeResult **result) {
+    return threeway_merge((const uint8_t *)base, strlen(base), (const uint8_t *)ours,
+                          strlen(ours), (const uint8_t *)theirs, strlen(theirs), options,
+                          result);
+}
+
+int main(void) {
+    ThreewayMergeResult *result = NULL;
+    size_t len = 0;
+
+    CHECK(threeway_merge_abi_version() == THREEWAY_MERGE_ABI_VERSION);
+
+    /* Default options */
+    CHECK(merge("a\nb\nc\nd\n", "a\nB\nc\nd\n", "a\nb\nc\nD\n", NULL, &result) ==
+          THREEWAY_MERGE_OK);
+    CHECK(strcmp(threeway_merge_result_content(result, &len), "a\nB\nc\nD\n") == 0);
+    CHECK(len == 8);
+    CHECK(threeway_merge_result_conflicts(result) == 0);
+    CHECK(threeway_merge_result_error(result) == NULL);
+    threeway_merge_result_free(result);
+
+    /* A conflict with the diff3 style and labels */
+    ThreewayMergeOptions *options = threeway_merge_options_new();
+    CHECK(threeway_merge_options_set_style(options, THREEWAY_MERGE_STYLE_DIFF3) ==
+          THREEWAY_MERGE_OK);
+    CHECK(threeway_merge_options_set_algorithm(options, THREEWAY_MERGE_ALGORITHM_HISTOGRAM) ==
+          THREEWAY_MERGE_OK);
+    CHECK(threeway_merge_options_set_labels(options, "base", "ours", NULL) == THREEWAY_MERGE_OK);
+    CHECK(merge("a\nb\nc\n", "a\nB\nc\n", "a\nb2\nc\n", options, &result) == THREEWAY_MERGE_OK);
+    CHECK(strcmp(threeway_merge_result_content(result, NULL),
+                 "a\n<<<<<<< ours\nB\n||||||| base\nb\n=======\nb2\n>>>>>>>\nc\n") == 0);
+    CHECK(threeway_merge_result_conflicts(result) == 1);
+    threeway_merge_result_free(result);
+
+    /* The same conflict resolved by a favor */
+    CHECK(threeway_merge_options_set_favor(options, THREEWAY_MERGE_FAVOR_UNION) ==
+          THREEWAY_MERGE_OK);
+    CHECK(merge("a\nb\nc\n", "a\nB\nc\n", "a\nb2\nc\n", options, &result) == THREEWAY_MERGE_OK);
+    CHECK(strcmp(threeway_merge_result_content(result, NULL), "a\nB\nb2\nc\n") == 0);
+    CHECK(threeway_merge_result_conflicts(result) == 0);
+    threeway_merge_result_free(result);
+
+    /* Out-of-range values are rejected and leave the options as they were */
+    CHECK(threeway_merge_options_set_style(options, 42) == THREEWAY_MERGE_ERROR_INVALID_INPUT);
+    CHECK(threeway_merge_options_set_style(NULL, THREEWAY_MERGE_STYLE_MERGE) ==
+          THREEWAY_MERGE_ERROR_INVALID_INPUT);
+
+    /* Errors come with a message */
+    CHECK(threeway_merge_options_set_limits(options, 4, 0, 0) == THREEWAY_MERGE_OK);
+    CHECK(merge("a\nb\nc\n", "a\n", "a\n", options, &result) ==
+          THREEWAY_MERGE_ERROR_LIMIT_EXCEEDED);
+    CHECK(threeway_merge_result_status(result) == THREEWAY_MERGE_ERROR_LIMIT_EXCEEDED);
+    CHECK(threeway_merge_result_content(result, &len) == NULL);
+    CHECK(strstr(threeway_merge_result_error(result), "base is 6 bytes") != NULL);
+    threeway_merge_result_free(result);
+    threeway_merge_options_free(options);
+
+    /* Binary inputs, which need not be NUL-terminated */
+    const uint8_t binary[] = {'a', 0, 'b'};
+    CHECK(threeway_merge(binary, sizeof binary, (const uint8_t *)"x\n", 2, NULL, 0, NULL,
+                         &result) == THREEWAY_MERGE_ERROR_BINARY);
+    threeway_merge_result_free(result);
+    CHECK(threeway_merge(NULL, 1, NULL, 0, NULL, 0, NULL, NULL) ==
+          THREEWAY_MERGE_ERROR_INVALID_INPUT);
+
+    return 0;
+}
@@ -119,6 +119,9 @@ fn git_merge_file(
             MergeFavor::Union => {
                 cmd.arg("--union");
             }
+            MergeFavor::UnionDedup | MergeFavor::UnionSorted => {
+                unreachable!("git merge-file has no deduplicating union")
+            }
         }
     }
 
@@ -203,14 +206,16 @@ fn test_comprehensive_git_comparison() {
                     for &style in &styles {
                         total_tests += 1;
 
-                        let mut options = MergeOptions::default();
-                        options.algorithm = algorithm;
-                        options.level = level;
-                        options.favor = favor;
-                        options.style = style;
-                        options.ours_label = Some("ours".to_string());
-                        options.base_label = Some("base".to_string());
-                        options.theirs_label = Some("theirs".to_string());
+                        let options = MergeOptions {
+                            algorithm,
+                            level,
+                            favor,
+                            style,
+                            ours_label: Some("ours".to_string()),
+                            base_label: Some("base".to_string()),
+                            theirs_label: Some("theirs".to_string()),
+                            ..MergeOptions::default()
+                        };
 
                         // Our result
                         let our_result = merge_strings(
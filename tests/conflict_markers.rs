@@ -0,0 +1,79 @@
+use threeway_merge::{ConflictMarkers, MarkerTemplate, MergeOptions, MergeStyle, merge_strings};
+
+const BASE: &str = "a: 1\nb: 2\nc: 3\n";
+const OURS: &str = "a: 1\nb: 20\nc: 3\n";
+const THEIRS: &str = "a: 1\nb: 200\nc: 3\n";
+
+fn labelled() -> MergeOptions {
+    MergeOptions {
+        base_label: Some("base".to_string()),
+        ours_label: Some("ours".to_string()),
+        theirs_label: Some("theirs".to_string()),
+        ..MergeOptions::default()
+    }
+}
+
+fn merge(options: &MergeOptions) -> String {
+    merge_strings(BASE, OURS, THEIRS, options).unwrap().content
+}
+
+#[test]
+fn default_markers_are_unchanged() {
+    assert_eq!(
+        merge(&labelled()),
+        "a: 1\n<<<<<<< ours\nb: 20\n=======\nb: 200\n>>>>>>> theirs\nc: 3\n"
+    );
+}
+
+#[test]
+fn prefixes_keep_yaml_parseable() {
+    let options = MergeOptions {
+        style: MergeStyle::Diff3,
+        markers: ConflictMarkers::with_prefix("# "),
+        ..labelled()
+    };
+    assert_eq!(
+        merge(&options),
+        "a: 1\n# <<<<<<< ours\nb: 20\n# ||||||| base\nb: 2\n# =======\nb: 200\n# >>>>>>> theirs\nc: 3\n"
+    );
+}
+
+#[test]
+fn templates_differ_per_marker_kind() {
+    let template = |prefix: &str, suffix: &str| MarkerTemplate {
+        prefix: prefix.to_string(),
+        suffix: suffix.to_string(),
+    };
+    let options = MergeOptions {
+        marker_size: 3,
+        ours_label: Some("local".to_string()),
+        theirs_label: Some("remote".to_string()),
+        markers: ConflictMarkers {
+            start: template("/* ", ""),
+            separator: template("", ""),
+            end: template("", " */"),
+            label: " [{label}]".to_string(),
+            ..ConflictMarkers::default()
+        },
+        ..labelled()
+    };
+    assert_eq!(
+        merge(&options),
+        "a: 1\n/* <<< [local]\nb: 20\n===\nb: 200\n>>> [remote] */\nc: 3\n"
+    );
+}
+
+#[test]
+fn markers_follow_crlf_line_endings() {
+    let crlf = |s: &str| s.replace('\n', "\r\n");
+    let options = MergeOptions {
+        markers: ConflictMarkers::with_prefix("-- "),
+        ..labelled()
+    };
+    let result = merge_strings(&crlf(BASE), &crlf(OURS), &crlf(THEIRS), &options).unwrap();
+    assert_eq!(
+        result.content,
+        crlf("a: 1\n-- <<<<<<< ours\nb: 20\n-- =======\nb: 200\n-- >>>>>>> theirs\nc: 3\n")
+    );
+    assert_eq!(result.conflicts, 1);
+}
@@ -0,0 +1,157 @@
+#![cfg(feature = "csv")]
+
+use threeway_merge::{CsvMergeOptions, MergeError, merge_csv, merge_strings};
+
+const BASE: &str = "id,name,price,stock\n1,apple,0.50,10\n2,banana,0.25,20\n3,cherry,3.00,5\n";
+
+#[test]
+fn edits_to_different_columns_of_a_row_merge() {
+    let ours = BASE.replace("2,banana,0.25,20", "2,banana,0.30,20");
+    let theirs = BASE.replace("2,banana,0.25,20", "2,banana,0.25,18");
+    assert!(
+        merge_strings(BASE, &ours, &theirs, &Default::default())
+            .unwrap()
+            .has_conflicts()
+    );
+
+    let result = merge_csv(BASE, &ours, &theirs, &CsvMergeOptions::default()).unwrap();
+    assert!(result.is_clean_merge());
+    assert!(result.fallback.is_none());
+    assert_eq!(
+        result.content,
+        BASE.replace("2,banana,0.25,20", "2,banana,0.30,18")
+    );
+}
+
+#[test]
+fn appends_from_both_sides_are_a_union() {
+    let ours = format!("{}4,date,2.00,7\n", BASE);
+    let theirs = format!("{}5,elderberry,4.00,1\n6,fig,1.00,9\n", BASE);
+
+    let result = merge_csv(BASE, &ours, &theirs, &CsvMergeOptions::default()).unwrap();
+    assert!(result.is_clean_merge());
+    assert_eq!(
+        result.content,
+        format!("{}4,date,2.00,7\n5,elderberry,4.00,1\n6,fig,1.00,9\n", BASE)
+    );
+}
+
+#[test]
+fn large_appends_keep_their_order() {
+    let rows = |ids: std::ops::Range<usize>| -> String {
+        ids.map(|id| format!("{},item {},1.00,1\n", id, id))
+            .collect()
+    };
+    let ours = format!("{}{}", BASE, rows(4..10));
+    let theirs = format!("{}{}", BASE, rows(10..50_000));
+
+    let result = merge_csv(BASE, &ours, &theirs, &CsvMergeOptions::default()).unwrap();
+    assert!(result.is_clean_merge());
+    assert_eq!(result.content, format!("{}{}", BASE, rows(4..50_000)));
+}
+
+#[test]
+fn insertions_follow_their_predecessor() {
+    let ours = BASE.replace("3,cherry,3.00,5\n", "");
+    let theirs = BASE.replace("1,apple,0.50,10\n", "1,apple,0.50,10\n7,apricot,1.50,3\n");
+
+    let result = merge_csv(BASE, &ours, &theirs, &CsvMergeOptions::default()).unwrap();
+    assert!(result.is_clean_merge());
+    assert_eq!(
+        result.content,
+        "id,name,price,stock\n1,apple,0.50,10\n7,apricot,1.50,3\n2,banana,0.25,20\n"
+    );
+}
+
+#[test]
+fn conflicts_are_reported_by_row_key_and_column() {
+    let ours = BASE.replace("3,cherry,3.00,5", "3,cherry,3.50,5");
+    let theirs = BASE
+        .replace("3,cherry,3.00,5", "3,cherry,2.75,5")
+        .replace("1,apple,0.50,10\n", "");
+
+    let result = merge_csv(BASE, &ours, &theirs, &CsvMergeOptions::default()).unwrap();
+    assert_eq!(result.conflicts.len(), 1);
+    let conflict = &result.conflicts[0];
+    assert_eq!(conflict.key, ["3"]);
+    assert_eq!(conflict.column.as_deref(), Some("price"));
+    assert_eq!(conflict.base.as_deref(), Some("3.00"));
+    assert_eq!(conflict.ours.as_deref(), Some("3.50"));
+    assert_eq!(conflict.theirs.as_deref(), Some("2.75"));
+    assert_eq!(
+        result.content,
+        "id,name,price,stock\n2,banana,0.25,20\n3,cherry,3.50,5\n"
+    );
+
+    // delete/modify is a row conflict
+    let theirs = BASE.replace("3,cherry,3.00,5\n", "");
+    let result = merge_csv(BASE, &ours, &theirs, &CsvMergeOptions::default()).unwrap();
+    assert_eq!(result.conflicts.len(), 1);
+    assert_eq!(result.conflicts[0].column, None);
+    assert_eq!(result.conflicts[0].theirs, None);
+    assert_eq!(result.conflicts[0].ours.as_deref(), Some("3,cherry,3.50,5"));
+}
+
+#[test]
+fn composite_keys_and_tsv() {
+    let base = "region\tsku\tqty\neu\t1\t5\nus\t1\t7\n";
+    let ours = "region\tsku\tqty\neu\t1\t6\nus\t1\t7\n";
+    let theirs = "region\tsku\tqty\neu\t1\t5\nus\t1\t8\neu\t2\t1\n";
+    let options = CsvMergeOptions {
+        key_columns: vec!["region".to_string(), "sku".to_string()],
+        delimiter: b'\t',
+        ..CsvMergeOptions::default()
+    };
+
+    let result = merge_csv(base, ours, theirs, &options).unwrap();
+    assert!(result.is_clean_merge());
+    assert_eq!(
+        result.content,
+        "region\tsku\tqty\neu\t1\t6\nus\t1\t8\neu\t2\t1\n"
+    );
+
+    let options = CsvMergeOptions {
+        key_columns: vec!["missing".to_string()],
+        ..options
+    };
+    assert!(matches!(
+        merge_csv(base, ours, theirs, &options),
+        Err(MergeError::InvalidInput(_))
+    ));
+}
+
+#[test]
+fn keeps_quoting_and_crlf() {
+    let base = "id,note\r\n1,\"a, b\"\r\n2,plain\r\n";
+    let ours = "id,note\r\n1,\"a, b\"\r\n2,changed\r\n";
+    let theirs = "id,note\r\n1,\"a, b\"\r\n2,plain\r\n3,\"multi\nline\"\r\n";
+
+    let result = merge_csv(base, ours, theirs, &CsvMergeOptions::default()).unwrap();
+    assert!(result.is_clean_merge());
+    assert_eq!(
+        result.content,
+        "id,note\r\n1,\"a, b\"\r\n2,changed\r\n3,\"multi\nline\"\r\n"
+    );
+}
+
+#[test]
+fn falls_back_to_line_merge() {
+    // ragged rows
+    let result = merge_csv(BASE, "id,name\n1\n", BASE, &CsvMergeOptions::default()).unwrap();
+    assert!(result.fallback.is_some());
+    assert_eq!(result.content, "id,name\n1\n");
+
+    // header change
+    let ours = BASE.replace("stock", "qty");
+    let theirs = BASE.replace("3,cherry", "3,Cherry");
+    let result = merge_csv(BASE, &ours, &theirs, &CsvMergeOptions::default()).unwrap();
+    let fallback = result.fallback.as_ref().unwrap();
+    assert!(fallback.is_clean_merge());
+    assert!(result.is_clean_merge());
+    assert_eq!(result.content, ours.replace("3,cherry", "3,Cherry"));
+
+    // duplicate keys
+    let ours = BASE.replace("2,banana", "1,banana");
+    let result = merge_csv(BASE, &ours, BASE, &CsvMergeOptions::default()).unwrap();
+    assert!(result.fallback.is_some());
+}
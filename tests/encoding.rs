@@ -0,0 +1,165 @@
+use threeway_merge::{
+    BinaryPolicy, MergeError, MergeOptions, TextEncoding, merge_encoded, merge_strings,
+};
+
+fn utf16le(text: &str) -> Vec<u8> {
+    let mut out = vec![0xFF, 0xFE];
+    for unit in text.encode_utf16() {
+        out.extend_from_slice(&unit.to_le_bytes());
+    }
+    out
+}
+
+fn utf16be(text: &str) -> Vec<u8> {
+    let mut out = vec![0xFE, 0xFF];
+    for unit in text.encode_utf16() {
+        out.extend_from_slice(&unit.to_be_bytes());
+    }
+    out
+}
+
+fn utf8_bom(text: &str) -> Vec<u8> {
+    [&[0xEF, 0xBB, 0xBF], text.as_bytes()].concat()
+}
+
+#[test]
+fn detects_byte_order_marks() {
+    assert_eq!(TextEncoding::detect(b"plain").unwrap(), TextEncoding::Utf8);
+    assert_eq!(
+        TextEncoding::detect(&utf8_bom("x")).unwrap(),
+        TextEncoding::Utf8Bom
+    );
+    assert_eq!(
+        TextEncoding::detect(&utf16le("x")).unwrap(),
+        TextEncoding::Utf16Le
+    );
+    assert_eq!(
+        TextEncoding::detect(&utf16be("x")).unwrap(),
+        TextEncoding::Utf16Be
+    );
+}
+
+#[test]
+fn rejects_utf32() {
+    let utf32le = [0xFF, 0xFE, 0x00, 0x00, b'x', 0x00, 0x00, 0x00];
+    let utf32be = [0x00, 0x00, 0xFE, 0xFF, 0x00, 0x00, 0x00, b'x'];
+    for bytes in [&utf32le, &utf32be] {
+        assert!(matches!(
+            TextEncoding::detect(bytes),
+            Err(MergeError::InvalidInput(_))
+        ));
+    }
+
+    let err = merge_encoded(b"x\n", b"x\n", &utf32le, &MergeOptions::default()).unwrap_err();
+    assert!(matches!(err, MergeError::InvalidInput(msg) if msg.contains("theirs")));
+}
+
+#[test]
+fn merges_utf16le_and_reencodes_output() {
+    let base = utf16le("one\r\ntwo\r\nthree\r\n");
+    let ours = utf16le("ONE\r\ntwo\r\nthree\r\n");
+    let theirs = utf16le("one\r\ntwo\r\nTHREE 🎉\r\n");
+
+    let result = merge_encoded(&base, &ours, &theirs, &MergeOptions::default()).unwrap();
+    assert_eq!(result.encoding, TextEncoding::Utf16Le);
+    assert_eq!(result.conflicts, 0);
+    assert_eq!(result.content, utf16le("ONE\r\ntwo\r\nTHREE 🎉\r\n"));
+}
+
+#[test]
+fn utf16_conflicts_keep_markers_in_encoding() {
+    let base = utf16be("a\n");
+    let ours = utf16be("b\n");
+    let theirs = utf16be("c\n");
+
+    let result = merge_encoded(&base, &ours, &theirs, &MergeOptions::default()).unwrap();
+    assert_eq!(result.encoding, TextEncoding::Utf16Be);
+    assert!(result.has_conflicts());
+    assert_eq!(result.content, utf16be("<<<<<<<\nb\n=======\nc\n>>>>>>>\n"));
+}
+
+#[test]
+fn bom_on_one_side_does_not_conflict() {
+    let base = b"first\nsecond\n".to_vec();
+    let ours = utf8_bom("first\nsecond\n");
+    let theirs = b"first\nsecond changed\n".to_vec();
+
+    // Plain merge_strings sees the BOM as an edit of the first line.
+    let plain = merge_strings(
+        std::str::from_utf8(&base).unwrap(),
+        std::str::from_utf8(&utf8_bom("first\nSECOND\n")).unwrap(),
+        std::str::from_utf8(&theirs).unwrap(),
+        &MergeOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(plain.conflicts, 1);
+
+    let result = merge_encoded(&base, &ours, &theirs, &MergeOptions::default()).unwrap();
+    assert_eq!(result.conflicts, 0);
+    assert_eq!(result.encoding, TextEncoding::Utf8Bom);
+    assert_eq!(result.content, utf8_bom("first\nsecond changed\n"));
+}
+
+#[test]
+fn encoding_change_on_their_side_is_taken() {
+    let base = b"x\ny\n".to_vec();
+    let ours = b"X\ny\n".to_vec();
+    let theirs = utf16le("x\ny\n");
+
+    let result = merge_encoded(&base, &ours, &theirs, &MergeOptions::default()).unwrap();
+    assert_eq!(result.encoding, TextEncoding::Utf16Le);
+    assert_eq!(result.content, utf16le("X\ny\n"));
+}
+
+#[test]
+fn plain_bytes_need_not_be_utf8() {
+    let base = b"caf\xe9\n-\n-\nbar\n";
+    let ours = b"caf\xe9\n-\n-\nBAR\n";
+    let theirs = b"CAF\xc9\n-\n-\nbar\n";
+
+    let result = merge_encoded(base, ours, theirs, &MergeOptions::default()).unwrap();
+    assert_eq!(result.encoding, TextEncoding::Utf8);
+    assert_eq!(result.content, b"CAF\xc9\n-\n-\nBAR\n");
+}
+
+#[test]
+fn rejects_malformed_utf16() {
+    let odd = [0xFF, 0xFE, b'a'];
+    let err = merge_encoded(&odd, &odd, &utf16le("b"), &MergeOptions::default()).unwrap_err();
+    assert!(matches!(err, MergeError::InvalidInput(msg) if msg.contains("base")));
+
+    let lone_surrogate = [0xFF, 0xFE, 0x00, 0xD8];
+    let err = merge_encoded(
+        &utf16le("a"),
+        &lone_surrogate,
+        &utf16le("b"),
+        &MergeOptions::default(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, MergeError::InvalidInput(msg) if msg.contains("ours")));
+}
+
+#[test]
+fn utf16_is_not_mistaken_for_binary() {
+    let base = utf16le("a\n-\n-\nb\n");
+    let ours = utf16le("A\n-\n-\nb\n");
+    let theirs = utf16le("a\n-\n-\nB\n");
+
+    let result = merge_encoded(&base, &ours, &theirs, &MergeOptions::default()).unwrap();
+    assert_eq!(result.binary, None);
+    assert_eq!(result.content, utf16le("A\n-\n-\nB\n"));
+}
+
+#[test]
+fn binary_policy_returns_original_bytes() {
+    let options = MergeOptions {
+        binary: BinaryPolicy::TakeTheirs,
+        ..MergeOptions::default()
+    };
+    let theirs = b"\0\x01theirs".to_vec();
+
+    let result = merge_encoded(&utf16le("a"), &utf16le("b"), &theirs, &options).unwrap();
+    assert_eq!(result.binary, Some(BinaryPolicy::TakeTheirs));
+    assert_eq!(result.content, theirs);
+    assert_eq!(result.encoding, TextEncoding::Utf8);
+}
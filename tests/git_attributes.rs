@@ -0,0 +1,192 @@
+use std::fs;
+use threeway_merge::{
+    AttributeSource, AttributeValue, EolPolicy, GitAttributes, GitConfig, MergeDecision,
+    MergeFavor, MergeOptions,
+};
+
+fn root(text: &str) -> GitAttributes {
+    let mut attributes = GitAttributes::new();
+    attributes.add(AttributeSource::Tree(String::new()), text);
+    attributes
+}
+
+fn text_options(decision: MergeDecision) -> MergeOptions {
+    match decision {
+        MergeDecision::Text(options) => options,
+        other => panic!("expected a text merge, got {:?}", other),
+    }
+}
+
+#[test]
+fn union_and_marker_size_apply_to_options() {
+    let attributes = root("CHANGELOG.md merge=union conflict-marker-size=32\n");
+
+    let options = text_options(attributes.merge_decision("CHANGELOG.md", &MergeOptions::default()));
+    assert_eq!(options.favor, Some(MergeFavor::Union));
+    assert_eq!(options.marker_size, 32);
+
+    let options = text_options(attributes.merge_decision("README.md", &MergeOptions::default()));
+    assert_eq!(options.favor, None);
+    assert_eq!(options.marker_size, 7);
+}
+
+#[test]
+fn binary_and_unset_merge_opt_out() {
+    let attributes = root("*.png binary\n*.lock -merge\n*.dat merge=binary\n*.txt merge=text\n");
+
+    for path in ["logo.png", "Cargo.lock", "data/blob.dat"] {
+        assert!(
+            matches!(
+                attributes.merge_decision(path, &MergeOptions::default()),
+                MergeDecision::Binary
+            ),
+            "{} should not be text-merged",
+            path
+        );
+    }
+    text_options(attributes.merge_decision("notes.txt", &MergeOptions::default()));
+
+    let png = attributes.attributes_for("logo.png");
+    assert_eq!(png.get("diff"), Some(&AttributeValue::Unset));
+    assert_eq!(png.get("text"), Some(&AttributeValue::Unset));
+    assert_eq!(png.get("binary"), Some(&AttributeValue::Set));
+}
+
+#[test]
+fn eol_attribute_selects_eol_policy() {
+    let attributes = root("*.bat text eol=crlf\n*.sh text eol=lf\n");
+
+    let options = text_options(attributes.merge_decision("run.bat", &MergeOptions::default()));
+    assert_eq!(options.eol, EolPolicy::Crlf);
+    let options = text_options(attributes.merge_decision("run.sh", &MergeOptions::default()));
+    assert_eq!(options.eol, EolPolicy::Lf);
+    let options = text_options(attributes.merge_decision("run.py", &MergeOptions::default()));
+    assert_eq!(options.eol, EolPolicy::Preserve);
+}
+
+#[test]
+fn custom_driver_names_are_reported() {
+    let attributes = root("*.json merge=jsonmerge\n");
+    match attributes.merge_decision("package.json", &MergeOptions::default()) {
+        MergeDecision::Driver(name) => assert_eq!(name, "jsonmerge"),
+        other => panic!("expected driver, got {:?}", other),
+    }
+}
+
+#[test]
+fn later_lines_and_deeper_files_take_precedence() {
+    let mut attributes = GitAttributes::new();
+    attributes.add(AttributeSource::Info, "docs/keep.md merge=text\n");
+    attributes.add(
+        AttributeSource::Tree("docs".to_string()),
+        "*.md -merge\nlegacy/*.md merge=union\n",
+    );
+    attributes.add(
+        AttributeSource::Tree(String::new()),
+        "*.md merge=union\n*.md !merge\n",
+    );
+    attributes.add(AttributeSource::Global, "*.md conflict-marker-size=9\n");
+
+    // root: `!merge` leaves it unspecified, so the global file's size applies
+    let options = text_options(attributes.merge_decision("README.md", &MergeOptions::default()));
+    assert_eq!(options.favor, None);
+    assert_eq!(options.marker_size, 9);
+
+    assert!(matches!(
+        attributes.merge_decision("docs/guide.md", &MergeOptions::default()),
+        MergeDecision::Binary
+    ));
+    let options =
+        text_options(attributes.merge_decision("docs/legacy/old.md", &MergeOptions::default()));
+    assert_eq!(options.favor, Some(MergeFavor::Union));
+    text_options(attributes.merge_decision("docs/keep.md", &MergeOptions::default()));
+}
+
+#[test]
+fn pattern_matching_follows_gitattributes_rules() {
+    let attributes = root(concat!(
+        "*.rs a\n",
+        "/top.txt b\n",
+        "src/*.c c\n",
+        "**/gen/** d\n",
+        "file[0-9].txt e\n",
+        "\"with space.txt\" f\n",
+        "dir/ g\n",
+        "text eol=crlf\n",
+    ));
+
+    let has = |path: &str, attr: &str| attributes.attributes_for(path).contains_key(attr);
+    assert!(has("a/b/main.rs", "a"));
+    assert!(has("top.txt", "b"));
+    assert!(!has("sub/top.txt", "b"));
+    assert!(has("src/x.c", "c"));
+    assert!(!has("src/deep/x.c", "c"));
+    assert!(has("gen/out.rs", "d"));
+    assert!(has("a/gen/b/out.rs", "d"));
+    assert!(has("file7.txt", "e"));
+    assert!(!has("filex.txt", "e"));
+    assert!(has("with space.txt", "f"));
+    assert!(!has("dir", "g"));
+    assert_eq!(
+        attributes.attributes_for("text").get("eol"),
+        Some(&AttributeValue::Value("crlf".to_string()))
+    );
+}
+
+#[test]
+fn loads_attribute_files_from_work_tree() {
+    let dir = tempfile::tempdir().unwrap();
+    let work_tree = dir.path();
+    fs::create_dir_all(work_tree.join("docs/api")).unwrap();
+    fs::create_dir_all(work_tree.join(".git/info")).unwrap();
+    fs::write(work_tree.join(".gitattributes"), "*.md merge=union\n").unwrap();
+    fs::write(
+        work_tree.join("docs/api/.gitattributes"),
+        "*.md conflict-marker-size=20\n",
+    )
+    .unwrap();
+    fs::write(work_tree.join(".git/info/attributes"), "secret.md -merge\n").unwrap();
+    let global = work_tree.join("global-attributes");
+    fs::write(&global, "*.md conflict-marker-size=12\n").unwrap();
+    let config =
+        GitConfig::parse(&format!("[core]\nattributesFile = {}\n", global.display())).unwrap();
+
+    let attributes =
+        GitAttributes::load_for_path(work_tree, "docs/api/index.md", Some(&config)).unwrap();
+    let options =
+        text_options(attributes.merge_decision("docs/api/index.md", &MergeOptions::default()));
+    assert_eq!(options.favor, Some(MergeFavor::Union));
+    assert_eq!(options.marker_size, 20);
+
+    let attributes = GitAttributes::load_for_path(work_tree, "secret.md", Some(&config)).unwrap();
+    assert!(matches!(
+        attributes.merge_decision("secret.md", &MergeOptions::default()),
+        MergeDecision::Binary
+    ));
+    let options = text_options(attributes.merge_decision("README.md", &MergeOptions::default()));
+    assert_eq!(options.marker_size, 12);
+}
+
+#[test]
+fn linked_worktrees_read_the_common_info_attributes() {
+    let dir = tempfile::tempdir().unwrap();
+    let main_git = dir.path().join("main/.git");
+    let private = main_git.join("worktrees/feature");
+    fs::create_dir_all(main_git.join("info")).unwrap();
+    fs::create_dir_all(&private).unwrap();
+    fs::write(main_git.join("info/attributes"), "secret.md -merge\n").unwrap();
+    fs::write(private.join("commondir"), "../..\n").unwrap();
+    let work_tree = dir.path().join("feature");
+    fs::create_dir_all(&work_tree).unwrap();
+    fs::write(
+        work_tree.join(".git"),
+        format!("gitdir: {}\n", private.display()),
+    )
+    .unwrap();
+
+    let attributes = GitAttributes::load_for_path(&work_tree, "secret.md", None).unwrap();
+    assert!(matches!(
+        attributes.merge_decision("secret.md", &MergeOptions::default()),
+        MergeDecision::Binary
+    ));
+}
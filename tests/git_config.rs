@@ -0,0 +1,178 @@
+use std::fs;
+use threeway_merge::{
+    ConfigFiles, DiffAlgorithm, GitConfig, MergeDriver, MergeError, MergeOptions, MergeStyle,
+};
+
+#[test]
+fn maps_conflict_style_and_diff_algorithm() {
+    let config =
+        GitConfig::parse("[merge]\n\tconflictStyle = zdiff3\n[diff]\n\talgorithm = Histogram\n")
+            .unwrap();
+
+    let options = MergeOptions::from_git_config(&config).unwrap();
+    assert_eq!(options.style, MergeStyle::ZealousDiff3);
+    assert_eq!(options.algorithm, DiffAlgorithm::Histogram);
+    assert_eq!(options.marker_size, MergeOptions::default().marker_size);
+}
+
+#[test]
+fn unset_keys_keep_defaults() {
+    let config = GitConfig::parse("[core]\n\tautocrlf = false\n").unwrap();
+    let options = MergeOptions::from_git_config(&config).unwrap();
+
+    assert_eq!(options.style, MergeStyle::Normal);
+    assert_eq!(options.algorithm, DiffAlgorithm::Myers);
+}
+
+#[test]
+fn rejects_unknown_conflict_style() {
+    let config = GitConfig::parse("[merge]\nconflictstyle = fancy\n").unwrap();
+    let err = MergeOptions::from_git_config(&config).unwrap_err();
+    assert!(matches!(err, MergeError::InvalidInput(_)));
+}
+
+#[test]
+fn parses_git_value_syntax() {
+    let config = GitConfig::parse(concat!(
+        "# comment\n",
+        "[Section \"Sub Section\"]\n",
+        "  Key = \"quoted ; not a comment\"  ; trailing comment\n",
+        "  spaced =   a   b   \n",
+        "  escaped = tab\\there \\\n",
+        "continued\n",
+        "  flag\n",
+        "[old.Style]\n",
+        "  key = value\n",
+    ))
+    .unwrap();
+
+    assert_eq!(
+        config.get("section.Sub Section.key"),
+        Some("quoted ; not a comment")
+    );
+    assert_eq!(
+        config.get("SECTION.Sub Section.KEY"),
+        Some("quoted ; not a comment")
+    );
+    assert_eq!(config.get("section.sub section.key"), None);
+    assert_eq!(config.get("section.Sub Section.spaced"), Some("a   b"));
+    assert_eq!(
+        config.get("section.Sub Section.escaped"),
+        Some("tab\there continued")
+    );
+    assert_eq!(config.get_bool("section.Sub Section.flag"), Some(true));
+    assert_eq!(config.get("old.style.key"), Some("value"));
+}
+
+#[test]
+fn reports_malformed_lines() {
+    let err = GitConfig::parse("[merge]\nconflictStyle = \"unterminated\n").unwrap_err();
+    assert!(matches!(err, MergeError::InvalidInput(msg) if msg.contains("line 2")));
+
+    let err = GitConfig::parse("key = value\n").unwrap_err();
+    assert!(matches!(err, MergeError::InvalidInput(_)));
+}
+
+#[test]
+fn later_layers_take_precedence() {
+    let mut config = GitConfig::parse("[merge]\nconflictStyle = diff3\n").unwrap();
+    config.layer(GitConfig::parse("[merge]\nconflictStyle = zdiff3\n").unwrap());
+
+    assert_eq!(config.get("merge.conflictstyle"), Some("zdiff3"));
+    assert_eq!(
+        config.get_all("merge.conflictStyle"),
+        vec!["diff3", "zdiff3"]
+    );
+}
+
+#[test]
+fn loads_layers_and_gates_worktree_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let system = dir.path().join("gitconfig");
+    let global = dir.path().join(".gitconfig");
+    let git_dir = dir.path().join(".git");
+    fs::create_dir(&git_dir).unwrap();
+
+    fs::write(
+        &system,
+        "[diff]\nalgorithm = patience\n[merge]\nconflictStyle = diff3\n",
+    )
+    .unwrap();
+    fs::write(&global, "[merge]\nconflictStyle = zdiff3\n").unwrap();
+    fs::write(git_dir.join("config"), "[diff]\nalgorithm = minimal\n").unwrap();
+    fs::write(
+        git_dir.join("config.worktree"),
+        "[diff]\nalgorithm = histogram\n",
+    )
+    .unwrap();
+
+    let files = ConfigFiles {
+        system: Some(system),
+        global: vec![global, dir.path().join("missing")],
+        repository: Some(git_dir.join("config")),
+        worktree: Some(git_dir.join("config.worktree")),
+    };
+
+    let options = MergeOptions::from_git_config(&GitConfig::load(&files).unwrap()).unwrap();
+    assert_eq!(options.style, MergeStyle::ZealousDiff3);
+    assert_eq!(options.algorithm, DiffAlgorithm::Minimal);
+
+    // git only honours the extension in the repository's own config.
+    fs::write(
+        &files.global[0],
+        "[merge]\nconflictStyle = zdiff3\n[extensions]\nworktreeConfig = true\n",
+    )
+    .unwrap();
+    let options = MergeOptions::from_git_config(&GitConfig::load(&files).unwrap()).unwrap();
+    assert_eq!(options.algorithm, DiffAlgorithm::Minimal);
+
+    fs::write(
+        git_dir.join("config"),
+        "[extensions]\nworktreeConfig = true\n[diff]\nalgorithm = minimal\n",
+    )
+    .unwrap();
+    let options = MergeOptions::from_git_config(&GitConfig::load(&files).unwrap()).unwrap();
+    assert_eq!(options.algorithm, DiffAlgorithm::Histogram);
+}
+
+#[test]
+fn follows_relative_includes() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("config"),
+        "[include]\npath = extra.inc\n[diff]\nalgorithm = patience\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("extra.inc"),
+        "[merge]\nconflictStyle = diff3\n[diff]\nalgorithm = histogram\n",
+    )
+    .unwrap();
+
+    let config = GitConfig::from_file(&dir.path().join("config")).unwrap();
+    let options = MergeOptions::from_git_config(&config).unwrap();
+    assert_eq!(options.style, MergeStyle::Diff3);
+    // The include is expanded in place, so the later value wins.
+    assert_eq!(options.algorithm, DiffAlgorithm::Patience);
+}
+
+#[test]
+fn reads_merge_driver_sections() {
+    let config = GitConfig::parse(concat!(
+        "[merge \"jsonmerge\"]\n",
+        "\tname = JSON merge driver\n",
+        "\tdriver = jsonmerge %O %A %B %L %P\n",
+    ))
+    .unwrap();
+
+    assert_eq!(
+        config.merge_driver("jsonmerge"),
+        Some(MergeDriver {
+            name: Some("JSON merge driver".to_string()),
+            driver: Some("jsonmerge %O %A %B %L %P".to_string()),
+            recursive: None,
+        })
+    );
+    assert_eq!(config.merge_driver("JSONMERGE"), None);
+    assert_eq!(config.merge_driver("missing"), None);
+}
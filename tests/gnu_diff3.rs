@@ -0,0 +1,263 @@
+use std::fs;
+use std::process::Command;
+use threeway_merge::{Diff3Format, Diff3Mode, MergeError, MergeOptions, diff3};
+
+const BASE: &str = "a\nb\nc\nd\ne\nf\ng\nh\n";
+/// Changes `b` like theirs, and `f`
+const OURS: &str = "a\nB\nc\nd\ne\nF\ng\nh\n";
+/// Changes `b` like ours, and `d` and `h`
+const THEIRS: &str = "a\nB\nc\nD\ne\nf\ng\nH\n";
+
+fn labelled() -> MergeOptions {
+    MergeOptions {
+        ours_label: Some("mine".to_string()),
+        base_label: Some("older".to_string()),
+        theirs_label: Some("yours".to_string()),
+        ..MergeOptions::default()
+    }
+}
+
+fn render(base: &str, ours: &str, theirs: &str, format: Diff3Format) -> String {
+    diff3(base, ours, theirs, format, &labelled())
+        .unwrap()
+        .content
+}
+
+#[test]
+fn listing_names_the_odd_file_out() {
+    assert_eq!(
+        render(BASE, OURS, THEIRS, Diff3Format::Listing),
+        "\
+====2
+1:2c
+3:2c
+  B
+2:2c
+  b
+====3
+1:4c
+2:4c
+  d
+3:4c
+  D
+====1
+1:6c
+  F
+2:6c
+3:6c
+  f
+====3
+1:8c
+2:8c
+  h
+3:8c
+  H
+"
+    );
+
+    let result = diff3(
+        "a\nb\nc",
+        "a\nX\nc",
+        "a\nY\nc\nz\n",
+        Diff3Format::Listing,
+        &MergeOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(result.conflicts, 1);
+    assert_eq!(
+        result.content,
+        "\
+====
+1:2,3c
+  X
+  c
+\\ No newline at end of file
+2:2,3c
+  b
+  c
+\\ No newline at end of file
+3:2,4c
+  Y
+  c
+  z
+"
+    );
+}
+
+#[test]
+fn ed_scripts_select_changes_by_mode() {
+    let (base, ours, theirs) = ("a\nb\nc\n", "a\nX\nc\n", "a\nY\nc\nz\n");
+    let ed = |mode| render(base, ours, theirs, Diff3Format::EdScript(mode));
+    assert_eq!(ed(Diff3Mode::Ed), "3a\nz\n.\n2c\nY\n.\n");
+    assert_eq!(ed(Diff3Mode::EasyOnly), "3a\nz\n.\n");
+    assert_eq!(ed(Diff3Mode::OverlapOnly), "2c\nY\n.\n");
+    assert_eq!(
+        ed(Diff3Mode::ShowOverlap),
+        "3a\nz\n.\n2a\n=======\nY\n>>>>>>> yours\n.\n1a\n<<<<<<< mine\n.\n"
+    );
+    assert_eq!(
+        ed(Diff3Mode::ShowAll),
+        "3a\nz\n.\n2a\n||||||| older\nb\n=======\nY\n>>>>>>> yours\n.\n1a\n<<<<<<< mine\n.\n"
+    );
+
+    // Changes both sides made alike are only shown by `-A`
+    let result = diff3(
+        BASE,
+        OURS,
+        THEIRS,
+        Diff3Format::EdScript(Diff3Mode::ShowAll),
+        &labelled(),
+    )
+    .unwrap();
+    assert_eq!(result.conflicts, 1);
+    assert_eq!(
+        result.content,
+        "8c\nH\n.\n4c\nD\n.\n2a\n>>>>>>> yours\n.\n1a\n<<<<<<< older\nb\n=======\n.\n"
+    );
+}
+
+#[test]
+fn ed_scripts_escape_leading_dots() {
+    assert_eq!(
+        render(
+            "a\nb\nc\n",
+            "a\nb\nc\n",
+            "a\n.x\nc\n",
+            Diff3Format::EdScript(Diff3Mode::Ed)
+        ),
+        "2c\n..x\n.\n2s/^\\.//\n"
+    );
+}
+
+#[test]
+fn merge_applies_changes_to_ours() {
+    let result = diff3(
+        BASE,
+        OURS,
+        THEIRS,
+        Diff3Format::Merge(Diff3Mode::ShowAll),
+        &labelled(),
+    )
+    .unwrap();
+    assert_eq!(
+        result.content,
+        "a\n<<<<<<< older\nb\n=======\nB\n>>>>>>> yours\nc\nD\ne\nF\ng\nH\n"
+    );
+    assert_eq!(result.conflicts, 1);
+
+    let result = diff3(
+        BASE,
+        OURS,
+        THEIRS,
+        Diff3Format::Merge(Diff3Mode::ShowOverlap),
+        &MergeOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(result.content, "a\nB\nc\nD\ne\nF\ng\nH\n");
+    assert!(!result.has_conflicts());
+}
+
+#[test]
+fn rejects_binary_input() {
+    let result = diff3(
+        "a\0\n",
+        "b\n",
+        "c\n",
+        Diff3Format::Listing,
+        &MergeOptions::default(),
+    );
+    assert!(matches!(result, Err(MergeError::Binary(_))));
+}
+
+/// Inputs with dots, duplicate lines, missing final newlines and every
+/// kind of block.
+const CASES: &[(&str, &str, &str)] = &[
+    (BASE, OURS, THEIRS),
+    ("a\nb\nc\n", "a\nX\nc\n", "a\nY\nc\nz\n"),
+    ("a\nb\nc", "a\nX\nc", "a\nY\nc\nz"),
+    ("a\nb\nc\n", "a\nb\nc", "a\nb\nc\nd\n"),
+    (
+        ".dot0\n.dot1\nl2\nl3\nl4\nl5\n",
+        "o1\no2\nl2\nsame0\nl5\n",
+        ".dot0\nsame0\nl2\nl4\nt3\n",
+    ),
+    (
+        "l0\nl1\n.dot2\nl3\nl4\nl5\nl6\nl7\nl8\n",
+        "l0\n.dot2\nl3\no1\nl4\nl5\nsame0\no2\nsame1\n",
+        "l0\nl1\n.dot2\nt3\nsame0\nl5\nl6\nl7\nl8\nt4\n",
+    ),
+    (
+        "l0\nl1\nl2\nl3\nl4\nl5\nl6\nl7\nl8\nl9\nl10\n",
+        "l0\no1\nl1\nl2\nl3\nl4\nl5\nl6\no2\nl7\nsame0\no3\n",
+        "same2\nl1\nl2\nl3\nl4\nt4\nsame0\nl7\nl8\nsame1\nt5\n",
+    ),
+    ("", "a\n", "b\n"),
+    ("x\ny\n", "", "x\n"),
+];
+
+#[test]
+fn matches_system_diff3() {
+    if Command::new("diff3").arg("--version").output().is_err() {
+        eprintln!("diff3 not found, skipping");
+        return;
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let (mine, older, yours) = (
+        dir.path().join("mine"),
+        dir.path().join("older"),
+        dir.path().join("yours"),
+    );
+    let formats: &[(&[&str], Diff3Format)] = &[
+        (&[], Diff3Format::Listing),
+        (&["-e"], Diff3Format::EdScript(Diff3Mode::Ed)),
+        (&["-E"], Diff3Format::EdScript(Diff3Mode::ShowOverlap)),
+        (&["-A"], Diff3Format::EdScript(Diff3Mode::ShowAll)),
+        (&["-3"], Diff3Format::EdScript(Diff3Mode::EasyOnly)),
+        (&["-x"], Diff3Format::EdScript(Diff3Mode::OverlapOnly)),
+        (&["-m"], Diff3Format::Merge(Diff3Mode::ShowAll)),
+        (&["-m", "-E"], Diff3Format::Merge(Diff3Mode::ShowOverlap)),
+        (&["-m", "-e"], Diff3Format::Merge(Diff3Mode::Ed)),
+        (&["-m", "-3"], Diff3Format::Merge(Diff3Mode::EasyOnly)),
+        (&["-m", "-x"], Diff3Format::Merge(Diff3Mode::OverlapOnly)),
+    ];
+
+    for &(base, ours, theirs) in CASES {
+        fs::write(&mine, ours).unwrap();
+        fs::write(&older, base).unwrap();
+        fs::write(&yours, theirs).unwrap();
+        for &(flags, format) in formats {
+            // `diff3` only takes labels for the formats with brackets
+            let brackets = matches!(
+                format,
+                Diff3Format::EdScript(Diff3Mode::ShowAll | Diff3Mode::ShowOverlap)
+                    | Diff3Format::Merge(Diff3Mode::ShowAll | Diff3Mode::ShowOverlap)
+            );
+            let mut command = Command::new("diff3");
+            command.args(flags);
+            if brackets {
+                command.args(["-L", "mine", "-L", "older", "-L", "yours"]);
+            }
+            let output = command.arg(&mine).arg(&older).arg(&yours).output().unwrap();
+            let expected = String::from_utf8(output.stdout).unwrap();
+
+            let result = diff3(base, ours, theirs, format, &labelled()).unwrap();
+            assert_eq!(
+                result.content,
+                expected,
+                "diff3 {:?} on {:?}",
+                flags,
+                (base, ours, theirs)
+            );
+            if format != Diff3Format::Listing {
+                assert_eq!(
+                    result.has_conflicts(),
+                    output.status.code() == Some(1),
+                    "diff3 {:?} on {:?}",
+                    flags,
+                    (base, ours, theirs)
+                );
+            }
+        }
+    }
+}
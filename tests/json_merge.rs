@@ -0,0 +1,125 @@
+#![cfg(feature = "json")]
+
+use serde_json::json;
+use threeway_merge::{MergeError, merge_json};
+
+#[test]
+fn different_keys_merge_cleanly() {
+    let base = r#"{"name": "app", "version": "1.0.0", "private": true}"#;
+    let ours = r#"{"name": "app", "version": "1.1.0", "private": true}"#;
+    let theirs = r#"{"name": "app", "version": "1.0.0", "private": true, "license": "MIT"}"#;
+
+    let result = merge_json(base, ours, theirs).unwrap();
+    assert!(result.is_clean_merge());
+    assert_eq!(
+        result.value,
+        json!({"name": "app", "version": "1.1.0", "private": true, "license": "MIT"})
+    );
+}
+
+#[test]
+fn trailing_commas_do_not_conflict() {
+    // Line-based merging conflicts here: both sides append after the same
+    // last entry, and each adds a comma to it.
+    let base = "{\n  \"a\": 1\n}\n";
+    let ours = "{\n  \"a\": 1,\n  \"b\": 2\n}\n";
+    let theirs = "{\n  \"a\": 1,\n  \"c\": 3\n}\n";
+
+    let result = merge_json(base, ours, theirs).unwrap();
+    assert!(result.is_clean_merge());
+    assert_eq!(
+        result.content,
+        "{\n  \"a\": 1,\n  \"b\": 2,\n  \"c\": 3\n}\n"
+    );
+    serde_json::from_str::<serde_json::Value>(&result.content).unwrap();
+}
+
+#[test]
+fn key_order_follows_ours() {
+    let base = r#"{"a": 1, "b": 2, "c": 3}"#;
+    let ours = r#"{"c": 3, "a": 1, "b": 2}"#;
+    let theirs = r#"{"a": 1, "b": 20, "c": 3, "d": 4}"#;
+
+    let result = merge_json(base, ours, theirs).unwrap();
+    let keys: Vec<_> = result.value.as_object().unwrap().keys().cloned().collect();
+    assert_eq!(keys, ["c", "a", "b", "d"]);
+    assert_eq!(result.content, r#"{"c":3,"a":1,"b":20,"d":4}"#);
+}
+
+#[test]
+fn conflicts_are_reported_as_json_pointers() {
+    let base = r#"{"deps": {"serde/derive": "1.0", "libc": "0.2"}, "keep": 1}"#;
+    let ours = r#"{"deps": {"serde/derive": "1.1", "libc": "0.2"}, "keep": 1}"#;
+    let theirs = r#"{"deps": {"serde/derive": "1.2"}, "keep": 1}"#;
+
+    let result = merge_json(base, ours, theirs).unwrap();
+    assert_eq!(result.conflicts.len(), 1);
+    let conflict = &result.conflicts[0];
+    assert_eq!(conflict.path, "/deps/serde~1derive");
+    assert_eq!(conflict.base, Some(json!("1.0")));
+    assert_eq!(conflict.ours, Some(json!("1.1")));
+    assert_eq!(conflict.theirs, Some(json!("1.2")));
+
+    // theirs' deletion of "libc" still applies, and ours is kept at the conflict
+    assert_eq!(
+        result.value,
+        json!({"deps": {"serde/derive": "1.1"}, "keep": 1})
+    );
+}
+
+#[test]
+fn delete_versus_modify_conflicts() {
+    let result = merge_json(r#"{"a": 1}"#, r#"{}"#, r#"{"a": 2}"#).unwrap();
+    assert_eq!(result.conflicts.len(), 1);
+    assert_eq!(result.conflicts[0].path, "/a");
+    assert_eq!(result.conflicts[0].ours, None);
+    assert_eq!(result.value, json!({}));
+}
+
+#[test]
+fn string_leaves_use_line_merge() {
+    let base = json!({"script": "one\ntwo\nthree\nfour\n"}).to_string();
+    let ours = json!({"script": "ONE\ntwo\nthree\nfour\n"}).to_string();
+    let theirs = json!({"script": "one\ntwo\nthree\nFOUR\n"}).to_string();
+
+    let result = merge_json(&base, &ours, &theirs).unwrap();
+    assert!(result.is_clean_merge());
+    assert_eq!(result.value, json!({"script": "ONE\ntwo\nthree\nFOUR\n"}));
+
+    let result = merge_json(r#"{"s": "a"}"#, r#"{"s": "b"}"#, r#"{"s": "c"}"#).unwrap();
+    assert_eq!(result.conflicts.len(), 1);
+}
+
+#[test]
+fn arrays_merge_element_wise() {
+    let base = r#"{"list": [1, 2, 3, 4, 5]}"#;
+    let ours = r#"{"list": [0, 1, 2, 3, 4, 5]}"#;
+    let theirs = r#"{"list": [1, 2, 3, 4, 5, {"x": 6}]}"#;
+
+    let result = merge_json(base, ours, theirs).unwrap();
+    assert!(result.is_clean_merge());
+    assert_eq!(result.value, json!({"list": [0, 1, 2, 3, 4, 5, {"x": 6}]}));
+
+    let result = merge_json(r#"[1]"#, r#"[2]"#, r#"[3]"#).unwrap();
+    assert_eq!(result.conflicts.len(), 1);
+    assert_eq!(result.conflicts[0].path, "");
+}
+
+#[test]
+fn preserves_indentation_of_ours() {
+    let base = "{\n    \"a\": 1\n}";
+    let ours = "{\n    \"a\": 2\n}";
+    let theirs = "{\n  \"a\": 1,\n  \"b\": [true]\n}\n";
+
+    let result = merge_json(base, ours, theirs).unwrap();
+    assert_eq!(
+        result.content,
+        "{\n    \"a\": 2,\n    \"b\": [\n        true\n    ]\n}"
+    );
+}
+
+#[test]
+fn rejects_invalid_json() {
+    let err = merge_json("{}", "{", "{}").unwrap_err();
+    assert!(matches!(err, MergeError::InvalidInput(msg) if msg.starts_with("ours")));
+}
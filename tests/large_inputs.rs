@@ -0,0 +1,71 @@
+//! Merges of inputs over 2 GiB, past the range of a C `int`. The inputs are
+//! written to temporary files and merged with `merge_files`, which maps them,
+//! but the output still needs a few GiB of memory, so these only run on
+//! request:
+//!
+//! ```bash
+//! cargo test --release --test large_inputs -- --ignored
+//! ```
+//!
+//! The size checks themselves are unit tested in `src/merge.rs` with sizes
+//! that are never allocated.
+
+#![cfg(feature = "fs")]
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::os::raw::c_long;
+use std::path::Path;
+use threeway_merge::{MergeError, MergeOptions, merge_files};
+
+const LINE_LEN: usize = 1 << 20;
+/// 2049 MiB in total
+const LINES: usize = 2049;
+
+/// Writes LINES numbered lines of LINE_LEN bytes, replacing the first
+/// character of the given lines.
+fn write_input(path: &Path, edits: &[(usize, u8)]) {
+    let mut out = BufWriter::new(File::create(path).unwrap());
+    let mut line = vec![b'.'; LINE_LEN];
+    *line.last_mut().unwrap() = b'\n';
+    for i in 0..LINES {
+        line[..10].copy_from_slice(format!("{:>10}", i).as_bytes());
+        if let Some(&(_, c)) = edits.iter().find(|(at, _)| *at == i) {
+            line[0] = c;
+        }
+        out.write_all(&line).unwrap();
+    }
+    out.into_inner().unwrap().sync_all().unwrap();
+}
+
+#[test]
+#[ignore = "needs about 7 GiB of disk and 5 GiB of memory"]
+fn merges_inputs_larger_than_2_gib() {
+    let dir = tempfile::tempdir().unwrap();
+    let [base, ours, theirs] = ["base", "ours", "theirs"].map(|name| dir.path().join(name));
+    write_input(&base, &[]);
+    write_input(&ours, &[(0, b'o'), (LINES - 1, b'o')]);
+    write_input(&theirs, &[(LINES - 1, b't')]);
+    assert!(std::fs::metadata(&base).unwrap().len() > i32::MAX as u64);
+
+    let result = merge_files(&base, &ours, &theirs, &MergeOptions::default());
+
+    if c_long::BITS < 64 {
+        // LLP64 targets such as Windows cannot hand xdiff more than 2 GiB
+        assert!(matches!(result, Err(MergeError::InvalidInput(_))));
+        return;
+    }
+    let result = result.unwrap();
+    assert_eq!(result.conflicts, 1);
+
+    // The clean edit at the start, then the conflict on the last line
+    let content = &result.content;
+    let conflict_at = (LINES - 1) * LINE_LEN;
+    assert_eq!(content.len(), LINES * LINE_LEN + LINE_LEN + 3 * 8);
+    assert_eq!(&content[..10], b"o        0");
+    assert_eq!(&content[conflict_at..conflict_at + 8], b"<<<<<<<\n");
+    assert_eq!(&content[conflict_at + 8..conflict_at + 18], b"o     2048");
+    let tail = &content[content.len() - LINE_LEN - 16..];
+    assert_eq!(&tail[..18], b"=======\nt     2048");
+    assert!(tail.ends_with(b"\n>>>>>>>\n"));
+}
@@ -0,0 +1,107 @@
+use threeway_merge::{EolPolicy, LineEnding, MergeOptions, merge_strings};
+
+fn with_eol(eol: EolPolicy) -> MergeOptions {
+    MergeOptions {
+        eol,
+        ..MergeOptions::default()
+    }
+}
+
+#[test]
+fn reports_detected_line_endings() {
+    let result =
+        merge_strings("a\nb\n", "a\r\nb\r\n", "a\r\nb\n", &MergeOptions::default()).unwrap();
+
+    assert_eq!(result.line_endings.base, LineEnding::Lf);
+    assert_eq!(result.line_endings.ours, LineEnding::Crlf);
+    assert_eq!(result.line_endings.theirs, LineEnding::Mixed);
+
+    let result = merge_strings("a", "a", "b", &MergeOptions::default()).unwrap();
+    assert_eq!(result.line_endings.base, LineEnding::None);
+}
+
+#[test]
+fn preserve_merges_inputs_untouched() {
+    // Only the line ending of "one" differs on our side, which xdiff sees as
+    // a change that overlaps theirs.
+    let base = "one\ntwo\nthree\n";
+    let ours = "one\r\ntwo\nthree\n";
+    let theirs = "uno\ntwo\nthree\n";
+
+    let result = merge_strings(base, ours, theirs, &with_eol(EolPolicy::Preserve)).unwrap();
+    assert_eq!(result.conflicts, 1);
+}
+
+#[test]
+fn lf_normalization_avoids_spurious_conflicts() {
+    let base = "one\ntwo\nthree\n";
+    let ours = "one\r\ntwo\r\nthree\r\n";
+    let theirs = "uno\ntwo\nthree\n";
+
+    let result = merge_strings(base, ours, theirs, &with_eol(EolPolicy::Lf)).unwrap();
+    assert_eq!(result.conflicts, 0);
+    assert_eq!(result.content, "uno\ntwo\nthree\n");
+}
+
+#[test]
+fn crlf_policy_restores_crlf_including_markers() {
+    let base = "one\ntwo\n";
+    let ours = "ONE\r\ntwo\r\n";
+    let theirs = "uno\ntwo\n";
+
+    let result = merge_strings(base, ours, theirs, &with_eol(EolPolicy::Crlf)).unwrap();
+    assert_eq!(result.conflicts, 1);
+    assert_eq!(
+        result.content,
+        "<<<<<<<\r\nONE\r\n=======\r\nuno\r\n>>>>>>>\r\ntwo\r\n"
+    );
+}
+
+#[test]
+fn crlf_policy_keeps_a_lone_cr_before_an_added_newline() {
+    // The last lines end in a lone CR; xdiff adds a newline after each in
+    // the conflict, which must not become CR CR LF
+    let base = "a\r\nb\r\n";
+    let ours = "a\r\nB\r";
+    let theirs = "a\r\nC\r";
+
+    let result = merge_strings(base, ours, theirs, &with_eol(EolPolicy::Crlf)).unwrap();
+    assert_eq!(result.conflicts, 1);
+    assert_eq!(
+        result.content,
+        "a\r\n<<<<<<<\r\nB\r\n=======\r\nC\r\n>>>>>>>\r\n"
+    );
+}
+
+#[test]
+fn match_ours_follows_dominant_style_of_ours() {
+    let base = "a\nb\nc\n";
+    let ours = "a\r\nb\r\nc\nd\r\n";
+    let theirs = "A\nb\nc\n";
+
+    let result = merge_strings(base, ours, theirs, &with_eol(EolPolicy::MatchOurs)).unwrap();
+    assert_eq!(result.conflicts, 0);
+    assert_eq!(result.content, "A\r\nb\r\nc\r\nd\r\n");
+
+    let result = merge_strings(
+        base,
+        "a\nb\nc\nd\n",
+        theirs,
+        &with_eol(EolPolicy::MatchOurs),
+    )
+    .unwrap();
+    assert_eq!(result.content, "A\nb\nc\nd\n");
+}
+
+#[test]
+fn fast_paths_apply_after_normalization() {
+    let result = merge_strings(
+        "x\ny\n",
+        "x\r\ny\r\n",
+        "x\ny\nz\n",
+        &with_eol(EolPolicy::Crlf),
+    )
+    .unwrap();
+    assert_eq!(result.conflicts, 0);
+    assert_eq!(result.content, "x\r\ny\r\nz\r\n");
+}
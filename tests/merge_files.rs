@@ -0,0 +1,89 @@
+#![cfg(feature = "fs")]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use threeway_merge::{MergeError, MergeOptions, TextEncoding, merge_files, merge_strings};
+
+fn write(dir: &Path, name: &str, content: &[u8]) -> PathBuf {
+    let path = dir.join(name);
+    fs::write(&path, content).unwrap();
+    path
+}
+
+#[test]
+fn merges_files_like_strings() {
+    let dir = tempfile::tempdir().unwrap();
+    let (base, ours, theirs) = ("a\nb\nc\n", "A\nb\nc\n", "a\nb\nC\n");
+    let paths = [("base", base), ("ours", ours), ("theirs", theirs)]
+        .map(|(name, content)| write(dir.path(), name, content.as_bytes()));
+
+    let result = merge_files(&paths[0], &paths[1], &paths[2], &MergeOptions::default()).unwrap();
+    let expected = merge_strings(base, ours, theirs, &MergeOptions::default()).unwrap();
+    assert_eq!(result.content, expected.content.as_bytes());
+    assert_eq!(result.encoding, TextEncoding::Utf8);
+
+    // Like `git merge-file` without --stdout
+    result.write_to(&paths[1]).unwrap();
+    assert_eq!(fs::read_to_string(&paths[1]).unwrap(), "A\nb\nC\n");
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+}
+
+#[test]
+fn empty_and_non_utf8_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let base = write(dir.path(), "base", b"");
+    let ours = write(dir.path(), "ours", b"\xff\xfe\xfd\n");
+    let theirs = write(dir.path(), "theirs", b"");
+
+    let result = merge_files(&base, &ours, &theirs, &MergeOptions::default()).unwrap();
+    assert!(!result.has_conflicts());
+    assert_eq!(result.content, b"\xff\xfe\xfd\n");
+}
+
+#[test]
+fn missing_input_is_an_io_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let base = write(dir.path(), "base", b"a\n");
+    let missing = dir.path().join("missing");
+
+    let err = merge_files(&base, &missing, &base, &MergeOptions::default()).unwrap_err();
+    assert!(matches!(err, MergeError::Io(msg) if msg.contains("missing")));
+}
+
+#[test]
+fn write_to_creates_new_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let base = write(dir.path(), "base", b"a\n");
+    let result = merge_files(&base, &base, &base, &MergeOptions::default()).unwrap();
+
+    let out = dir.path().join("out");
+    result.write_to(&out).unwrap();
+    assert_eq!(fs::read(&out).unwrap(), b"a\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn write_to_keeps_permissions_and_follows_symlinks() {
+    use std::os::unix::fs::{PermissionsExt, symlink};
+
+    let dir = tempfile::tempdir().unwrap();
+    let base = write(dir.path(), "base", b"a\n");
+    let theirs = write(dir.path(), "theirs", b"b\n");
+    let target = write(dir.path(), "script.sh", b"a\n");
+    fs::set_permissions(&target, fs::Permissions::from_mode(0o750)).unwrap();
+    let link = dir.path().join("link");
+    symlink("script.sh", &link).unwrap();
+
+    let result = merge_files(&base, &link, &theirs, &MergeOptions::default()).unwrap();
+    result.write_to(&link).unwrap();
+
+    assert!(
+        fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink()
+    );
+    assert_eq!(fs::read(&target).unwrap(), b"b\n");
+    let mode = fs::metadata(&target).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o750);
+}
@@ -0,0 +1,148 @@
+use std::thread;
+use std::time::{Duration, Instant};
+use threeway_merge::{
+    CancellationToken, Diff3Format, DiffAlgorithm, MergeError, MergeLimits, MergeOptions,
+    RenderOptions, diff3, merge_strings, merge_with_moves, render_ansi,
+};
+
+fn limited(limits: MergeLimits) -> MergeOptions {
+    MergeOptions {
+        limits,
+        ..MergeOptions::default()
+    }
+}
+
+/// Inputs that make a minimal Myers diff do quadratic work
+fn pathological() -> (String, String, String) {
+    let base: String = (0..20_000).map(|i| format!("line {}\n", i)).collect();
+    let ours: String = (0..20_000).rev().map(|i| format!("line {}\n", i)).collect();
+    let theirs = format!("{}tail\n", base);
+    (base, ours, theirs)
+}
+
+#[test]
+fn oversized_inputs_are_rejected() {
+    let options = limited(MergeLimits {
+        max_input_bytes: Some(8),
+        ..MergeLimits::default()
+    });
+    let err = merge_strings("a\n", "a\nb\n", "a\nb\nc\nd\ne\n", &options).unwrap_err();
+    assert!(matches!(err, MergeError::LimitExceeded(msg) if msg.starts_with("theirs is 10 bytes")));
+
+    let options = limited(MergeLimits {
+        max_lines: Some(2),
+        ..MergeLimits::default()
+    });
+    assert!(merge_strings("a\nb", "a\nc", "a\nd", &options).is_ok());
+    let err = merge_strings("a\nb\nc", "a\n", "a\n", &options).unwrap_err();
+    assert!(matches!(err, MergeError::LimitExceeded(msg) if msg.starts_with("base has 3 lines")));
+
+    let err = merge_with_moves("a\nb\nc", "a\n", "a\n", &options).unwrap_err();
+    assert!(matches!(err, MergeError::LimitExceeded(_)));
+}
+
+#[test]
+fn cancelled_token_stops_every_algorithm() {
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+
+    for algorithm in [
+        DiffAlgorithm::Myers,
+        DiffAlgorithm::Minimal,
+        DiffAlgorithm::Patience,
+        DiffAlgorithm::Histogram,
+    ] {
+        let options = MergeOptions {
+            algorithm,
+            ..limited(MergeLimits {
+                cancel: Some(cancel.clone()),
+                ..MergeLimits::default()
+            })
+        };
+        // Swapped lines still match, so xdiff has to search for a split
+        let err =
+            merge_strings("a\nb\nc\nd\n", "a\nc\nb\nd\n", "a\nb\nc\nd\ne\n", &options).unwrap_err();
+        assert!(
+            matches!(&err, MergeError::LimitExceeded(msg) if msg == "merge was cancelled"),
+            "{:?}: {:?}",
+            algorithm,
+            err
+        );
+    }
+}
+
+#[test]
+fn timeout_interrupts_a_pathological_diff() {
+    let (base, ours, theirs) = pathological();
+    let options = MergeOptions {
+        algorithm: DiffAlgorithm::Minimal,
+        ..limited(MergeLimits {
+            timeout: Some(Duration::from_millis(50)),
+            ..MergeLimits::default()
+        })
+    };
+
+    let start = Instant::now();
+    let err = merge_strings(&base, &ours, &theirs, &options).unwrap_err();
+    assert!(matches!(err, MergeError::LimitExceeded(msg) if msg == "merge timed out"));
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn cancellation_from_another_thread() {
+    let (base, ours, theirs) = pathological();
+    let cancel = CancellationToken::new();
+    let options = MergeOptions {
+        algorithm: DiffAlgorithm::Minimal,
+        ..limited(MergeLimits {
+            cancel: Some(cancel.clone()),
+            ..MergeLimits::default()
+        })
+    };
+
+    let canceller = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        cancel.cancel();
+    });
+    let err = merge_strings(&base, &ours, &theirs, &options).unwrap_err();
+    canceller.join().unwrap();
+    assert!(matches!(err, MergeError::LimitExceeded(msg) if msg == "merge was cancelled"));
+}
+
+#[test]
+fn every_diff_of_a_call_shares_its_limits() {
+    let (base, ours, theirs) = ("a\nb\nc\nd\n", "a\nc\nb\nd\n", "a\nb\nc\nd\ne\n");
+    let result = merge_strings(base, ours, "x a b c d\n", &MergeOptions::default()).unwrap();
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+    let options = limited(MergeLimits {
+        cancel: Some(cancel),
+        ..MergeLimits::default()
+    });
+
+    let cancelled =
+        |err| matches!(err, MergeError::LimitExceeded(msg) if msg == "merge was cancelled");
+    assert!(cancelled(
+        diff3(base, ours, theirs, Diff3Format::Listing, &options).unwrap_err()
+    ));
+    assert!(cancelled(
+        merge_with_moves(base, ours, theirs, &options).unwrap_err()
+    ));
+    // The token-level diffs of the conflict columns
+    assert!(cancelled(
+        render_ansi(&result, &options, &RenderOptions::default()).unwrap_err()
+    ));
+}
+
+#[test]
+fn merges_within_limits_are_unaffected() {
+    let options = limited(MergeLimits {
+        max_input_bytes: Some(1024),
+        max_lines: Some(10),
+        timeout: Some(Duration::from_secs(60)),
+        cancel: Some(CancellationToken::new()),
+    });
+    let result = merge_strings("a\nb\nc\n", "a\nx\nc\n", "a\nb\nc\nd\n", &options).unwrap();
+    assert!(result.is_clean_merge());
+    assert_eq!(result.content, "a\nx\nc\nd\n");
+}
@@ -0,0 +1,136 @@
+use threeway_merge::{MergeOptions, MergeSide, MoveStatus, merge_strings, merge_with_moves};
+
+const BASE: &str = "\
+fn parse() {
+    let a = 1;
+    let b = 2;
+    let c = 3;
+}
+
+fn render() {
+    draw();
+}
+
+fn main() {
+    run();
+}
+";
+
+/// `parse` moved to the end of the file
+const MOVED: &str = "\
+fn render() {
+    draw();
+}
+
+fn main() {
+    run();
+}
+
+fn parse() {
+    let a = 1;
+    let b = 2;
+    let c = 3;
+}
+";
+
+const EDITED: &str = "\
+fn parse() {
+    let a = 1;
+    let b = 20;
+    let c = 3;
+}
+
+fn render() {
+    draw();
+}
+
+fn main() {
+    run();
+}
+";
+
+const MOVED_AND_EDITED: &str = "\
+fn render() {
+    draw();
+}
+
+fn main() {
+    run();
+}
+
+fn parse() {
+    let a = 1;
+    let b = 20;
+    let c = 3;
+}
+";
+
+#[test]
+fn edits_follow_a_block_moved_by_ours() {
+    let plain = merge_strings(BASE, MOVED, EDITED, &MergeOptions::default()).unwrap();
+    assert_ne!(plain.content, MOVED_AND_EDITED);
+
+    let result = merge_with_moves(BASE, MOVED, EDITED, &MergeOptions::default()).unwrap();
+    assert!(result.is_clean_merge());
+    assert_eq!(result.content, MOVED_AND_EDITED);
+    assert_eq!(result.moves.len(), 1);
+    assert_eq!(result.moves[0].side, MergeSide::Ours);
+    assert_eq!(result.moves[0].base_lines, 0..6);
+    assert_eq!(result.moves[0].status, MoveStatus::EditsApplied);
+}
+
+#[test]
+fn edits_follow_a_block_moved_by_theirs() {
+    let result = merge_with_moves(BASE, EDITED, MOVED, &MergeOptions::default()).unwrap();
+    assert!(result.is_clean_merge());
+    assert_eq!(result.content, MOVED_AND_EDITED);
+    assert_eq!(result.moves[0].side, MergeSide::Theirs);
+    assert_eq!(result.moves[0].status, MoveStatus::EditsApplied);
+}
+
+#[test]
+fn clashing_edit_of_moved_block_conflicts_at_new_location() {
+    let moved = MOVED.replace("let b = 2;", "let b = 200;");
+    let result = merge_with_moves(BASE, &moved, EDITED, &MergeOptions::default()).unwrap();
+
+    assert_eq!(result.conflicts, 1);
+    assert_eq!(result.moves[0].status, MoveStatus::Conflict);
+    assert!(result.content.starts_with("fn render() {\n"));
+    assert!(result.content.contains(
+        "fn parse() {\n    let a = 1;\n<<<<<<<\n    let b = 200;\n=======\n    let b = 20;\n>>>>>>>\n"
+    ));
+}
+
+#[test]
+fn untouched_moves_merge_as_usual() {
+    let theirs = BASE.replace("run();", "run(true);");
+    let result = merge_with_moves(BASE, MOVED, &theirs, &MergeOptions::default()).unwrap();
+    let plain = merge_strings(BASE, MOVED, &theirs, &MergeOptions::default()).unwrap();
+
+    assert_eq!(result.content, plain.content);
+    assert_eq!(result.conflicts, plain.conflicts);
+    assert_eq!(result.moves[0].status, MoveStatus::Untouched);
+}
+
+#[test]
+fn short_blocks_are_not_moves() {
+    let base = "a\nb\nc\nd\ne\n";
+    let ours = "c\nd\ne\na\nb\n";
+    let theirs = "A\nb\nc\nd\ne\n";
+
+    let result = merge_with_moves(base, ours, theirs, &MergeOptions::default()).unwrap();
+    assert!(result.moves.is_empty());
+    let plain = merge_strings(base, ours, theirs, &MergeOptions::default()).unwrap();
+    assert_eq!(result.content, plain.content);
+}
+
+#[test]
+fn edits_across_the_block_edge_are_not_transplanted() {
+    // Joins the end of `parse` with the start of `render`
+    let theirs = EDITED.replace("}\n\nfn render() {", "}\nfn render() { // draws");
+    let result = merge_with_moves(BASE, MOVED, &theirs, &MergeOptions::default()).unwrap();
+    let plain = merge_strings(BASE, MOVED, &theirs, &MergeOptions::default()).unwrap();
+
+    assert!(result.moves.is_empty());
+    assert_eq!(result.content, plain.content);
+}
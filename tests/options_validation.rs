@@ -1,9 +1,11 @@
-use threeway_merge::{MergeError, MergeOptions, merge_strings};
+use threeway_merge::{ConflictMarkers, MergeError, MergeOptions, merge_strings};
 
 #[test]
 fn rejects_labels_with_nul_byte() {
-    let mut options = MergeOptions::default();
-    options.base_label = Some("ba\0se".to_string());
+    let options = MergeOptions {
+        base_label: Some("ba\0se".to_string()),
+        ..MergeOptions::default()
+    };
 
     let err = merge_strings("base", "ours", "theirs", &options).unwrap_err();
     assert!(matches!(err, MergeError::InvalidInput(_)));
@@ -11,9 +13,38 @@ fn rejects_labels_with_nul_byte() {
 
 #[test]
 fn rejects_marker_size_over_c_int_max() {
-    let mut options = MergeOptions::default();
-    options.marker_size = (i32::MAX as usize) + 1;
+    let options = MergeOptions {
+        marker_size: (i32::MAX as usize) + 1,
+        ..MergeOptions::default()
+    };
 
     let err = merge_strings("base", "ours", "theirs", &options).unwrap_err();
     assert!(matches!(err, MergeError::InvalidInput(_)));
 }
+
+#[test]
+fn rejects_invalid_marker_templates() {
+    let mut line_break = ConflictMarkers::default();
+    line_break.end.suffix = " */\n".to_string();
+    let mut nul = ConflictMarkers::default();
+    nul.start.prefix = "\0".to_string();
+    for markers in [
+        line_break,
+        nul,
+        ConflictMarkers {
+            label: " ({name})".to_string(),
+            ..ConflictMarkers::default()
+        },
+        ConflictMarkers {
+            label: " {label} {label}".to_string(),
+            ..ConflictMarkers::default()
+        },
+    ] {
+        let options = MergeOptions {
+            markers,
+            ..MergeOptions::default()
+        };
+        let err = merge_strings("base", "ours", "theirs", &options).unwrap_err();
+        assert!(matches!(err, MergeError::InvalidInput(_)));
+    }
+}
@@ -0,0 +1,156 @@
+use threeway_merge::{MergeError, apply_patch_3way};
+
+const ORIGINAL: &str = "\
+one
+two
+three
+four
+five
+six
+seven
+eight
+nine
+";
+
+/// Changes `two` and `eight` in `ORIGINAL`
+const PATCH: &str = "\
+diff --git a/numbers.txt b/numbers.txt
+index 1111111..2222222 100644
+--- a/numbers.txt
++++ b/numbers.txt
+@@ -1,4 +1,4 @@
+ one
+-two
++TWO
+ three
+ four
+@@ -7,3 +7,3 @@
+ seven
+-eight
++EIGHT
+ nine
+";
+
+#[test]
+fn applies_cleanly_to_the_original() {
+    let result = apply_patch_3way(ORIGINAL, PATCH).unwrap();
+    assert_eq!(
+        result.content,
+        "one\nTWO\nthree\nfour\nfive\nsix\nseven\nEIGHT\nnine\n"
+    );
+    assert!(result.is_clean_merge());
+    assert!(!result.three_way);
+}
+
+#[test]
+fn applies_hunks_at_an_offset() {
+    let current = format!("zero\nminus one\n{}", ORIGINAL);
+    let result = apply_patch_3way(&current, PATCH).unwrap();
+    assert_eq!(
+        result.content,
+        "zero\nminus one\none\nTWO\nthree\nfour\nfive\nsix\nseven\nEIGHT\nnine\n"
+    );
+    assert!(!result.three_way);
+}
+
+#[test]
+fn merges_when_context_changed() {
+    // `four` was edited since the patch was made, so the first hunk no
+    // longer matches, but its change does not touch that line
+    let current = ORIGINAL.replace("four", "FOUR");
+    let result = apply_patch_3way(&current, PATCH).unwrap();
+    assert!(result.three_way);
+    assert!(result.is_clean_merge());
+    assert_eq!(
+        result.content,
+        "one\nTWO\nthree\nFOUR\nfive\nsix\nseven\nEIGHT\nnine\n"
+    );
+}
+
+#[test]
+fn leaves_conflict_markers_for_clashing_changes() {
+    let current = ORIGINAL.replace("two", "deux");
+    let result = apply_patch_3way(&current, PATCH).unwrap();
+    assert!(result.three_way);
+    assert_eq!(result.conflicts, 1);
+    assert_eq!(
+        result.content,
+        "one\n<<<<<<< ours\ndeux\n=======\nTWO\n>>>>>>> theirs\nthree\nfour\nfive\nsix\nseven\nEIGHT\nnine\n"
+    );
+}
+
+#[test]
+fn merges_zero_context_hunks_in_place() {
+    // `diff -U0`: hunks without context lines, the second of which no
+    // longer matches
+    let patch = "\
+--- a/numbers.txt
++++ b/numbers.txt
+@@ -2 +2 @@
+-two
++TWO
+@@ -4 +4 @@
+-four
++FOUR
+@@ -8 +8 @@
+-eight
++EIGHT
+";
+    let current = ORIGINAL.replace("four", "vier");
+    let result = apply_patch_3way(&current, patch).unwrap();
+    assert!(result.three_way);
+    assert_eq!(result.conflicts, 1);
+    assert_eq!(
+        result.content,
+        "one\nTWO\nthree\n<<<<<<< ours\nvier\n=======\nFOUR\n>>>>>>> theirs\nfive\nsix\nseven\nEIGHT\nnine\n"
+    );
+}
+
+#[test]
+fn handles_missing_newline_at_end_of_file() {
+    let patch = "\
+--- a/file
++++ b/file
+@@ -1,2 +1,2 @@
+ first
+-last
+\\ No newline at end of file
++LAST
+\\ No newline at end of file
+";
+    let result = apply_patch_3way("first\nlast", patch).unwrap();
+    assert_eq!(result.content, "first\nLAST");
+    assert!(!result.three_way);
+}
+
+#[test]
+fn applies_pure_additions() {
+    let patch = "\
+--- a/file
++++ b/file
+@@ -1,0 +2,1 @@
++inserted
+";
+    let result = apply_patch_3way("first\nsecond\n", patch).unwrap();
+    assert_eq!(result.content, "first\ninserted\nsecond\n");
+}
+
+#[test]
+fn rejects_invalid_patches() {
+    for patch in [
+        "not a patch\n",
+        "@@ -1,x +1 @@\n a\n",
+        "@@ -1,2 +1,2 @@\n a\n",
+        "--- a/x\n+++ b/x\n@@ -1 +1 @@\n-a\n+b\n--- a/y\n+++ b/y\n@@ -1 +1 @@\n-a\n+b\n",
+        "diff --git a/x b/x\nGIT binary patch\nliteral 0\n",
+    ] {
+        assert!(
+            matches!(
+                apply_patch_3way("a\n", patch),
+                Err(MergeError::InvalidInput(_))
+            ),
+            "{:?}",
+            patch
+        );
+    }
+}
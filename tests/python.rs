@@ -0,0 +1,84 @@
+#![cfg(feature = "python")]
+
+use pyo3::ffi::c_str;
+use pyo3::prelude::*;
+use std::ffi::CStr;
+use threeway_merge::python_module;
+
+/// Runs `code` with the module importable as `threeway_merge`.
+fn run(code: &CStr) {
+    Python::initialize();
+    Python::attach(|py| {
+        let module = PyModule::new(py, "threeway_merge").unwrap();
+        python_module(&module).unwrap();
+        py.import("sys")
+            .unwrap()
+            .getattr("modules")
+            .unwrap()
+            .set_item("threeway_merge", module)
+            .unwrap();
+        if let Err(err) = py.run(code, None, None) {
+            err.display(py);
+            panic!("{}", err);
+        }
+    });
+}
+
+#[test]
+fn merge_returns_content_conflicts_and_hunks() {
+    run(c_str!(
+        r#"
+import threeway_merge
+
+result = threeway_merge.merge("a\nb\nc\n", "a\nB\nc\n", "a\nb2\nc\n",
+                              style="diff3", algorithm="histogram", ours_label="ours")
+assert result.content == "a\n<<<<<<< ours\nB\n|||||||\nb\n=======\nb2\n>>>>>>>\nc\n", result.content
+assert result.conflicts == 1 and result.has_conflicts
+
+clean, conflict, tail = result.hunks
+assert (clean.kind, clean.line, clean.content) == ("clean", 1, "a\n")
+assert (conflict.kind, conflict.line) == ("conflict", 2)
+assert (conflict.ours, conflict.base, conflict.theirs) == ("B\n", "b\n", "b2\n")
+assert conflict.content is None
+assert (tail.line, tail.content) == (9, "c\n")
+
+result = threeway_merge.merge("a\n", "b\n", "c\n", favor="union")
+assert (result.content, result.conflicts) == ("b\nc\n", 0)
+assert [hunk.kind for hunk in result.hunks] == ["clean"]
+
+result = threeway_merge.merge("x\n", "Heading\n=======\ntext\n", "other\n")
+[conflict] = result.hunks
+assert (conflict.ours, conflict.theirs) == ("Heading\n=======\ntext\n", "other\n")
+"#
+    ));
+}
+
+#[test]
+fn errors_raise_typed_exceptions() {
+    run(c_str!(
+        r#"
+import threeway_merge
+
+def raises(exception, **options):
+    try:
+        threeway_merge.merge(options.pop("base", "a\n"), "b\n", "c\n", **options)
+    except exception as err:
+        assert isinstance(err, threeway_merge.MergeError)
+        return str(err)
+    raise AssertionError(f"{exception.__name__} not raised for {options}")
+
+assert "bogus" in raises(threeway_merge.InvalidInputError, style="bogus")
+raises(threeway_merge.InvalidInputError, eol="cr")
+raises(threeway_merge.BinaryError, base="a\0")
+assert "limit" in raises(threeway_merge.LimitExceededError, max_lines=0, base="a\nb\n")
+assert issubclass(threeway_merge.MergeError, Exception)
+
+try:
+    threeway_merge.merge("a\n", "b\n", "c\n", colour="red")
+except TypeError:
+    pass
+else:
+    raise AssertionError("unknown option accepted")
+"#
+    ));
+}
@@ -0,0 +1,191 @@
+use threeway_merge::{
+    ConflictMarkers, MergeError, MergeOptions, MergeStyle, RenderOptions, merge_strings,
+    render_ansi, render_html,
+};
+
+fn numbered(lines: usize) -> String {
+    (1..=lines).map(|i| format!("line {}\n", i)).collect()
+}
+
+fn diff3_options() -> MergeOptions {
+    MergeOptions {
+        style: MergeStyle::Diff3,
+        ours_label: Some("mine".to_string()),
+        theirs_label: Some("yours".to_string()),
+        ..MergeOptions::default()
+    }
+}
+
+#[test]
+fn ansi_shows_columns_and_collapses_clean_lines() {
+    let base = numbered(20);
+    let ours = base.replace("line 10\n", "let x = compute(1, 2);\n");
+    let theirs = base.replace("line 10\n", "let x = compute(1, 3);\n");
+    let options = diff3_options();
+    let result = merge_strings(&base, &ours, &theirs, &options).unwrap();
+
+    let render = RenderOptions {
+        context: 2,
+        width: 24,
+    };
+    let view = render_ansi(&result, &options, &render).unwrap();
+    let plain = strip_ansi(&view);
+    assert_eq!(
+        plain,
+        "      ┊ ⋯ 7 unchanged lines
+    8 │ line 8
+    9 │ line 9
+   10 ✖ conflict 1 of 1
+      │ mine                     │ base                     │ yours
+      │ let x = compute(1, 2);   │ line 10                  │ let x = compute(1, 3);
+   17 │ line 11
+   18 │ line 12
+      ┊ ⋯ 8 unchanged lines
+"
+    );
+    // Only the differing words are highlighted
+    assert!(view.contains("\x1b[32mlet x = compute(1, \x1b[7m2\x1b[27m);\x1b[0m"));
+    assert!(view.contains("\x1b[36mlet x = compute(1, \x1b[7m3\x1b[27m);\x1b[0m"));
+}
+
+#[test]
+fn ansi_cuts_off_long_lines() {
+    let options = MergeOptions::default();
+    let result = merge_strings("a\n", "a very long line of text\n", "b\n", &options).unwrap();
+    let render = RenderOptions {
+        context: 0,
+        width: 10,
+    };
+    let plain = strip_ansi(&render_ansi(&result, &options, &render).unwrap());
+    assert!(plain.contains("│ a very lo… │ b\n"), "{}", plain);
+}
+
+#[test]
+fn html_escapes_and_marks_changed_words() {
+    let options = MergeOptions::default();
+    let result = merge_strings(
+        "x\n<p>old</p>\ny\n",
+        "x\n<p>ours & more</p>\ny\n",
+        "x\n<p>theirs</p>\ny\n",
+        &options,
+    )
+    .unwrap();
+    let html = render_html(&result, &options, &RenderOptions::default()).unwrap();
+    assert_eq!(
+        html,
+        "<table class=\"merge\">
+<tbody class=\"clean\">
+<tr><td class=\"line\">1</td><td colspan=\"2\">x</td></tr>
+</tbody>
+<tbody class=\"conflict\" id=\"conflict-1\">
+<tr><td class=\"line\">2</td><th class=\"ours\">ours</th><th class=\"theirs\">theirs</th></tr>
+<tr><td class=\"line\"></td><td class=\"ours\">&lt;p&gt;<mark>ours &amp; more</mark>&lt;/p&gt;</td><td class=\"theirs\">&lt;p&gt;<mark>theirs</mark>&lt;/p&gt;</td></tr>
+</tbody>
+<tbody class=\"clean\">
+<tr><td class=\"line\">7</td><td colspan=\"2\">y</td></tr>
+</tbody>
+</table>
+"
+    );
+}
+
+#[test]
+fn html_base_column_marks_what_the_sides_changed() {
+    let options = diff3_options();
+    let result = merge_strings(
+        "a\nkeep drop\nb\n",
+        "a\nkeep one\nb\n",
+        "a\nkeep two\nb\n",
+        &options,
+    )
+    .unwrap();
+    let html = render_html(&result, &options, &RenderOptions::default()).unwrap();
+    assert!(
+        html.contains("<td class=\"base\">keep <mark>drop</mark></td>"),
+        "{}",
+        html
+    );
+    assert!(html.contains("<td colspan=\"3\">a</td>"));
+}
+
+#[test]
+fn clean_results_collapse_entirely() {
+    let options = MergeOptions::default();
+    let base = numbered(5);
+    let result = merge_strings(&base, &base, &base, &options).unwrap();
+    let html = render_html(&result, &options, &RenderOptions::default()).unwrap();
+    assert_eq!(
+        html,
+        "<table class=\"merge\">
+<tbody class=\"clean\">
+<tr class=\"collapsed\"><td class=\"line\"></td><td colspan=\"1\">5 unchanged lines</td></tr>
+</tbody>
+</table>
+"
+    );
+    let view = render_ansi(&result, &options, &RenderOptions::default()).unwrap();
+    assert_eq!(strip_ansi(&view), "      ┊ ⋯ 5 unchanged lines\n");
+}
+
+#[test]
+fn finds_conflicts_with_marker_templates() {
+    let options = MergeOptions {
+        markers: ConflictMarkers::with_prefix("-- "),
+        ..diff3_options()
+    };
+    let result = merge_strings("a\nb\n", "a\nx\n", "a\ny\n", &options).unwrap();
+    assert!(result.content.contains("-- <<<<<<< mine\n"));
+    let plain = strip_ansi(&render_ansi(&result, &options, &RenderOptions::default()).unwrap());
+    assert!(plain.contains("conflict 1 of 1"), "{}", plain);
+    assert!(!plain.contains("<<<<<<<"), "{}", plain);
+}
+
+/// Removes the SGR escape sequences the terminal view uses.
+fn strip_ansi(text: &str) -> String {
+    let mut out = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|&c| c == 'm');
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[test]
+fn marker_like_lines_stay_in_their_side() {
+    // A setext heading underline looks like a separator marker
+    let options = MergeOptions::default();
+    let result = merge_strings("x\n", "Heading\n=======\ntext\n", "other\n", &options).unwrap();
+    let html = render_html(&result, &options, &RenderOptions::default()).unwrap();
+    assert_eq!(
+        html,
+        "<table class=\"merge\">
+<tbody class=\"conflict\" id=\"conflict-1\">
+<tr><td class=\"line\">1</td><th class=\"ours\">ours</th><th class=\"theirs\">theirs</th></tr>
+<tr><td class=\"line\"></td><td class=\"ours\"><mark>Heading</mark></td><td class=\"theirs\"><mark>other</mark></td></tr>
+<tr><td class=\"line\"></td><td class=\"ours\"><mark>=======</mark></td><td class=\"theirs\"></td></tr>
+<tr><td class=\"line\"></td><td class=\"ours\"><mark>text</mark></td><td class=\"theirs\"></td></tr>
+</tbody>
+</table>
+"
+    );
+}
+
+#[test]
+fn rejects_a_zero_width() {
+    let options = MergeOptions::default();
+    let result = merge_strings("a\n", "b\n", "c\n", &options).unwrap();
+    let render = RenderOptions {
+        width: 0,
+        ..RenderOptions::default()
+    };
+    assert!(matches!(
+        render_ansi(&result, &options, &render),
+        Err(MergeError::InvalidInput(_))
+    ));
+    // The HTML view has no columns to size
+    assert!(render_html(&result, &options, &render).is_ok());
+}
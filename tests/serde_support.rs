@@ -0,0 +1,133 @@
+#![cfg(feature = "serde")]
+
+use threeway_merge::{
+    BinaryPolicy, DiffAlgorithm, EolPolicy, MergeError, MergeFavor, MergeLevel, MergeOptions,
+    MergeStyle, merge_strings,
+};
+
+#[test]
+fn enums_use_git_option_spellings() {
+    assert_eq!(
+        serde_json::to_string(&DiffAlgorithm::Histogram).unwrap(),
+        "\"histogram\""
+    );
+    assert_eq!(
+        serde_json::to_string(&MergeStyle::ZealousDiff3).unwrap(),
+        "\"zdiff3\""
+    );
+    assert_eq!(
+        serde_json::to_string(&MergeStyle::Normal).unwrap(),
+        "\"merge\""
+    );
+    assert_eq!(
+        serde_json::to_string(&MergeFavor::Union).unwrap(),
+        "\"union\""
+    );
+    assert_eq!(
+        serde_json::to_string(&MergeFavor::UnionDedup).unwrap(),
+        "\"union-dedup\""
+    );
+    assert_eq!(
+        serde_json::to_string(&MergeLevel::ZealousAlnum).unwrap(),
+        "\"zealous-alnum\""
+    );
+}
+
+#[test]
+fn options_deserialize_with_defaults_for_missing_fields() {
+    let options: MergeOptions =
+        serde_json::from_str(r#"{"algorithm": "patience", "style": "diff3", "favor": "ours"}"#)
+            .unwrap();
+
+    assert_eq!(options.algorithm, DiffAlgorithm::Patience);
+    assert_eq!(options.style, MergeStyle::Diff3);
+    assert_eq!(options.favor, Some(MergeFavor::Ours));
+    assert_eq!(options.marker_size, 7);
+    assert_eq!(options.level, MergeLevel::ZealousAlnum);
+    assert_eq!(options.ours_label, None);
+}
+
+#[test]
+fn options_round_trip() {
+    let options = MergeOptions {
+        algorithm: DiffAlgorithm::Minimal,
+        style: MergeStyle::ZealousDiff3,
+        favor: Some(MergeFavor::Theirs),
+        marker_size: 10,
+        ours_label: Some("mine".to_string()),
+        ..MergeOptions::default()
+    };
+
+    let json = serde_json::to_string(&options).unwrap();
+    let decoded: MergeOptions = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(decoded.algorithm, options.algorithm);
+    assert_eq!(decoded.style, options.style);
+    assert_eq!(decoded.favor, options.favor);
+    assert_eq!(decoded.marker_size, options.marker_size);
+    assert_eq!(decoded.ours_label, options.ours_label);
+}
+
+#[test]
+fn result_and_error_serialize() {
+    let result = merge_strings("a\n", "b\n", "c\n", &MergeOptions::default()).unwrap();
+    let value = serde_json::to_value(&result).unwrap();
+    assert_eq!(value["conflicts"], 1);
+    assert_eq!(value["content"], result.content.as_str());
+
+    let err = MergeError::InvalidInput("Invalid base label".to_string());
+    let json = serde_json::to_string(&err).unwrap();
+    assert_eq!(json, r#"{"invalid-input":"Invalid base label"}"#);
+    let decoded: MergeError = serde_json::from_str(&json).unwrap();
+    assert!(matches!(decoded, MergeError::InvalidInput(msg) if msg == "Invalid base label"));
+}
+
+#[test]
+fn from_str_accepts_the_serde_spellings() {
+    fn round_trip<T>(values: &[T])
+    where
+        T: serde::Serialize + std::str::FromStr<Err = MergeError> + PartialEq + std::fmt::Debug,
+    {
+        for value in values {
+            let spelling = serde_json::to_value(value).unwrap();
+            assert_eq!(&spelling.as_str().unwrap().parse::<T>().unwrap(), value);
+        }
+    }
+
+    round_trip(&[
+        DiffAlgorithm::Myers,
+        DiffAlgorithm::Minimal,
+        DiffAlgorithm::Patience,
+        DiffAlgorithm::Histogram,
+    ]);
+    round_trip(&[
+        MergeStyle::Normal,
+        MergeStyle::Diff3,
+        MergeStyle::ZealousDiff3,
+    ]);
+    round_trip(&[
+        MergeFavor::Ours,
+        MergeFavor::Theirs,
+        MergeFavor::Union,
+        MergeFavor::UnionDedup,
+        MergeFavor::UnionSorted,
+    ]);
+    round_trip(&[
+        MergeLevel::Minimal,
+        MergeLevel::Eager,
+        MergeLevel::Zealous,
+        MergeLevel::ZealousAlnum,
+    ]);
+    round_trip(&[
+        BinaryPolicy::Error,
+        BinaryPolicy::TakeOurs,
+        BinaryPolicy::TakeTheirs,
+        BinaryPolicy::Conflict,
+    ]);
+    round_trip(&[
+        EolPolicy::Preserve,
+        EolPolicy::Lf,
+        EolPolicy::Crlf,
+        EolPolicy::MatchOurs,
+    ]);
+}
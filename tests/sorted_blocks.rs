@@ -0,0 +1,83 @@
+use threeway_merge::{MergeOptions, MergeStyle, merge_strings};
+
+fn sorted() -> MergeOptions {
+    MergeOptions {
+        sorted_blocks: true,
+        ..MergeOptions::default()
+    }
+}
+
+const BASE: &str = "use std::fmt;\nuse std::io;\n\nfn main() {}\n";
+
+#[test]
+fn both_sides_adding_imports_merge_sorted() {
+    let ours = "use std::fmt;\nuse std::fs;\nuse std::io;\n\nfn main() {}\n";
+    let theirs = "use std::fmt;\nuse std::hash;\nuse std::io;\n\nfn main() {}\n";
+
+    let plain = merge_strings(BASE, ours, theirs, &MergeOptions::default()).unwrap();
+    assert_eq!(plain.conflicts, 1);
+
+    let result = merge_strings(BASE, ours, theirs, &sorted()).unwrap();
+    assert!(result.is_clean_merge());
+    assert_eq!(
+        result.content,
+        "use std::fmt;\nuse std::fs;\nuse std::hash;\nuse std::io;\n\nfn main() {}\n"
+    );
+}
+
+#[test]
+fn deletions_from_either_side_are_kept() {
+    let base = "a\nb\nc\nd\n";
+    let ours = "a\nbb\nc\nd\n";
+    let theirs = "a\nb\nba\nd\n";
+
+    // ours replaced b, theirs deleted c: neither comes back
+    let result = merge_strings(base, ours, theirs, &sorted()).unwrap();
+    assert!(result.is_clean_merge());
+    assert_eq!(result.content, "a\nba\nbb\nd\n");
+}
+
+#[test]
+fn unsorted_hunks_still_conflict() {
+    let base = "alpha\nbeta\n\nx\n\nfoo\n";
+    let ours = "alpha\ngamma\nbeta\n\nx\n\nqux\nfoo\n";
+    let theirs = "alpha\ndelta\nbeta\n\nx\n\nbar\nfoo\n";
+
+    // Neither insertion sorts between its neighbours
+    let result = merge_strings(base, ours, theirs, &sorted()).unwrap();
+    assert_eq!(result.conflicts, 2);
+
+    // A blank line ends a block, so appending to the first one resolves
+    let ours = "alpha\nbeta\ncharlie\n\nx\n\nqux\nfoo\n";
+    let theirs = "alpha\nbeta\nbravo\n\nx\n\nbar\nfoo\n";
+    let result = merge_strings(base, ours, theirs, &sorted()).unwrap();
+    assert_eq!(result.conflicts, 1);
+    assert!(
+        result
+            .content
+            .starts_with("alpha\nbeta\nbravo\ncharlie\n\nx\n")
+    );
+}
+
+#[test]
+fn changing_the_same_entry_still_conflicts() {
+    let base = "[deps]\nbar = 1\nfoo = 1\n";
+    let ours = "[deps]\nbar = 1\nfoo = 2\n";
+    let theirs = "[deps]\nbar = 1\nfoo = 3\n";
+
+    let result = merge_strings(base, ours, theirs, &sorted()).unwrap();
+    assert_eq!(result.conflicts, 1);
+}
+
+#[test]
+fn applies_to_diff3_style() {
+    let ours = "use std::fmt;\nuse std::fs;\nuse std::io;\n\nfn main() {}\n";
+    let theirs = "use std::fmt;\nuse std::hash;\nuse std::io;\n\nfn main() {}\n";
+    let options = MergeOptions {
+        style: MergeStyle::Diff3,
+        ..sorted()
+    };
+
+    let result = merge_strings(BASE, ours, theirs, &options).unwrap();
+    assert!(result.is_clean_merge());
+}
@@ -0,0 +1,169 @@
+use std::io::{self, Write};
+use std::panic;
+use threeway_merge::{
+    BinaryPolicy, EolPolicy, MergeError, MergeOptions, MergeStyle, merge_into, merge_into_vec,
+    merge_strings,
+};
+
+const BASE: &str = "a\nb\nc\nd\ne\n";
+const OURS: &str = "a\nB\nc\nd\nx\n";
+const THEIRS: &str = "a\nb\nc\nD\ny\n";
+
+fn diff3() -> MergeOptions {
+    MergeOptions {
+        style: MergeStyle::Diff3,
+        ours_label: Some("ours".to_string()),
+        theirs_label: Some("theirs".to_string()),
+        marker_size: 100,
+        ..MergeOptions::default()
+    }
+}
+
+/// Accepts `limit` bytes, then fails.
+struct FailingWriter {
+    written: Vec<u8>,
+    limit: usize,
+}
+
+impl Write for FailingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written.len() + buf.len() > self.limit {
+            return Err(io::Error::other("disk full"));
+        }
+        self.written.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn streams_the_same_result_as_merge_strings() {
+    for options in [MergeOptions::default(), diff3()] {
+        let expected = merge_strings(BASE, OURS, THEIRS, &options).unwrap();
+
+        let mut out = io::BufWriter::new(Vec::new());
+        let summary = merge_into(
+            BASE.as_bytes(),
+            OURS.as_bytes(),
+            THEIRS.as_bytes(),
+            &options,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(out.into_inner().unwrap(), expected.content.as_bytes());
+        assert_eq!(summary.conflicts, expected.conflicts);
+        assert_eq!(summary.line_endings, expected.line_endings);
+        assert!(summary.has_conflicts());
+    }
+}
+
+#[test]
+fn appends_to_a_reused_vec() {
+    let options = MergeOptions::default();
+    let mut out = b"header\n".to_vec();
+
+    let summary =
+        merge_into_vec(b"a\nb\nc\n", b"A\nb\nc\n", b"a\nb\nC\n", &options, &mut out).unwrap();
+    assert!(summary.is_clean_merge());
+    assert_eq!(out, b"header\nA\nb\nC\n");
+
+    // Fast paths append too
+    merge_into_vec(b"a\n", b"a\n", b"c\n", &options, &mut out).unwrap();
+    assert_eq!(out, b"header\nA\nb\nC\nc\n");
+}
+
+#[test]
+fn failed_merge_leaves_vec_untouched() {
+    let mut out = b"kept".to_vec();
+    let err =
+        merge_into_vec(b"a\n", b"\0b\n", b"c\n", &MergeOptions::default(), &mut out).unwrap_err();
+    assert!(matches!(err, MergeError::Binary(_)));
+    assert_eq!(out, b"kept");
+
+    let options = MergeOptions {
+        binary: BinaryPolicy::Conflict,
+        ..MergeOptions::default()
+    };
+    let summary = merge_into_vec(b"a\n", b"\0b\n", b"c\n", &options, &mut out).unwrap();
+    assert_eq!(summary.binary, Some(BinaryPolicy::Conflict));
+    assert_eq!(out, b"kept\0b\n");
+}
+
+#[test]
+fn crlf_output_is_converted_while_streaming() {
+    let options = MergeOptions {
+        eol: EolPolicy::Crlf,
+        ..diff3()
+    };
+    let expected = merge_strings(BASE, OURS, THEIRS, &options).unwrap();
+    assert!(expected.content.contains("\r\n"));
+
+    let mut streamed = Vec::new();
+    merge_into(
+        BASE.as_bytes(),
+        OURS.as_bytes(),
+        THEIRS.as_bytes(),
+        &options,
+        &mut streamed,
+    )
+    .unwrap();
+    let mut buffered = Vec::new();
+    merge_into_vec(
+        BASE.as_bytes(),
+        OURS.as_bytes(),
+        THEIRS.as_bytes(),
+        &options,
+        &mut buffered,
+    )
+    .unwrap();
+    assert_eq!(streamed, expected.content.as_bytes());
+    assert_eq!(buffered, expected.content.as_bytes());
+}
+
+#[test]
+fn writer_errors_are_reported() {
+    let mut writer = FailingWriter {
+        written: Vec::new(),
+        limit: 8,
+    };
+    let err = merge_into(
+        BASE.as_bytes(),
+        OURS.as_bytes(),
+        THEIRS.as_bytes(),
+        &MergeOptions::default(),
+        &mut writer,
+    )
+    .unwrap_err();
+    assert!(matches!(err, MergeError::Io(msg) if msg.contains("disk full")));
+    assert!(writer.written.len() <= 8);
+}
+
+#[test]
+fn writer_panics_propagate() {
+    struct PanickingWriter;
+
+    impl Write for PanickingWriter {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            panic!("writer panicked");
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let result = panic::catch_unwind(|| {
+        merge_into(
+            BASE.as_bytes(),
+            OURS.as_bytes(),
+            THEIRS.as_bytes(),
+            &MergeOptions::default(),
+            &mut PanickingWriter,
+        )
+    });
+    let payload = result.unwrap_err();
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"writer panicked"));
+}
@@ -0,0 +1,137 @@
+#![cfg(feature = "toml")]
+
+use threeway_merge::{MergeError, merge_strings, merge_toml};
+
+const BASE: &str = r#"# Package manifest
+[package]
+name = "app"
+version = "0.1.0" # bumped on release
+
+[dependencies]
+libc = "0.2"
+"#;
+
+#[test]
+fn adjacent_key_edits_merge_cleanly() {
+    let ours = BASE.replace("libc = \"0.2\"\n", "libc = \"0.2\"\nserde = \"1\"\n");
+    let theirs = BASE.replace("libc = \"0.2\"\n", "libc = \"0.2\"\nthiserror = \"2\"\n");
+
+    // Both sides append to the same table: a line conflict, but different keys
+    assert!(
+        merge_strings(BASE, &ours, &theirs, &Default::default())
+            .unwrap()
+            .has_conflicts()
+    );
+
+    let result = merge_toml(BASE, &ours, &theirs).unwrap();
+    assert!(result.is_clean_merge());
+    assert_eq!(
+        result.content,
+        BASE.replace(
+            "libc = \"0.2\"\n",
+            "libc = \"0.2\"\nserde = \"1\"\nthiserror = \"2\"\n"
+        )
+    );
+}
+
+#[test]
+fn preserves_comments_and_formatting_of_ours() {
+    let ours = BASE.replace("version = \"0.1.0\"", "version   =   \"0.2.0\"");
+    let theirs = format!("{}\n# Optional features\n[features]\ndefault = []\n", BASE)
+        .replace("libc = \"0.2\"", "libc = { version = \"0.2\" }");
+
+    let result = merge_toml(BASE, &ours, &theirs).unwrap();
+    assert!(result.is_clean_merge());
+    assert_eq!(
+        result.content,
+        r#"# Package manifest
+[package]
+name = "app"
+version   =   "0.2.0" # bumped on release
+
+[dependencies]
+libc = { version = "0.2" }
+
+# Optional features
+[features]
+default = []
+"#
+    );
+}
+
+#[test]
+fn removals_apply() {
+    let ours = BASE.replace("name = \"app\"\n", "");
+    let theirs = BASE.replace("\n[dependencies]\nlibc = \"0.2\"\n", "");
+
+    let result = merge_toml(BASE, &ours, &theirs).unwrap();
+    assert!(result.is_clean_merge());
+    assert_eq!(
+        result.content,
+        "# Package manifest\n[package]\nversion = \"0.1.0\" # bumped on release\n"
+    );
+}
+
+#[test]
+fn conflicts_are_reported_per_key_path() {
+    let ours = BASE.replace("libc = \"0.2\"", "libc = \"0.3\"");
+    let theirs = BASE.replace("libc = \"0.2\"", "libc = { version = \"0.4\" }");
+
+    let result = merge_toml(BASE, &ours, &theirs).unwrap();
+    assert_eq!(result.conflicts.len(), 1);
+    let conflict = &result.conflicts[0];
+    assert_eq!(conflict.path, ["dependencies", "libc"]);
+    assert_eq!(conflict.base.as_deref(), Some("\"0.2\""));
+    assert_eq!(conflict.ours.as_deref(), Some("\"0.3\""));
+    assert_eq!(conflict.theirs.as_deref(), Some("{ version = \"0.4\" }"));
+    assert_eq!(result.content, ours);
+
+    // delete/modify
+    let theirs = BASE.replace("libc = \"0.2\"\n", "");
+    let result = merge_toml(BASE, &ours, &theirs).unwrap();
+    assert_eq!(result.conflicts.len(), 1);
+    assert_eq!(result.conflicts[0].theirs, None);
+}
+
+#[test]
+fn multiline_strings_use_line_merge() {
+    let base = "text = \"\"\"\none\ntwo\nthree\nfour\n\"\"\"\n";
+    let ours = base.replace("one", "ONE");
+    let theirs = base.replace("four", "FOUR");
+
+    let result = merge_toml(base, &ours, &theirs).unwrap();
+    assert!(result.is_clean_merge());
+    let merged: toml_edit::DocumentMut = result.content.parse().unwrap();
+    assert_eq!(merged["text"].as_str(), Some("ONE\ntwo\nthree\nFOUR\n"));
+
+    let theirs = base.replace("one", "uno");
+    let result = merge_toml(base, &ours, &theirs).unwrap();
+    assert_eq!(result.conflicts.len(), 1);
+    assert_eq!(result.conflicts[0].path, ["text"]);
+}
+
+#[test]
+fn arrays_of_tables_merge_by_index() {
+    let base = "[[bin]]\nname = \"a\"\n\n[[bin]]\nname = \"b\"\n";
+    let ours = base.replace("name = \"a\"", "name = \"a\"\npath = \"src/a.rs\"");
+    let theirs = base.replace("name = \"b\"", "name = \"b\"\ntest = false");
+
+    let result = merge_toml(base, &ours, &theirs).unwrap();
+    assert!(result.is_clean_merge());
+    assert_eq!(
+        result.content,
+        "[[bin]]\nname = \"a\"\npath = \"src/a.rs\"\n\n[[bin]]\nname = \"b\"\ntest = false\n"
+    );
+
+    let ours = format!("{}\n[[bin]]\nname = \"c\"\n", base);
+    let theirs = base.replace("name = \"b\"", "name = \"d\"");
+    let result = merge_toml(base, &ours, &theirs).unwrap();
+    assert_eq!(result.conflicts.len(), 1);
+    assert_eq!(result.conflicts[0].path, ["bin"]);
+}
+
+#[test]
+fn rejects_invalid_toml() {
+    let err = merge_toml(BASE, BASE, "[package").unwrap_err();
+    assert!(matches!(err, MergeError::InvalidInput(msg) if msg.starts_with("theirs")));
+}
@@ -0,0 +1,89 @@
+use threeway_merge::{MergeFavor, MergeLevel, MergeOptions, merge_strings};
+
+fn merge(base: &str, ours: &str, theirs: &str, favor: MergeFavor) -> String {
+    let options = MergeOptions {
+        favor: Some(favor),
+        ..MergeOptions::default()
+    };
+    let result = merge_strings(base, ours, theirs, &options).unwrap();
+    assert!(result.is_clean_merge());
+    result.content
+}
+
+#[test]
+fn union_keeps_duplicates() {
+    let base = "## Unreleased\n\n## 0.1.0\n";
+    let ours = "## Unreleased\n- fix parser\n- bump deps\n\n## 0.1.0\n";
+    let theirs = "## Unreleased\n- bump deps\n- add docs\n\n## 0.1.0\n";
+
+    assert_eq!(
+        merge(base, ours, theirs, MergeFavor::Union),
+        "## Unreleased\n- fix parser\n- bump deps\n- bump deps\n- add docs\n\n## 0.1.0\n"
+    );
+    assert_eq!(
+        merge(base, ours, theirs, MergeFavor::UnionDedup),
+        "## Unreleased\n- fix parser\n- bump deps\n- add docs\n\n## 0.1.0\n"
+    );
+}
+
+#[test]
+fn dedup_only_drops_lines_shared_with_ours() {
+    let options = MergeOptions {
+        favor: Some(MergeFavor::UnionDedup),
+        level: MergeLevel::Eager,
+        ..MergeOptions::default()
+    };
+    let result = merge_strings("a\n", "a\nx\n", "a\ny\ny\nx\n", &options).unwrap();
+
+    // Duplicates within one side are kept; lines ours has are dropped
+    assert_eq!(result.content, "a\nx\ny\ny\n");
+}
+
+#[test]
+fn identical_lines_without_final_newline() {
+    let base = "a\n";
+    let ours = "a\nb\nc";
+    let theirs = "a\nc\n";
+
+    assert_eq!(merge(base, ours, theirs, MergeFavor::UnionDedup), "a\nb\nc");
+    assert_eq!(
+        merge(base, "a\nb", "a\nc", MergeFavor::UnionDedup),
+        "a\nb\nc"
+    );
+}
+
+#[test]
+fn sorted_union_interleaves_both_sides() {
+    let base = "*.log\n/target\n";
+    let ours = "*.log\n*.swp\n.env\n/target\n";
+    let theirs = "*.log\n*.tmp\n.env\n/dist\n/target\n";
+
+    assert_eq!(
+        merge(base, ours, theirs, MergeFavor::UnionSorted),
+        "*.log\n*.swp\n*.tmp\n.env\n/dist\n/target\n"
+    );
+}
+
+#[test]
+fn crlf_lines_are_deduplicated() {
+    let base = "a\r\n";
+    let ours = "a\r\nb\r\nc\r\n";
+    let theirs = "a\r\nc\r\nd\r\n";
+
+    assert_eq!(
+        merge(base, ours, theirs, MergeFavor::UnionSorted),
+        "a\r\nb\r\nc\r\nd\r\n"
+    );
+}
+
+#[test]
+fn parses_favor_names() {
+    assert_eq!(
+        "union-dedup".parse::<MergeFavor>().unwrap(),
+        MergeFavor::UnionDedup
+    );
+    assert_eq!(
+        "union-sorted".parse::<MergeFavor>().unwrap(),
+        MergeFavor::UnionSorted
+    );
+}
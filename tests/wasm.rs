@@ -0,0 +1,86 @@
+//! Run under Node with `wasm-pack test --node --features wasm`.
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+use serde_json::{Value, json};
+use threeway_merge::merge_js;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+fn merge(base: &str, ours: &str, theirs: &str, options: Value) -> Result<Value, String> {
+    let options = js_sys::JSON::parse(&options.to_string()).unwrap();
+    match merge_js(base, ours, theirs, options) {
+        Ok(result) => {
+            let json = js_sys::JSON::stringify(&result).unwrap();
+            Ok(serde_json::from_str(&String::from(json)).unwrap())
+        }
+        Err(err) => Err(js_sys::Error::from(JsValue::from(err)).message().into()),
+    }
+}
+
+#[wasm_bindgen_test]
+fn returns_content_conflicts_and_hunks() {
+    let result = merge(
+        "a\nb\nc\n",
+        "a\nB\nc\n",
+        "a\nb2\nc\n",
+        json!({ "style": "diff3", "algorithm": "histogram" }),
+    )
+    .unwrap();
+
+    assert_eq!(
+        result["content"],
+        "a\n<<<<<<<\nB\n|||||||\nb\n=======\nb2\n>>>>>>>\nc\n"
+    );
+    assert_eq!(result["conflicts"], 1);
+    assert_eq!(
+        result["hunks"],
+        json!([
+            { "kind": "clean", "line": 1, "content": "a\n" },
+            { "kind": "conflict", "line": 2, "ours": "B\n", "base": "b\n", "theirs": "b2\n" },
+            { "kind": "clean", "line": 9, "content": "c\n" },
+        ])
+    );
+}
+
+#[wasm_bindgen_test]
+fn missing_options_take_the_defaults() {
+    let result = merge("a\n", "a\nb\n", "a\n", json!(null)).unwrap();
+    assert_eq!(result["content"], "a\nb\n");
+    assert_eq!(result["conflicts"], 0);
+    assert_eq!(
+        result["hunks"],
+        json!([{ "kind": "clean", "line": 1, "content": "a\nb\n" }])
+    );
+
+    let result = merge("a\n", "b\n", "c\n", json!({ "marker_size": 3 })).unwrap();
+    assert_eq!(result["content"], "<<<\nb\n===\nc\n>>>\n");
+    assert_eq!(result["hunks"][0]["base"], Value::Null);
+}
+
+#[wasm_bindgen_test]
+fn marker_like_lines_stay_in_their_side() {
+    let result = merge("x\n", "Heading\n=======\ntext\n", "other\n", json!({})).unwrap();
+    assert_eq!(
+        result["hunks"],
+        json!([{
+            "kind": "conflict",
+            "line": 1,
+            "ours": "Heading\n=======\ntext\n",
+            "base": null,
+            "theirs": "other\n",
+        }])
+    );
+}
+
+#[wasm_bindgen_test]
+fn errors_are_thrown() {
+    let err = merge("a\n", "b\n", "c\n", json!({ "style": "bogus" })).unwrap_err();
+    assert!(err.contains("bogus"), "{}", err);
+
+    let err = merge("a\0", "b\n", "c\n", json!({})).unwrap_err();
+    assert!(err.starts_with("Cannot merge binary content"), "{}", err);
+
+    let timeout = json!({ "limits": { "timeout": { "secs": 1, "nanos": 0 } } });
+    let err = merge("a\n", "b\n", "c\n", timeout).unwrap_err();
+    assert!(err.contains("limits.timeout"), "{}", err);
+}
@@ -0,0 +1,90 @@
+//! These exercise the allocator hooks of the C sources.
+#![cfg(not(pure_rust))]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
+use std::thread;
+use threeway_merge::{DiffAlgorithm, MergeOptions, merge_strings, with_xdiff_allocator};
+
+/// Counts what goes through it, like an arena would.
+#[derive(Default)]
+struct Counting {
+    allocs: AtomicUsize,
+    frees: AtomicUsize,
+    live_bytes: AtomicIsize,
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.allocs.fetch_add(1, Ordering::Relaxed);
+        self.live_bytes
+            .fetch_add(layout.size() as isize, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.frees.fetch_add(1, Ordering::Relaxed);
+        self.live_bytes
+            .fetch_sub(layout.size() as isize, Ordering::Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+fn conflicting_merge(options: &MergeOptions) {
+    let base: String = (0..200).map(|i| format!("line {}\n", i)).collect();
+    let ours = base
+        .replace("line 50\n", "ours\n")
+        .replace("line 150\n", "x\n");
+    let theirs = base.replace("line 50\n", "theirs\n");
+    let result = merge_strings(&base, &ours, &theirs, options).unwrap();
+    assert_eq!(result.conflicts, 1);
+}
+
+#[test]
+fn xdiff_allocates_from_the_installed_allocator() {
+    for algorithm in [
+        DiffAlgorithm::Myers,
+        DiffAlgorithm::Patience,
+        DiffAlgorithm::Histogram,
+    ] {
+        let arena = Counting::default();
+        let options = MergeOptions {
+            algorithm,
+            ..MergeOptions::default()
+        };
+        with_xdiff_allocator(&arena, || conflicting_merge(&options));
+
+        let allocs = arena.allocs.load(Ordering::Relaxed);
+        assert!(allocs > 0, "{:?}", algorithm);
+        // Everything is returned before the merge does
+        assert_eq!(arena.frees.load(Ordering::Relaxed), allocs);
+        assert_eq!(arena.live_bytes.load(Ordering::Relaxed), 0);
+    }
+}
+
+#[test]
+fn allocator_is_scoped_to_the_calling_thread() {
+    let outer = Counting::default();
+    let inner = Counting::default();
+
+    with_xdiff_allocator(&outer, || {
+        with_xdiff_allocator(&inner, || conflicting_merge(&MergeOptions::default()));
+        let inner_allocs = inner.allocs.load(Ordering::Relaxed);
+        assert!(inner_allocs > 0);
+        assert_eq!(outer.allocs.load(Ordering::Relaxed), 0);
+
+        // Restored after the inner scope, and not seen by other threads
+        thread::spawn(|| conflicting_merge(&MergeOptions::default()))
+            .join()
+            .unwrap();
+        assert_eq!(outer.allocs.load(Ordering::Relaxed), 0);
+        conflicting_merge(&MergeOptions::default());
+        assert_eq!(outer.allocs.load(Ordering::Relaxed), inner_allocs);
+    });
+
+    conflicting_merge(&MergeOptions::default());
+    assert_eq!(
+        outer.allocs.load(Ordering::Relaxed),
+        inner.allocs.load(Ordering::Relaxed)
+    );
+}
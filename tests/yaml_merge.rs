@@ -0,0 +1,129 @@
+#![cfg(feature = "yaml")]
+
+use threeway_merge::{MergeError, merge_strings, merge_yaml};
+
+const BASE: &str = "# web deployment
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: web
+spec:
+  replicas: 1 # scaled by the autoscaler
+  template:
+    spec:
+      containers:
+        - name: web
+          image: web:1.0
+        - name: sidecar
+          image: proxy:1.0
+";
+
+#[test]
+fn keeps_comments_when_the_line_merge_agrees() {
+    let ours = BASE.replace("replicas: 1", "replicas: 3");
+    let theirs = BASE.replace("image: proxy:1.0", "image: proxy:2.0");
+
+    let result = merge_yaml(BASE, &ours, &theirs).unwrap();
+    assert!(result.is_clean_merge());
+    assert_eq!(
+        result.content,
+        ours.replace("image: proxy:1.0", "image: proxy:2.0")
+    );
+}
+
+#[test]
+fn adjacent_key_edits_merge_cleanly() {
+    let ours = BASE.replace(
+        "name: web\n          image: web:1.0",
+        "name: web-app\n          image: web:1.0",
+    );
+    let theirs = BASE.replace("image: web:1.0", "image: web:2.0");
+    assert!(
+        merge_strings(BASE, &ours, &theirs, &Default::default())
+            .unwrap()
+            .has_conflicts()
+    );
+
+    let result = merge_yaml(BASE, &ours, &theirs).unwrap();
+    assert!(result.is_clean_merge());
+    let expected = BASE
+        .replace(
+            "name: web\n          image: web:1.0",
+            "name: web-app\n          image: web:2.0",
+        )
+        .replace("# web deployment\n", "")
+        .replace("image: web:2.0", "image: \"web:2.0\"")
+        .replace("image: proxy:1.0", "image: \"proxy:1.0\"")
+        .replace(" # scaled by the autoscaler", "");
+    // The line merge conflicted, so the document is re-emitted
+    assert_eq!(result.content, expected);
+}
+
+#[test]
+fn conflicts_are_reported_per_key_path() {
+    let ours = BASE.replace("image: web:1.0", "image: web:1.1");
+    let theirs = BASE.replace("image: web:1.0", "image: web:2.0");
+
+    let result = merge_yaml(BASE, &ours, &theirs).unwrap();
+    assert_eq!(result.conflicts.len(), 1);
+    let conflict = &result.conflicts[0];
+    assert_eq!(
+        conflict.path,
+        ["spec", "template", "spec", "containers", "0", "image"]
+    );
+    assert_eq!(conflict.base.as_deref(), Some("\"web:1.0\""));
+    assert_eq!(conflict.ours.as_deref(), Some("\"web:1.1\""));
+    assert_eq!(conflict.theirs.as_deref(), Some("\"web:2.0\""));
+    assert!(result.content.contains("image: \"web:1.1\""));
+}
+
+#[test]
+fn sequences_of_different_lengths_conflict() {
+    let ours = BASE.replace("        - name: sidecar\n          image: proxy:1.0\n", "");
+    let theirs = BASE.replace("image: web:1.0", "image: web:2.0");
+
+    let result = merge_yaml(BASE, &ours, &theirs).unwrap();
+    assert_eq!(result.conflicts.len(), 1);
+    assert_eq!(
+        result.conflicts[0].path,
+        ["spec", "template", "spec", "containers"]
+    );
+}
+
+#[test]
+fn multiline_strings_use_line_merge() {
+    let base = "script: |\n  one\n  two\n  three\n  four\nname: job\n";
+    let ours = "script: |\n  ONE\n  two\n  three\n  four\nname: job-a\n";
+    let theirs = "script: |\n  one\n  two\n  three\n  FOUR\nname: job\nretries: 2\n";
+
+    let result = merge_yaml(base, ours, theirs).unwrap();
+    assert!(result.is_clean_merge());
+    assert_eq!(
+        result.content,
+        "script: |\n  ONE\n  two\n  three\n  FOUR\nname: job-a\nretries: 2\n"
+    );
+}
+
+#[test]
+fn merges_multi_document_streams() {
+    let base = "---\nkind: Service\nport: 80\n---\nkind: Deployment\nreplicas: 1\n";
+    let ours = base.replace("port: 80", "port: 8080");
+    let theirs = base.replace("replicas: 1", "replicas: 2");
+
+    let result = merge_yaml(base, &ours, &theirs).unwrap();
+    assert!(result.is_clean_merge());
+    assert_eq!(
+        result.content,
+        "---\nkind: Service\nport: 8080\n---\nkind: Deployment\nreplicas: 2\n"
+    );
+
+    let theirs = base.replace("port: 80", "port: 443");
+    let result = merge_yaml(base, &ours, &theirs).unwrap();
+    assert_eq!(result.conflicts[0].path, ["0", "port"]);
+}
+
+#[test]
+fn rejects_invalid_yaml() {
+    let err = merge_yaml(BASE, "a: [1", BASE).unwrap_err();
+    assert!(matches!(err, MergeError::InvalidInput(msg) if msg.starts_with("ours")));
+}
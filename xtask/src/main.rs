@@ -1,3 +1,4 @@
+use std::path::Path;
 use std::process::{Command, exit};
 use std::thread::sleep;
 use std::time::Duration;
@@ -10,12 +11,16 @@ fn main() {
     match args.get(1).map(|s| s.as_str()) {
         Some("publish") => publish(false),
         Some("publish-dry") => publish(true),
+        Some("capi-header") => capi_header(args.get(2).map(|s| s.as_str()) == Some("--check")),
         _ => {
             eprintln!("Usage: cargo xtask <command>");
             eprintln!();
             eprintln!("Commands:");
             eprintln!("  publish      Publish crates to crates.io");
             eprintln!("  publish-dry  Dry run publish");
+            eprintln!(
+                "  capi-header  Regenerate include/threeway_merge.h (--check: verify it is current)"
+            );
             exit(1);
         }
     }
@@ -51,3 +56,42 @@ fn publish(dry_run: bool) {
 
     println!("All crates published!");
 }
+
+fn capi_header(check: bool) {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+    let header_path = root.join("include/threeway_merge.h");
+
+    let config = cbindgen::Config {
+        language: cbindgen::Language::C,
+        header: Some("/* Generated by `cargo xtask capi-header` from src/capi.rs. */".to_string()),
+        include_guard: Some("THREEWAY_MERGE_H".to_string()),
+        cpp_compat: true,
+        usize_is_size_t: true,
+        documentation_style: cbindgen::DocumentationStyle::C,
+        ..Default::default()
+    };
+    let bindings = cbindgen::Builder::new()
+        .with_config(config)
+        .with_src(root.join("src/capi.rs"))
+        .generate()
+        .expect("Failed to generate the C header");
+
+    let mut header = Vec::new();
+    bindings.write(&mut header);
+
+    if check {
+        let current = std::fs::read(&header_path).unwrap_or_default();
+        if current != header {
+            eprintln!(
+                "{} is out of date; run `cargo xtask capi-header`",
+                header_path.display()
+            );
+            exit(1);
+        }
+        println!("{} is up to date", header_path.display());
+    } else {
+        std::fs::create_dir_all(header_path.parent().unwrap()).expect("Failed to create include/");
+        std::fs::write(&header_path, header).expect("Failed to write the C header");
+        println!("Wrote {}", header_path.display());
+    }
+}